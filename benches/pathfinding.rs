@@ -0,0 +1,65 @@
+//! Rustacean pathfinding on small and large boards, `cargo bench --bench pathfinding --features bench-reference` runs it.
+//!
+//! The searches over flat arrays run next to the hashed ones they replaced, the `hashed` rows.
+
+use std::{collections::HashSet, hint::black_box};
+
+use bevy::ecs::entity::Entity;
+use criterion::{criterion_group, criterion_main, Criterion};
+use trap_the_tiger_rs::{game::{difficulty::RustaceanAi, hashed_pathfinding::{self, HashedBoard}, pathfinding::{choose_move, choose_move_or_jump, pathfind, BoardView, RustaceanBoard, TileSet}, solver::greedy_tap, tiles::{TileKind, TileKinds}}, utils::{hexgrid_utils::GridSize, rng_utils::{random_range, seed_game_rng}}};
+
+/// A board with about `trap_share` of its tiles trapped at random, the center kept free for the rustacean
+fn board(cols: i32, rows: i32, trap_share: f64) -> (GridSize, TileSet, (i32, i32)) {
+    seed_game_rng(7);
+    let grid_size = GridSize::new(cols, rows);
+    let center = (cols / 2, rows / 2);
+    let open_tiles = TileSet::from_tiles(grid_size, (0..cols)
+        .flat_map(|x| (0..rows).map(move |y| (x, y)))
+        .filter(|pos| *pos == center || random_range(0.0..1.0) >= trap_share));
+    (grid_size, open_tiles, center)
+}
+
+fn bench_pathfinding(c: &mut Criterion) {
+    let tiles = TileKinds::new([]);
+
+    for (cols, rows) in [(11, 11), (30, 30), (100, 100)] {
+        let (grid_size, mut open_tiles, start) = board(cols, rows, 0.15);
+        open_tiles.remove(&start);
+        let board = BoardView { grid_size, open_tiles: &open_tiles, tiles: &tiles };
+
+        let open: HashSet<(i32, i32)> = open_tiles.iter().collect();
+        let hashed = HashedBoard { grid_size, open_tiles: &open, tiles: &tiles };
+
+        c.bench_function(&format!("pathfind {cols}x{rows}"), |b| b.iter(|| pathfind(black_box(start), &board)));
+        c.bench_function(&format!("hashed pathfind {cols}x{rows}"), |b| b.iter(|| hashed_pathfinding::pathfind(black_box(start), &hashed)));
+        c.bench_function(&format!("strategist move {cols}x{rows}"), |b| {
+            b.iter(|| choose_move(RustaceanAi::Strategist, black_box(start), &board))
+        });
+    }
+
+    // a rustacean step in the game gathers the board from the tiles once, then picks and checks its move on it
+    for (cols, rows) in [(11, 11), (100, 100)] {
+        let (grid_size, open_tiles, start) = board(cols, rows, 0.15);
+        let tile_states: Vec<((i32, i32), bool, Option<TileKind>, bool)> = (0..cols)
+            .flat_map(|x| (0..rows).map(move |y| (x, y)))
+            .map(|pos| (pos, !open_tiles.contains(&pos), None, false))
+            .collect();
+
+        c.bench_function(&format!("rustacean step {cols}x{rows}"), |b| b.iter(|| {
+            let board = RustaceanBoard::new(grid_size, tile_states.iter().copied(), vec![(Entity::PLACEHOLDER, black_box(start), None)]);
+            choose_move_or_jump(RustaceanAi::Strategist, start, &board.view(), &board.jumps(0))
+        }));
+    }
+
+    // every candidate tap runs a search of its own
+    for (cols, rows) in [(11, 11), (30, 30)] {
+        let (grid_size, mut open_tiles, start) = board(cols, rows, 0.15);
+        open_tiles.remove(&start);
+        let board = BoardView { grid_size, open_tiles: &open_tiles, tiles: &tiles };
+
+        c.bench_function(&format!("greedy tap {cols}x{rows}"), |b| b.iter(|| greedy_tap(&board, black_box(start))));
+    }
+}
+
+criterion_group!(benches, bench_pathfinding);
+criterion_main!(benches);
@@ -0,0 +1,36 @@
+//! Plays classic games without a window, for tuning the trap formula and checking rustacean AI changes
+
+use trap_the_tiger_rs::{game::{simulation::{run_simulation, SimConfig, SimFormat, SIMULATE_USAGE}, strategy::StrategyRegistry}, settings::launch::set_data_dir};
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{SIMULATE_USAGE}");
+        return;
+    }
+
+    let config = SimConfig::parse(args).unwrap_or_else(|err| {
+        eprintln!("Error: {err}\n\n{SIMULATE_USAGE}");
+        std::process::exit(2);
+    });
+    if let Some(data_dir) = &config.data_dir {
+        set_data_dir(data_dir.clone());
+    }
+
+    let report = run_simulation(&config, &StrategyRegistry::default()).unwrap_or_else(|err| {
+        eprintln!("Error: {err}\n\n{SIMULATE_USAGE}");
+        std::process::exit(2);
+    });
+    let text = match config.format {
+        SimFormat::Csv => report.to_csv(),
+        SimFormat::Json => report.to_json(),
+    };
+
+    match &config.output {
+        Some(path) => std::fs::write(path, text).unwrap_or_else(|err| {
+            eprintln!("Error: could not write {}: {err}", path.display());
+            std::process::exit(1);
+        }),
+        None => print!("{text}"),
+    }
+}
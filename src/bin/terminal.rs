@@ -0,0 +1,26 @@
+//! Plays the game in a terminal, over SSH or with commands piped in by a script
+
+use std::io;
+
+use trap_the_tiger_rs::{game::strategy::StrategyRegistry, settings::launch::set_data_dir, terminal::terminal::{run_terminal, TerminalOptions, TERMINAL_USAGE}};
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{TERMINAL_USAGE}");
+        return;
+    }
+
+    let options = TerminalOptions::parse(args).unwrap_or_else(|err| {
+        eprintln!("Error: {err}\n\n{TERMINAL_USAGE}");
+        std::process::exit(2);
+    });
+    if let Some(data_dir) = &options.data_dir {
+        set_data_dir(data_dir.clone());
+    }
+
+    if let Err(err) = run_terminal(&options, &StrategyRegistry::default(), io::stdin().lock(), io::stdout().lock()) {
+        eprintln!("Error: {err}");
+        std::process::exit(1);
+    }
+}
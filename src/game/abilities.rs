@@ -0,0 +1,332 @@
+use bevy::{color::Alpha, ecs::{component::Component, entity::Entity, event::{Event, EventReader, EventWriter}, change_detection::DetectChanges, hierarchy::Children, query::{Changed, Has, With}, resource::Resource, system::{Commands, Query, Res, ResMut, Single}, world::World}, input::{keyboard::KeyCode, ButtonInput}, log::debug, state::state::State, text::TextColor, ui::{widget::{Button, Text}, AlignItems, BackgroundColor, Display, FlexDirection, Interaction, JustifyContent, Node, PositionType, UiRect, Val}, utils::default};
+use crate::{game::{endless::LevelRules, events::{AbilityUsed, TrapPlaced}, level_setup::RustaceanPos, levels::{spawn_hud_node, TurnState}, pathfinding::{pathfind, BoardView, Path, RustaceanMover}, rustaceans::RustaceanTurnProgress}, network::session::NetSession, settings::{locale::Localization, theme::Theme}, utils::hexgrid_utils::GridTilePos};
+
+/// Once per level abilities of the trapper
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum PlayerAbility {
+    /// The rustaceans sit out their next turn
+    Freeze,
+    /// The next trap doesnt end the turn, so two go down before the rustaceans move
+    DoubleTrap,
+    /// Shows the way each rustacean would take to get out, until the turn ends
+    RevealPath,
+}
+
+impl PlayerAbility {
+    /// In HUD order
+    pub const ALL: [PlayerAbility; 3] = [
+        PlayerAbility::Freeze,
+        PlayerAbility::DoubleTrap,
+        PlayerAbility::RevealPath,
+    ];
+
+    /// Localization key of the ability's display name
+    pub fn key(self) -> &'static str {
+        match self {
+            PlayerAbility::Freeze => "ability.freeze",
+            PlayerAbility::DoubleTrap => "ability.double_trap",
+            PlayerAbility::RevealPath => "ability.reveal_path",
+        }
+    }
+
+    /// Key that uses the ability, shown on its button
+    pub fn key_code(self) -> (KeyCode, &'static str) {
+        match self {
+            PlayerAbility::Freeze => (KeyCode::KeyF, "F"),
+            PlayerAbility::DoubleTrap => (KeyCode::KeyT, "T"),
+            PlayerAbility::RevealPath => (KeyCode::KeyR, "R"),
+        }
+    }
+
+    /// Rustacean turns that have to pass in a level before the ability can be used
+    pub fn cooldown(self) -> u32 {
+        match self {
+            PlayerAbility::Freeze => 3,
+            PlayerAbility::DoubleTrap => 2,
+            PlayerAbility::RevealPath => 1,
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            PlayerAbility::Freeze => 0,
+            PlayerAbility::DoubleTrap => 1,
+            PlayerAbility::RevealPath => 2,
+        }
+    }
+}
+
+/// Where an ability stands this level
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AbilityCharge {
+    /// Rustacean turns left before it can be used
+    Cooling(u32),
+    Ready,
+    Used,
+}
+
+/// The trapper's abilities for the current level
+#[derive(Resource, Debug)]
+pub struct PlayerAbilities {
+    charges: [AbilityCharge; 3],
+    /// Puzzles and online clients dont get any abilities
+    pub offered: bool,
+    /// Traps that can still go down this turn before the rustaceans move
+    pub extra_traps: u32,
+    /// Whether the rustaceans' ways out are shown this turn
+    pub revealing: bool,
+}
+
+impl Default for PlayerAbilities {
+    fn default() -> Self {
+        PlayerAbilities {
+            charges: PlayerAbility::ALL.map(|ability| match ability.cooldown() {
+                0 => AbilityCharge::Ready,
+                turns => AbilityCharge::Cooling(turns),
+            }),
+            offered: false,
+            extra_traps: 0,
+            revealing: false,
+        }
+    }
+}
+
+impl PlayerAbilities {
+    pub fn charge(&self, ability: PlayerAbility) -> AbilityCharge {
+        self.charges[ability.index()]
+    }
+
+    /// Counts every cooling ability down by one rustacean turn
+    pub fn tick(&mut self) {
+        for charge in self.charges.iter_mut() {
+            if let AbilityCharge::Cooling(turns) = charge {
+                *charge = match *turns {
+                    0 | 1 => AbilityCharge::Ready,
+                    turns => AbilityCharge::Cooling(turns - 1),
+                };
+            }
+        }
+    }
+
+    /// Uses up the ability if it is ready
+    pub fn take(&mut self, ability: PlayerAbility) -> bool {
+        let charge = &mut self.charges[ability.index()];
+        if *charge != AbilityCharge::Ready {
+            return false;
+        }
+        *charge = AbilityCharge::Used;
+        true
+    }
+}
+
+pub fn reset_player_abilities(
+    mut commands: Commands,
+    rules: Res<LevelRules>,
+    session: Option<Res<NetSession>>,
+) {
+    // puzzles are solved with their taps alone, and online only the host traps
+    let offered = rules.puzzle.is_none() && !session.is_some_and(|session| session.is_client());
+    commands.insert_resource(PlayerAbilities { offered, ..default() });
+}
+
+pub fn tick_ability_cooldowns(
+    mut abilities: ResMut<PlayerAbilities>,
+) {
+    abilities.tick();
+}
+
+/// Button of the ability bar that uses an ability
+#[derive(Component)]
+pub struct AbilityButton(pub PlayerAbility);
+
+/// Row of ability buttons at the bottom of the level HUD
+#[derive(Component)]
+pub struct AbilityBar;
+
+/// Asks for an ability to be used the same way its key does, sent by replays
+#[derive(Event, Clone, Copy, Debug)]
+pub struct AbilityPicked(pub PlayerAbility);
+
+/// Uses abilities picked on the ability bar, with their keys or by a replay
+pub fn use_player_abilities(
+    keys: Res<ButtonInput<KeyCode>>,
+    button_q: Query<(&Interaction, &AbilityButton), (Changed<Interaction>, With<Button>)>,
+    mut picked_events: EventReader<AbilityPicked>,
+    turn_state: Res<State<TurnState>>,
+    session: Option<Res<NetSession>>,
+    rustacean_q: Query<Entity, With<RustaceanPos>>,
+    mut abilities: ResMut<PlayerAbilities>,
+    mut progress: ResMut<RustaceanTurnProgress>,
+    mut ability_events: EventWriter<AbilityUsed>,
+) {
+    let mut picked: Vec<PlayerAbility> = PlayerAbility::ALL.into_iter()
+        .filter(|ability| keys.just_pressed(ability.key_code().0))
+        .collect();
+    picked.extend(button_q.iter()
+        .filter(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, button)| button.0));
+    picked.extend(picked_events.read().map(|event| event.0));
+
+    // the same rules as for placing a trap
+    let waiting_online = session.is_some_and(|session| session.is_client() || !session.connected);
+    if picked.is_empty() || !abilities.offered || *turn_state != TurnState::PlayerTurn || waiting_online {
+        return;
+    }
+
+    for ability in picked {
+        if !abilities.take(ability) {
+            continue;
+        }
+        debug!("abilities: used {:?}", ability);
+        ability_events.write(AbilityUsed { ability });
+
+        match ability {
+            // counted as moved, so the next rustacean turn ends right away
+            PlayerAbility::Freeze => {
+                for entity in rustacean_q.iter() {
+                    if !progress.moved.contains(&entity) {
+                        progress.moved.push(entity);
+                    }
+                }
+            }
+            PlayerAbility::DoubleTrap => abilities.extra_traps += 1,
+            PlayerAbility::RevealPath => abilities.revealing = true,
+        }
+    }
+}
+
+/// Tile on the way out of a rustacean, while the path is revealed
+#[derive(Component)]
+pub struct RevealedPath;
+
+/// Marks the cheapest way out of every rustacean, again whenever a trap goes down
+pub fn reveal_rustacean_paths(
+    mut commands: Commands,
+    abilities: Res<PlayerAbilities>,
+    mut trap_events: EventReader<TrapPlaced>,
+    mover: RustaceanMover,
+    tile_q: Query<(Entity, &GridTilePos, Has<RevealedPath>)>,
+) {
+    let traps_placed = trap_events.read().count() > 0;
+    if !abilities.revealing || !(abilities.is_changed() || traps_placed) {
+        return;
+    }
+
+    let (grid_size, open_tiles, tiles) = (mover.grid_size(), mover.open_tiles(), mover.tiles());
+    let board = BoardView { grid_size, open_tiles: &open_tiles, tiles: &tiles };
+    let path_tiles: Vec<(i32, i32)> = mover.rustaceans().into_iter()
+        .filter(|(_, pos)| !tiles.escaped(*pos, &grid_size))
+        .flat_map(|(_, pos)| match pathfind(pos, &board) {
+            Path::Found(path) => path,
+            Path::NotFound | Path::Escaped(..) => Vec::new(),
+        })
+        .collect();
+    debug!("abilities: revealing {} tiles", path_tiles.len());
+
+    for (entity, pos, is_revealed) in tile_q.iter() {
+        let on_path = path_tiles.contains(&(pos.x, pos.y));
+        if on_path && !is_revealed {
+            commands.entity(entity).insert(RevealedPath);
+        } else if !on_path && is_revealed {
+            commands.entity(entity).remove::<RevealedPath>();
+        }
+    }
+}
+
+/// Hides the revealed paths once the player's turn is over
+pub fn hide_rustacean_paths(
+    mut commands: Commands,
+    mut abilities: ResMut<PlayerAbilities>,
+    tile_q: Query<Entity, With<RevealedPath>>,
+) {
+    abilities.revealing = false;
+    for entity in tile_q.iter() {
+        commands.entity(entity).remove::<RevealedPath>();
+    }
+}
+
+pub fn spawn_ability_bar(
+    world: &mut World,
+) {
+    let theme = *world.resource::<Theme>();
+    let font = world.resource::<Localization>().text_font(28.0);
+
+    spawn_hud_node(world, (
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(20.0),
+            left: Val::Px(20.0),
+            flex_direction: FlexDirection::Row,
+            ..default()
+        },
+        AbilityBar,
+    )).with_children(|bar| {
+        for ability in PlayerAbility::ALL {
+            bar.spawn((
+                Button,
+                Node {
+                    width: Val::Px(260.0),
+                    height: Val::Px(64.0),
+                    margin: UiRect::right(Val::Px(12.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                BackgroundColor(theme.button_normal),
+                AbilityButton(ability),
+            )).with_child((
+                Text::default(),
+                font.clone(),
+                TextColor(theme.menu_text),
+            ));
+        }
+    });
+}
+
+/// Shows the ability bar only when the level has abilities, and what is left of each
+pub fn ability_bar_update(
+    abilities: Res<PlayerAbilities>,
+    localization: Res<Localization>,
+    theme: Res<Theme>,
+    bar: Single<&mut Node, With<AbilityBar>>,
+    mut button_q: Query<(&AbilityButton, &Interaction, &mut BackgroundColor, &Children)>,
+    mut text_q: Query<&mut Text>,
+) {
+    let display = if abilities.offered { Display::Flex } else { Display::None };
+    let mut bar = bar.into_inner();
+    if bar.display != display {
+        bar.display = display;
+    }
+
+    for (button, interaction, mut background, children) in button_q.iter_mut() {
+        let ability = button.0;
+        let charge = abilities.charge(ability);
+        let status = match charge {
+            AbilityCharge::Ready => localization.text("ability.ready"),
+            AbilityCharge::Cooling(turns) => localization.plural("ability.cooling", turns as u64),
+            AbilityCharge::Used => localization.text("ability.used"),
+        };
+        let label = localization.format("ability.button", &[
+            ("key", ability.key_code().1),
+            ("name", &localization.text(ability.key())),
+            ("status", &status),
+        ]);
+
+        let color = match (charge, interaction) {
+            (AbilityCharge::Ready, Interaction::Hovered | Interaction::Pressed) => theme.button_hovered,
+            (AbilityCharge::Ready, Interaction::None) => theme.button_normal,
+            // greyed out until it can be used
+            (AbilityCharge::Cooling(_) | AbilityCharge::Used, _) => theme.button_normal.with_alpha(0.4),
+        };
+        if background.0 != color {
+            background.0 = color;
+        }
+
+        let mut texts = text_q.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            if text.0 != label {
+                text.0 = label.clone();
+            }
+        }
+    }
+}
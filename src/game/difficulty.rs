@@ -0,0 +1,319 @@
+
+use bevy::{ecs::{resource::Resource, system::{Commands, Res}}, log::warn};
+use crate::{game::rustaceans::RustaceanAbility, settings::{launch::{data_path, ensure_data_dir, LaunchOptions}, settings::Settings}, utils::hexgrid_utils::GridSize};
+
+/// Difficulty presets selectable from the main menu, each backed by a profile in difficulty.json in the data directory
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum DifficultyKind {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    /// Starts as a copy of normal, meant to be edited by hand in the config file
+    Custom,
+}
+
+impl DifficultyKind {
+    pub const ALL: [DifficultyKind; 4] = [
+        DifficultyKind::Easy,
+        DifficultyKind::Normal,
+        DifficultyKind::Hard,
+        DifficultyKind::Custom,
+    ];
+
+    /// Localization key of the difficulty's display name
+    pub fn key(self) -> &'static str {
+        match self {
+            DifficultyKind::Easy => "difficulty.easy",
+            DifficultyKind::Normal => "difficulty.normal",
+            DifficultyKind::Hard => "difficulty.hard",
+            DifficultyKind::Custom => "difficulty.custom",
+        }
+    }
+
+    /// The difficulty after this one, wrapping around, used by the main menu button
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|kind| *kind == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// How the number of starting traps shrinks as the levels go up
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum DecayCurve {
+    /// Removes `per_level` traps for every level after the first
+    Linear { per_level: f32 },
+    /// Multiplies the trap count by `factor` for every level after the first
+    Exponential { factor: f32 },
+}
+
+/// How clever the rustacean is when picking its next tile, from least to most clever
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+pub enum RustaceanAi {
+    /// Hops to a random open neighbor, only leaving the board when standing on its edge
+    Wanderer,
+    /// Hops to the open neighbor closest to the board edge without looking further ahead
+    Greedy,
+    /// Follows the shortest open path to the edge
+    #[default]
+    Pathfinder,
+    /// Follows a shortest path, preferring the tiles with the most ways out so single traps cant cut it off
+    Strategist,
+}
+
+impl RustaceanAi {
+    pub const ALL: [RustaceanAi; 4] = [
+        RustaceanAi::Wanderer,
+        RustaceanAi::Greedy,
+        RustaceanAi::Pathfinder,
+        RustaceanAi::Strategist,
+    ];
+
+    /// Name the tier is registered and picked by on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            RustaceanAi::Wanderer => "wanderer",
+            RustaceanAi::Greedy => "greedy",
+            RustaceanAi::Pathfinder => "pathfinder",
+            RustaceanAi::Strategist => "strategist",
+        }
+    }
+}
+
+/// From `from_level` on, levels are played on a `cols` x `rows` board
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub struct GridStep {
+    pub from_level: u32,
+    pub cols: i32,
+    pub rows: i32,
+}
+
+/// How many special tiles a generated board gets
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SpecialTileCounts {
+    pub rocks: u32,
+    pub mud: u32,
+    pub teleporter_pairs: u32,
+    pub one_way: u32,
+    /// Any exit closes the board edge, the rustacean can then only leave through one
+    pub exits: u32,
+}
+
+/// Limited traps the player gets at the start of every level, on top of the unlimited regular ones
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct TrapInventoryCounts {
+    pub decaying: u32,
+    pub reinforced: u32,
+    pub area: u32,
+}
+
+impl TrapInventoryCounts {
+    pub const NONE: TrapInventoryCounts = TrapInventoryCounts { decaying: 0, reinforced: 0, area: 0 };
+}
+
+impl Default for TrapInventoryCounts {
+    fn default() -> Self {
+        TrapInventoryCounts { decaying: 2, reinforced: 1, area: 1 }
+    }
+}
+
+/// First level each rustacean ability shows up on, abilities without one never do
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct RustaceanAbilityLevels {
+    pub jumper: Option<u32>,
+    pub sprinter: Option<u32>,
+}
+
+impl RustaceanAbilityLevels {
+    /// Abilities a rustacean on `level` may have
+    pub fn unlocked(&self, level: u32) -> Vec<RustaceanAbility> {
+        [(RustaceanAbility::Jumper, self.jumper), (RustaceanAbility::Sprinter, self.sprinter)].into_iter()
+            .filter(|(_, from_level)| from_level.is_some_and(|from_level| from_level <= level))
+            .map(|(ability, _)| ability)
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct DifficultyProfile {
+    /// Share of the tiles that start trapped on the first level
+    pub initial_trap_density: f32,
+    pub decay_curve: DecayCurve,
+    /// The trap count never decays below this
+    pub min_traps: u32,
+    /// Board sizes by level, the first step is the starting board
+    pub grid_growth: Vec<GridStep>,
+    pub rustacean_ai: RustaceanAi,
+    /// Missing from profiles written before special tiles existed, those get plain boards
+    #[serde(default)]
+    pub special_tiles: SpecialTileCounts,
+    #[serde(default)]
+    pub trap_inventory: TrapInventoryCounts,
+    #[serde(default)]
+    pub rustacean_abilities: RustaceanAbilityLevels,
+}
+
+impl DifficultyProfile {
+    /// Number of traps placed at the start of `level` on a board of `tile_count` tiles
+    pub fn trap_count(&self, level: u32, tile_count: usize) -> usize {
+        let initial = tile_count as f32 * self.initial_trap_density;
+        let levels_passed = level.saturating_sub(1) as f32;
+
+        let traps = match self.decay_curve {
+            DecayCurve::Linear { per_level } => initial.round() - per_level * levels_passed,
+            DecayCurve::Exponential { factor } => initial * factor.powf(levels_passed),
+        };
+
+        // leave plenty of room for the rustacean and an open neighbor, whatever the config says
+        let max_traps = tile_count / 2;
+        (traps.round().max(0.0) as usize)
+            .max(self.min_traps as usize)
+            .min(max_traps)
+    }
+
+    /// Board size used for `level`
+    pub fn grid_size(&self, level: u32) -> GridSize {
+        let step = self.grid_growth.iter()
+            .filter(|step| step.from_level <= level)
+            .max_by_key(|step| step.from_level);
+
+        match step {
+            // anything smaller than 3x3 has no tile that isnt on the border
+            Some(step) => GridSize::new(step.cols.max(3), step.rows.max(3)),
+            None => GridSize::new(DEFAULT_GRID_COLS, DEFAULT_GRID_ROWS),
+        }
+    }
+}
+
+const DEFAULT_GRID_COLS: i32 = 7;
+const DEFAULT_GRID_ROWS: i32 = 12;
+
+/// Every difficulty profile, persisted to difficulty.json in the data directory
+#[derive(Resource, Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct DifficultyProfiles {
+    pub easy: DifficultyProfile,
+    pub normal: DifficultyProfile,
+    pub hard: DifficultyProfile,
+    pub custom: DifficultyProfile,
+}
+
+impl Default for DifficultyProfiles {
+    fn default() -> Self {
+        let base_grid = GridStep { from_level: 1, cols: DEFAULT_GRID_COLS, rows: DEFAULT_GRID_ROWS };
+
+        // matches the original formula: round(tiles * 0.25974) - min(level - 1, 20) on a 7x12 board
+        let normal = DifficultyProfile {
+            initial_trap_density: 0.25974,
+            decay_curve: DecayCurve::Linear { per_level: 1.0 },
+            min_traps: 2,
+            grid_growth: vec![base_grid],
+            rustacean_ai: RustaceanAi::Pathfinder,
+            special_tiles: SpecialTileCounts::default(),
+            trap_inventory: TrapInventoryCounts::NONE,
+            rustacean_abilities: RustaceanAbilityLevels::default(),
+        };
+
+        DifficultyProfiles {
+            easy: DifficultyProfile {
+                initial_trap_density: 0.32,
+                decay_curve: DecayCurve::Linear { per_level: 0.5 },
+                min_traps: 8,
+                grid_growth: vec![base_grid],
+                rustacean_ai: RustaceanAi::Greedy,
+                special_tiles: SpecialTileCounts::default(),
+                trap_inventory: TrapInventoryCounts { decaying: 3, reinforced: 2, area: 2 },
+                rustacean_abilities: RustaceanAbilityLevels::default(),
+            },
+            hard: DifficultyProfile {
+                initial_trap_density: 0.2,
+                decay_curve: DecayCurve::Exponential { factor: 0.9 },
+                min_traps: 0,
+                grid_growth: vec![
+                    base_grid,
+                    GridStep { from_level: 8, cols: 9, rows: 12 },
+                    GridStep { from_level: 16, cols: 11, rows: 13 },
+                ],
+                rustacean_ai: RustaceanAi::Pathfinder,
+                special_tiles: SpecialTileCounts { rocks: 3, mud: 2, teleporter_pairs: 1, one_way: 2, exits: 0 },
+                trap_inventory: TrapInventoryCounts { decaying: 1, reinforced: 1, area: 0 },
+                rustacean_abilities: RustaceanAbilityLevels { jumper: Some(5), sprinter: Some(8) },
+            },
+            // a copy of Normal with a few special tiles and rustacean variants to start tweaking from
+            custom: DifficultyProfile {
+                special_tiles: SpecialTileCounts { rocks: 2, mud: 2, ..Default::default() },
+                rustacean_abilities: RustaceanAbilityLevels { jumper: Some(10), sprinter: Some(15) },
+                ..normal.clone()
+            },
+            normal,
+        }
+    }
+}
+
+impl DifficultyProfiles {
+    pub fn get(&self, kind: DifficultyKind) -> &DifficultyProfile {
+        match kind {
+            DifficultyKind::Easy => &self.easy,
+            DifficultyKind::Normal => &self.normal,
+            DifficultyKind::Hard => &self.hard,
+            DifficultyKind::Custom => &self.custom,
+        }
+    }
+}
+
+/// The difficulty the current game was started with, changing the setting mid game has no effect
+#[derive(Resource, Clone, Debug)]
+pub struct ActiveDifficulty {
+    pub kind: DifficultyKind,
+    pub profile: DifficultyProfile,
+}
+
+pub fn setup_difficulty_profiles(
+    mut commands: Commands,
+) {
+    commands.insert_resource(load_difficulty_profiles());
+}
+
+/// Reads difficulty.json, writing the default profiles out first when there is none yet
+pub fn load_difficulty_profiles() -> DifficultyProfiles {
+    let profiles_path = data_path("difficulty.json");
+
+    if profiles_path.exists() {
+        let json = std::fs::read_to_string(&profiles_path)
+            .expect("Error: Path must exist\nContents of the file must be valid utf8");
+
+        serde_json::from_str(&json).unwrap_or_else(|err| {
+            warn!("Could not parse {}, using default profiles: {err}", profiles_path.display());
+            DifficultyProfiles::default()
+        })
+    } else {
+        // write out the defaults so there is a file to tweak
+        let profiles = DifficultyProfiles::default();
+
+        ensure_data_dir();
+
+        let json = serde_json::to_string_pretty(&profiles)
+            .expect("Error: Implementation of Serialize must not decide to fail\nT should contain a map with string keys");
+
+        std::fs::write(&profiles_path, json)
+            .expect("Error: The data directory must exist");
+
+        profiles
+    }
+}
+
+pub fn select_difficulty(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    profiles: Res<DifficultyProfiles>,
+    options: Res<LaunchOptions>,
+) {
+    let kind = options.difficulty.unwrap_or(settings.difficulty);
+    commands.insert_resource(ActiveDifficulty {
+        kind,
+        profile: profiles.get(kind).clone(),
+    });
+}
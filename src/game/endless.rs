@@ -0,0 +1,227 @@
+use bevy::{ecs::{component::Component, query::With, resource::Resource, system::{Commands, Query, Res}}, log::{debug, warn}, text::TextSpan};
+use crate::{game::{difficulty::{ActiveDifficulty, DifficultyProfile, RustaceanAi, SpecialTileCounts, TrapInventoryCounts}, game::CurrentLevel, game_mode::{ActiveGameMode, GameMode}, hotseat::HotseatMatch, puzzle::{Puzzle, PuzzleBook}, rustaceans::{random_rustacean_abilities, RustaceanAbility, WinCondition, MAX_RUSTACEANS}, tiles::SpecialTile}, settings::{launch::LaunchOptions, locale::Localization, settings::Settings}, utils::hexgrid_utils::GridSize};
+
+/// Last level of the regular progression, every level after it is part of endless mode
+pub const ENDLESS_START_LEVEL: u32 = 21;
+/// Endless levels per tier, every tier grows the board and may unlock a modifier
+const LEVELS_PER_TIER: u32 = 5;
+/// Largest board endless mode grows to
+const MAX_ENDLESS_COLS: i32 = 15;
+const MAX_ENDLESS_ROWS: i32 = 18;
+
+/// Extra rules switched on at endless milestones
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EndlessModifier {
+    /// The rustacean starts one hop away from the center
+    HeadStart,
+    /// No traps are placed on the border at the start of the level
+    OpenEdges,
+    /// One trap fewer for every tier past the milestone, ignoring the profile's minimum
+    SparseTraps,
+}
+
+impl EndlessModifier {
+    /// Tier from which the modifier is active
+    fn unlock_tier(self) -> u32 {
+        match self {
+            EndlessModifier::HeadStart => 2,
+            EndlessModifier::OpenEdges => 4,
+            EndlessModifier::SparseTraps => 6,
+        }
+    }
+
+    /// Localization key of the modifier's HUD label
+    pub fn key(self) -> &'static str {
+        match self {
+            EndlessModifier::HeadStart => "endless.head_start",
+            EndlessModifier::OpenEdges => "endless.open_edges",
+            EndlessModifier::SparseTraps => "endless.sparse_traps",
+        }
+    }
+}
+
+/// How far into endless mode `level` is, 0 for the regular levels
+pub fn endless_tier(level: u32) -> u32 {
+    level.saturating_sub(ENDLESS_START_LEVEL).div_ceil(LEVELS_PER_TIER)
+}
+
+/// Everything that decides how the current level is set up and played,
+/// worked out from the difficulty profile and endless mode when the level starts
+#[derive(Resource, Clone, Debug)]
+pub struct LevelRules {
+    pub grid_size: GridSize,
+    pub trap_count: usize,
+    pub rustacean_ai: RustaceanAi,
+    pub rustacean_count: usize,
+    pub win_condition: WinCondition,
+    /// Special tiles of a generated board
+    pub special_tiles: SpecialTileCounts,
+    /// Limited traps handed out at the start of the level
+    pub trap_inventory: TrapInventoryCounts,
+    /// Ability of every rustacean on a generated board, in turn order
+    pub rustacean_abilities: Vec<Option<RustaceanAbility>>,
+    pub endless_tier: u32,
+    pub modifiers: Vec<EndlessModifier>,
+    /// Set in puzzle mode, for the tap budget and star rating
+    pub puzzle: Option<Puzzle>,
+    /// Rustacean starts, traps and special tiles of a board that is laid out ahead of time instead of at random
+    pub fixed_board: Option<FixedBoard>,
+}
+
+#[derive(Clone, Debug)]
+pub struct FixedBoard {
+    /// Start of every rustacean, in turn order
+    pub rustaceans: Vec<(i32, i32)>,
+    pub traps: Vec<(i32, i32)>,
+    pub special_tiles: Vec<SpecialTile>,
+    /// Ability of every rustacean, in turn order
+    pub abilities: Vec<Option<RustaceanAbility>>,
+}
+
+impl LevelRules {
+    pub fn has(&self, modifier: EndlessModifier) -> bool {
+        self.modifiers.contains(&modifier)
+    }
+}
+
+pub fn prepare_level_rules(
+    mut commands: Commands,
+    current_level: Res<CurrentLevel>,
+    difficulty: Res<ActiveDifficulty>,
+    game_mode: Res<ActiveGameMode>,
+    puzzle_book: Res<PuzzleBook>,
+    hotseat: Option<Res<HotseatMatch>>,
+    settings: Res<Settings>,
+    options: Res<LaunchOptions>,
+) {
+    let level = current_level.0;
+
+    if game_mode.0 == GameMode::Puzzle {
+        match puzzle_book.for_level(level) {
+            Some(puzzle) => {
+                debug!("prepare_level_rules: puzzle {} with {} taps", puzzle.id, puzzle.tap_budget);
+                commands.insert_resource(LevelRules {
+                    grid_size: puzzle.grid_size,
+                    trap_count: puzzle.traps.len(),
+                    rustacean_ai: puzzle.rustacean_ai,
+                    rustacean_count: 1,
+                    win_condition: WinCondition::TrapAll,
+                    special_tiles: SpecialTileCounts::default(),
+                    // the solver only knows regular traps
+                    trap_inventory: TrapInventoryCounts::NONE,
+                    rustacean_abilities: Vec::new(),
+                    endless_tier: 0,
+                    modifiers: Vec::new(),
+                    puzzle: Some(puzzle.clone()),
+                    fixed_board: Some(FixedBoard {
+                        rustaceans: vec![puzzle.rustacean],
+                        traps: puzzle.traps.clone(),
+                        special_tiles: puzzle.special_tiles.clone(),
+                        abilities: Vec::new(),
+                    }),
+                });
+                return;
+            }
+            None => warn!("No puzzles to play, falling back to a random board"),
+        }
+    }
+
+    // both hotseat rounds are played on the board the first one was given
+    let fixed_board = match (game_mode.0, hotseat) {
+        (GameMode::Hotseat, Some(hotseat)) => hotseat.board.clone(),
+        _ => None,
+    };
+    let rustaceans = options.rustaceans.unwrap_or(settings.rustaceans);
+    let win_condition = options.win_condition.unwrap_or(settings.win_condition);
+
+    commands.insert_resource(LevelRules {
+        fixed_board,
+        ..generated_level_rules(&difficulty.profile, level, options.grid_size, rustaceans, win_condition)
+    });
+}
+
+/// Rules of a generated board for `level`, `grid_size` replaces the size the profile and endless mode would pick
+pub fn generated_level_rules(
+    profile: &DifficultyProfile,
+    level: u32,
+    grid_size: Option<GridSize>,
+    rustaceans: u32,
+    win_condition: WinCondition,
+) -> LevelRules {
+    let tier = endless_tier(level);
+
+    // boards grow by two columns and a row per tier
+    let base_size = profile.grid_size(level);
+    let grid_size = grid_size.unwrap_or(GridSize::new(
+        (base_size.cols + 2 * tier as i32).min(MAX_ENDLESS_COLS.max(base_size.cols)),
+        (base_size.rows + tier as i32).min(MAX_ENDLESS_ROWS.max(base_size.rows)),
+    ));
+
+    // the rustacean climbs one AI tier per endless tier
+    let rustacean_ai = match tier {
+        0 => profile.rustacean_ai,
+        1 => profile.rustacean_ai.max(RustaceanAi::Greedy),
+        2 => profile.rustacean_ai.max(RustaceanAi::Pathfinder),
+        _ => RustaceanAi::Strategist,
+    };
+
+    let modifiers: Vec<EndlessModifier> = [
+        EndlessModifier::HeadStart,
+        EndlessModifier::OpenEdges,
+        EndlessModifier::SparseTraps,
+    ].into_iter()
+        .filter(|modifier| tier >= modifier.unlock_tier())
+        .collect();
+
+    let mut trap_count = profile.trap_count(level, grid_size.count());
+    if modifiers.contains(&EndlessModifier::SparseTraps) {
+        let removed = (tier + 1 - EndlessModifier::SparseTraps.unlock_tier()) as usize;
+        trap_count = trap_count.saturating_sub(removed);
+    }
+
+    let rustacean_count = rustaceans.clamp(1, MAX_RUSTACEANS) as usize;
+    let rustacean_abilities = random_rustacean_abilities(rustacean_count, &profile.rustacean_abilities.unlocked(level));
+
+    debug!("prepare_level_rules: level {level}, tier {tier}, {:?}, {trap_count} traps, {rustacean_count} {:?}, {:?}", grid_size, rustacean_ai, modifiers);
+
+    LevelRules {
+        grid_size,
+        trap_count,
+        rustacean_ai,
+        rustacean_count,
+        win_condition,
+        special_tiles: profile.special_tiles,
+        trap_inventory: profile.trap_inventory,
+        rustacean_abilities,
+        endless_tier: tier,
+        modifiers,
+        puzzle: None,
+        fixed_board: None,
+    }
+}
+
+/// HUD line under the level number listing the endless tier and active modifiers
+#[derive(Component)]
+pub struct EndlessText;
+
+pub fn endless_text_update(
+    rules: Res<LevelRules>,
+    localization: Res<Localization>,
+    mut query: Query<&mut TextSpan, With<EndlessText>>,
+) {
+    let text = if rules.endless_tier == 0 {
+        String::new()
+    } else {
+        let mut text = format!("\n{}", localization.format("endless.tier", &[("n", &rules.endless_tier.to_string())]));
+        for modifier in &rules.modifiers {
+            text.push_str(&format!("\n{}", localization.text(modifier.key())));
+        }
+        text
+    };
+
+    for mut span in &mut query {
+        if text != span.0 {
+            **span = text.clone()
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use bevy::{ecs::event::{Event, EventReader}, log::debug};
+use crate::{game::abilities::PlayerAbility, menu::menu::MenuButtonPressed};
+
+/// The player placed a trap on the tile at (x, y)
+#[derive(Event, Clone, Copy, Debug)]
+pub struct TrapPlaced {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// The rustacean hopped to a neighboring tile
+#[derive(Event, Clone, Copy, Debug)]
+pub struct RustaceanMoved {
+    pub from: (i32, i32),
+    pub to: (i32, i32),
+}
+
+/// The rustacean left the board through the out of bounds position (x, y)
+#[derive(Event, Clone, Copy, Debug)]
+pub struct RustaceanEscaped {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// The rustacean has no open neighbor left on the tile at (x, y)
+#[derive(Event, Clone, Copy, Debug)]
+pub struct RustaceanTrapped {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// A board has been set up and the player can start tapping
+#[derive(Event, Clone, Copy, Debug)]
+pub struct LevelStarted {
+    pub level: u32,
+}
+
+/// The level is over, won once the rustaceans are trapped, lost once they escaped or time or taps ran out
+#[derive(Event, Clone, Copy, Debug)]
+pub struct LevelEnded {
+    pub won: bool,
+}
+
+/// The run is over and the game goes back to the main menu, `won` tells how its last level ended
+#[derive(Event, Clone, Copy, Debug)]
+pub struct GameOver {
+    pub won: bool,
+}
+
+/// The player used one of their abilities
+#[derive(Event, Clone, Copy, Debug)]
+pub struct AbilityUsed {
+    pub ability: PlayerAbility,
+}
+
+/// Writes every gameplay event to the debug log
+pub fn log_gameplay_events(
+    mut trap_events: EventReader<TrapPlaced>,
+    mut moved_events: EventReader<RustaceanMoved>,
+    mut escaped_events: EventReader<RustaceanEscaped>,
+    mut trapped_events: EventReader<RustaceanTrapped>,
+    mut level_events: EventReader<LevelStarted>,
+    mut level_end_events: EventReader<LevelEnded>,
+    mut game_over_events: EventReader<GameOver>,
+    mut ability_events: EventReader<AbilityUsed>,
+    mut menu_events: EventReader<MenuButtonPressed>,
+) {
+    for event in trap_events.read() {
+        debug!("event: trap placed at ({}, {})", event.x, event.y);
+    }
+    for event in moved_events.read() {
+        debug!("event: rustacean moved {:?} -> {:?}", event.from, event.to);
+    }
+    for event in escaped_events.read() {
+        debug!("event: rustacean escaped through ({}, {})", event.x, event.y);
+    }
+    for event in trapped_events.read() {
+        debug!("event: rustacean trapped at ({}, {})", event.x, event.y);
+    }
+    for event in level_events.read() {
+        debug!("event: level {} started", event.level);
+    }
+    for event in level_end_events.read() {
+        debug!("event: level ended, {}", if event.won { "won" } else { "lost" });
+    }
+    for event in game_over_events.read() {
+        debug!("event: game over, {}", if event.won { "won" } else { "lost" });
+    }
+    for event in ability_events.read() {
+        debug!("event: ability {:?} used", event.ability);
+    }
+    for event in menu_events.read() {
+        debug!("event: menu button {:?} pressed", event.0);
+    }
+}
@@ -1,11 +1,8 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
 
 use anyhow::Context;
 use bevy::{prelude::*};
-use crate::{ game::{level_reset::reset_level, level_setup::{prepare_tile_traps, spawn_rustacean, tile_observer}, levels::{despawn_current_stats, goto_main_menu, level_text_update, reset_current_level_taps, run_levelend_timer, set_player_turn, spawn_current_stats_text, LevelState, TurnState}, pathfinding::pathfind_and_move}, utils::hexgrid_utils::{get_startup_hexgrid, GridSize, GridTilePos, HexGridOrientation, HextileF2FSize}};
-
-const GRID_COLS: i32 = 7;
-const GRID_ROWS: i32 = 12;
+use crate::{ game::{abilities::{ability_bar_update, hide_rustacean_paths, reset_player_abilities, reveal_rustacean_paths, spawn_ability_bar, tick_ability_cooldowns, use_player_abilities, AbilityPicked, PlayerAbilities}, difficulty::{select_difficulty, setup_difficulty_profiles, ActiveDifficulty, DifficultyKind}, endless::{endless_text_update, prepare_level_rules, LevelRules}, game_mode::{game_mode_is, select_game_mode, GameMode}, hotseat::{clear_legal_moves, hotseat_match_over, hotseat_next_round, hotseat_text_update, record_hotseat_round, remember_hotseat_board, apply_chosen_rustacean_moves, end_turn_if_rustacean_stuck, highlight_rustacean_moves, rustacean_controlled_here, rustacean_move_observer, spawn_hotseat_text, start_hotseat_match, HotseatMatch, RustaceanMoveChosen}, rustaceans::{reset_rustacean_turn_progress, rest_muddy_rustaceans, rustacean_ability_text_update, rustaceans_text_update, tick_rustacean_abilities, RustaceanTurnProgress}, tiles::place_special_tiles, traps::{decay_traps, reset_trap_inventory, select_trap_kind, show_decay_countdowns, trap_inventory_text_update, TrapInventory}, rating::{compute_level_par, despawn_level_end_text, level_taps_text_update, record_level_stars, spawn_level_end_text, LevelPar}, rules::{record_game_started, record_rustaceans}, replay::{play_back_replay, record_replay_abilities, record_replay_tap, save_replay, start_replay_recording, ReplayPlayback}, puzzle::{puzzle_text_update, record_puzzle_stars, setup_puzzle_book, spawn_puzzle_text, start_at_unsolved_puzzle}, strategy::StrategyRegistry, score::{count_used_abilities, edit_high_score_name, finish_scored_run, high_score_name_text_update, run_score_text_update, score_cleared_level, setup_high_scores, spawn_run_score_text, start_run_score, tick_run_clock, RunScore}, time_attack::{apply_time_attack_results, record_time_attack_score, spawn_time_attack_text, start_time_attack_clock, tick_time_attack_clock, time_attack_running, time_attack_text_update}, events::{log_gameplay_events, AbilityUsed, GameOver, LevelEnded, LevelStarted, RustaceanEscaped, RustaceanMoved, RustaceanTrapped, TrapPlaced}, level_reset::reset_level, level_setup::{prepare_tile_traps, spawn_rustacean, tile_click_observer, tile_observer}, levels::{announce_game_over, announce_level_start, despawn_current_stats, goto_main_menu, level_text_update, reset_current_level_taps, run_levelend_timer, set_player_turn, spawn_current_stats_text, LevelState, TurnState}, pathfinding::pathfind_and_move, tile_cursor::{confirm_tile_cursor, move_tile_cursor, reset_tile_cursor, TileCursor}, tile_feedback::{animate_invalid_taps, animate_trap_placement, color_board_on_level_end, tile_hover_observer, tile_unhover_observer, update_tile_colors}}, network::session::is_net_client, settings::launch::{data_path, ensure_data_dir, LaunchOptions, LaunchWindowMode}, utils::hexgrid_utils::{spawn_hexgrid, GridSize, GridTilePos, HexGridOrientation, HextileF2FSize}};
 
 pub struct GamePlugin;
 impl Plugin for GamePlugin {
@@ -13,6 +10,20 @@ impl Plugin for GamePlugin {
 
         app.insert_state(AppState::MainMenu);
 
+        app.add_event::<TrapPlaced>()
+            .add_event::<RustaceanMoved>()
+            .add_event::<RustaceanEscaped>()
+            .add_event::<RustaceanTrapped>()
+            .add_event::<LevelStarted>()
+            .add_event::<LevelEnded>()
+            .add_event::<GameOver>()
+            .add_event::<AbilityUsed>()
+            .add_event::<AbilityPicked>()
+            .add_event::<RustaceanMoveChosen>();
+        app.add_systems(Update, log_gameplay_events);
+        // after everything that ends a level in Update, so the stats are counted before the level end saves them
+        app.add_systems(PostUpdate, (count_rustacean_outcomes.run_if(resource_exists::<ActiveDifficulty>), color_board_on_level_end));
+
         // no logging developed yet
         app.insert_resource(
             TotalGameStats {
@@ -21,41 +32,287 @@ impl Plugin for GamePlugin {
             }
         );
 
-        app.add_systems(Startup, (spawn_camera, setup_total_game_stats));
+        app.add_systems(Startup, (spawn_camera, setup_total_game_stats, setup_difficulty_profiles, setup_puzzle_book, setup_high_scores, launch_into_game));
 
         app.init_state::<LevelState>();
         app.add_systems(
             OnEnter(AppState::InGame), 
             (
-                get_startup_hexgrid(
-                    Vec3::new(0., 0., 0.),
-                    GridSize::new(GRID_COLS, GRID_ROWS),
-                    HexGridOrientation::Vertical,
-                    HextileF2FSize(90.0),
-                    Some("hex.png")
-                ),
-                add_clicking_observers_to_tiles,
                 reset_current_level,
+                select_difficulty,
+                select_game_mode,
                 spawn_current_stats_text,
+                spawn_ability_bar,
                 start_new_level,
             ).before(spawn_rustacean).before(prepare_tile_traps).chain()
         )
         .add_systems(Update, level_text_update.run_if(in_state(AppState::InGame)))
+        .add_systems(
+            Update, 
+            (endless_text_update, level_taps_text_update, rustaceans_text_update, rustacean_ability_text_update, trap_inventory_text_update)
+                .run_if(in_state(AppState::InGame))
+                .run_if(resource_exists::<LevelRules>)
+        )
+        .add_systems(
+            Update, 
+            (animate_trap_placement, animate_invalid_taps, show_decay_countdowns)
+                .run_if(in_state(AppState::InGame))
+        )
+        .add_systems(
+            Update, 
+            update_tile_colors
+                .after(animate_trap_placement)
+                .run_if(in_state(LevelState::InLevel))
+                .run_if(
+                    in_state(TurnState::PlayerTurn)
+                        .or(in_state(TurnState::RustaceanTurn).and(rustacean_controlled_here))
+                )
+        )
         .add_systems(
             OnExit(AppState::InGame),
             despawn_current_stats
         );
 
+        app.add_systems(
+            OnEnter(AppState::InGame),
+            (start_time_attack_clock, spawn_time_attack_text)
+                .after(select_game_mode)
+                .before(start_new_level)
+                .run_if(game_mode_is(GameMode::TimeAttack))
+        )
+        .add_systems(
+            Update,
+            (
+                tick_time_attack_clock.run_if(in_state(LevelState::InLevel)),
+                apply_time_attack_results,
+                time_attack_text_update,
+            )
+                .chain()
+                .before(reset_level)
+                .before(goto_main_menu)
+                .run_if(in_state(AppState::InGame))
+                .run_if(game_mode_is(GameMode::TimeAttack))
+        )
+        .add_systems(
+            OnExit(AppState::InGame),
+            (record_time_attack_score, save_total_game_stats)
+                .chain()
+                .run_if(game_mode_is(GameMode::TimeAttack))
+        );
+
+        app.add_systems(
+            OnEnter(AppState::InGame),
+            (start_run_score, spawn_run_score_text)
+                .after(select_game_mode)
+                .before(start_new_level)
+                .run_if(game_mode_is(GameMode::Classic))
+        )
+        .add_systems(
+            Update,
+            (
+                tick_run_clock.run_if(in_state(LevelState::InLevel)),
+                count_used_abilities,
+                run_score_text_update.run_if(resource_exists::<LevelRules>),
+            )
+                .run_if(in_state(AppState::InGame))
+                .run_if(resource_exists::<RunScore>)
+                .run_if(game_mode_is(GameMode::Classic))
+        )
+        .add_systems(
+            OnEnter(LevelState::LevelWin),
+            score_cleared_level
+                .run_if(resource_exists::<RunScore>)
+                .run_if(game_mode_is(GameMode::Classic))
+        )
+        .add_systems(
+            OnExit(AppState::InGame),
+            finish_scored_run
+                .run_if(resource_exists::<RunScore>)
+                .run_if(game_mode_is(GameMode::Classic))
+        )
+        .add_systems(
+            Update,
+            (edit_high_score_name, high_score_name_text_update)
+                .chain()
+                .run_if(in_state(MenuState::HighScores))
+        );
+
+        app.add_systems(
+            OnEnter(AppState::InGame),
+            start_replay_recording
+                .after(select_difficulty)
+                .after(select_game_mode)
+                .after(reset_current_level)
+                .after(start_at_unsolved_puzzle)
+                .before(start_new_level)
+        )
+        .add_systems(Update, record_replay_abilities.after(use_player_abilities))
+        .add_systems(
+            Update,
+            play_back_replay
+                .run_if(in_state(LevelState::InLevel))
+                .run_if(in_state(TurnState::PlayerTurn))
+                .run_if(resource_exists::<ReplayPlayback>)
+        )
+        .add_systems(OnExit(AppState::InGame), save_replay)
+        .add_observer(record_replay_tap);
+
+        app.add_systems(
+            OnEnter(LevelState::LevelWin),
+            record_level_stars
+                .before(save_total_game_stats)
+                .run_if(game_mode_is(GameMode::Classic).or(game_mode_is(GameMode::TimeAttack)))
+        )
+        .add_systems(
+            OnEnter(LevelState::LevelWin),
+            // time attack chains its boards without a pause to read it
+            spawn_level_end_text.run_if(game_mode_is(GameMode::Classic).or(game_mode_is(GameMode::Puzzle)))
+        )
+        .add_systems(
+            OnEnter(LevelState::LevelLose),
+            spawn_level_end_text.run_if(game_mode_is(GameMode::Classic).or(game_mode_is(GameMode::Puzzle)))
+        )
+        .add_systems(OnExit(LevelState::LevelWin), despawn_level_end_text)
+        .add_systems(OnExit(LevelState::LevelLose), despawn_level_end_text);
+
+        app.add_systems(
+            OnEnter(AppState::InGame),
+            (start_at_unsolved_puzzle, spawn_puzzle_text)
+                .after(reset_current_level)
+                .after(select_game_mode)
+                .before(start_new_level)
+                .run_if(game_mode_is(GameMode::Puzzle))
+        )
+        .add_systems(
+            Update,
+            puzzle_text_update
+                .run_if(in_state(AppState::InGame))
+                .run_if(resource_exists::<LevelRules>)
+                .run_if(game_mode_is(GameMode::Puzzle))
+        )
+        .add_systems(
+            OnEnter(LevelState::LevelWin),
+            record_puzzle_stars
+                .before(save_total_game_stats)
+                .run_if(game_mode_is(GameMode::Puzzle))
+        );
+
+        app.add_systems(
+            OnEnter(AppState::InGame),
+            (start_hotseat_match, spawn_hotseat_text)
+                .after(select_game_mode)
+                .before(start_new_level)
+                .run_if(game_mode_is(GameMode::Hotseat))
+        )
+        .add_systems(
+            OnEnter(LevelState::InLevel),
+            remember_hotseat_board
+                .after(prepare_tile_traps)
+                .run_if(game_mode_is(GameMode::Hotseat))
+        )
+        .add_systems(
+            Update,
+            hotseat_text_update
+                .run_if(in_state(AppState::InGame))
+                .run_if(resource_exists::<HotseatMatch>)
+                .run_if(game_mode_is(GameMode::Hotseat))
+        )
+        .add_systems(
+            OnEnter(TurnState::RustaceanTurn),
+            (
+                // whoever owns the board decides when the human rustacean is stuck
+                end_turn_if_rustacean_stuck
+                    .run_if(game_mode_is(GameMode::Hotseat).or(game_mode_is(GameMode::Online).and(not(is_net_client)))),
+                highlight_rustacean_moves.run_if(rustacean_controlled_here),
+            )
+                .chain()
+                .run_if(in_state(LevelState::InLevel))
+        )
+        .add_systems(
+            Update,
+            apply_chosen_rustacean_moves
+                .run_if(in_state(LevelState::InLevel))
+                .run_if(game_mode_is(GameMode::Hotseat))
+        )
+        .add_systems(
+            Update,
+            // with several rustaceans the next one gets its highlights once the one before moved
+            highlight_rustacean_moves
+                .after(apply_chosen_rustacean_moves)
+                .run_if(on_event::<RustaceanMoved>.or(on_event::<RustaceanEscaped>))
+                .run_if(in_state(LevelState::InLevel))
+                .run_if(in_state(TurnState::RustaceanTurn))
+                .run_if(rustacean_controlled_here)
+        )
+        .add_systems(OnExit(TurnState::RustaceanTurn), (clear_legal_moves, rest_muddy_rustaceans))
+        .add_systems(OnExit(TurnState::RustaceanTurn), tick_ability_cooldowns)
+        .add_systems(OnExit(TurnState::PlayerTurn), hide_rustacean_paths)
+        // a client gets its decayed traps and ability cooldowns from the host's snapshots
+        .add_systems(OnExit(TurnState::RustaceanTurn), (decay_traps, tick_rustacean_abilities).run_if(not(is_net_client)))
+        .add_systems(OnExit(LevelState::InLevel), clear_legal_moves)
+        .add_systems(
+            OnEnter(LevelState::LevelWin),
+            record_hotseat_round.run_if(game_mode_is(GameMode::Hotseat))
+        )
+        .add_systems(
+            OnEnter(LevelState::LevelLose),
+            record_hotseat_round.run_if(game_mode_is(GameMode::Hotseat))
+        )
+        // the run ends where goto_main_menu takes over, a hotseat round has to be recorded to know
+        .add_systems(
+            OnEnter(LevelState::LevelLose),
+            announce_game_over
+                .after(record_hotseat_round)
+                .run_if(not(time_attack_running).and(not(hotseat_next_round)))
+        )
+        .add_systems(
+            OnEnter(LevelState::LevelWin),
+            announce_game_over
+                .after(record_hotseat_round)
+                .run_if(hotseat_match_over)
+        );
+
         app.init_state::<TurnState>();
+        app.init_resource::<TileCursor>();
+        app.init_resource::<RustaceanTurnProgress>();
+        app.init_resource::<TrapInventory>();
+        app.init_resource::<PlayerAbilities>();
+        app.init_resource::<LevelPar>();
+        app.init_resource::<StrategyRegistry>();
         app.add_systems(
             OnEnter(LevelState::InLevel), 
             (
+                prepare_level_rules,
+                ensure_level_grid,
+                add_clicking_observers_to_tiles,
                 spawn_rustacean,
+                place_special_tiles,
                 prepare_tile_traps,
                 reset_current_level_taps,
+                compute_level_par,
+                reset_trap_inventory,
+                reset_player_abilities,
+                reset_rustacean_turn_progress,
+                reset_tile_cursor,
                 set_player_turn,
+                announce_level_start,
             ).chain()
         );
+        app.add_systems(
+            Update, 
+            (move_tile_cursor, confirm_tile_cursor)
+                .chain()
+                .run_if(in_state(LevelState::InLevel))
+        );
+        app.add_systems(Update, select_trap_kind.run_if(in_state(LevelState::InLevel)));
+        app.add_systems(
+            Update,
+            (use_player_abilities, reveal_rustacean_paths)
+                .chain()
+                .run_if(in_state(LevelState::InLevel))
+                .run_if(in_state(TurnState::PlayerTurn))
+        );
+        app.add_systems(Update, ability_bar_update.run_if(in_state(AppState::InGame)));
         app.add_systems(
             OnEnter(LevelState::LevelWin), 
                 (run_levelend_timer, save_total_game_stats)
@@ -65,7 +322,12 @@ impl Plugin for GamePlugin {
             reset_level
                 .before(spawn_rustacean)
                 .before(prepare_tile_traps)
-                .run_if(in_state(LevelState::LevelWin))
+                // a client waits for the host to send the next board
+                .run_if(not(is_net_client))
+                .run_if(
+                    in_state(LevelState::LevelWin).and(not(hotseat_match_over))
+                        .or(in_state(LevelState::LevelLose).and(time_attack_running.or(hotseat_next_round)))
+                )
         )
         .add_systems(
             OnEnter(LevelState::LevelLose), 
@@ -74,13 +336,18 @@ impl Plugin for GamePlugin {
         .add_systems(
             Update, 
             goto_main_menu
-                .run_if(in_state(LevelState::LevelLose))
+                .run_if(
+                    in_state(LevelState::LevelLose).and(not(time_attack_running)).and(not(hotseat_next_round))
+                        .or(in_state(LevelState::LevelWin).and(hotseat_match_over))
+                )
                 .chain()
         );
 
         app.add_systems(
             OnEnter(TurnState::RustaceanTurn), 
                 pathfind_and_move
+                    .run_if(not(game_mode_is(GameMode::Hotseat)))
+                    .run_if(not(game_mode_is(GameMode::Online)))
         );
     }
 }
@@ -97,6 +364,10 @@ pub enum MenuState{
     Disabled,
     Main,
     Stats,
+    Settings,
+    Lobby,
+    /// Best runs, and the score of the run that just ended
+    HighScores,
     Quit
 }
 
@@ -111,88 +382,198 @@ pub struct TotalGameStats {
     pub tigers_escaped: u64,
     pub games_played: u64,
     pub record_level: u64,
+    /// Most rustaceans trapped in a single time attack game
+    #[serde(default)]
+    pub time_attack_best: u64,
+    /// Best star rating of every solved puzzle, by puzzle id
+    #[serde(default)]
+    pub puzzle_stars: HashMap<String, u8>,
+    /// Stars earned on every generated level won
+    #[serde(default)]
+    pub level_stars: u64,
+    /// The same stats split by the difficulty they were played on
+    #[serde(default)]
+    pub per_difficulty: HashMap<DifficultyKind, DifficultyStats>,
+}
+
+#[derive(Default, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DifficultyStats {
+    pub tiles_tapped: u64,
+    pub tigers_trapped: u64,
+    pub tigers_escaped: u64,
+    pub games_played: u64,
+    pub record_level: u64,
+    #[serde(default)]
+    pub time_attack_best: u64,
+    #[serde(default)]
+    pub level_stars: u64,
+}
+
+impl TotalGameStats {
+    pub fn for_difficulty(&mut self, kind: DifficultyKind) -> &mut DifficultyStats {
+        self.per_difficulty.entry(kind).or_default()
+    }
 }
 
 fn spawn_camera(
     mut commands: Commands,
-    mut uiscale: ResMut<UiScale>
+    mut uiscale: ResMut<UiScale>,
+    options: Res<LaunchOptions>,
 ) {
+    // a fullscreen window has the room to show the board at its own size
+    let fullscreen = options.window_mode == LaunchWindowMode::Fullscreen;
+
     commands.spawn((
         Camera2d,
         Projection::Orthographic(
             OrthographicProjection {
-                scale: 2.0,
+                scale: if fullscreen { 1.0 } else { 2.0 },
                 ..OrthographicProjection::default_2d()
             }
         )
     ));
-    uiscale.0 = uiscale.0 / 2.;
+    if !fullscreen {
+        uiscale.0 /= 2.;
+    }
+}
+
+/// Starts a game right away when the command line asked to skip the main menu
+fn launch_into_game(
+    options: Res<LaunchOptions>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    mut next_menu_state: ResMut<NextState<MenuState>>,
+) {
+    if options.skip_menu {
+        next_app_state.set(AppState::InGame);
+        next_menu_state.set(MenuState::Disabled);
+    }
 }
 
 fn setup_total_game_stats(
     mut commands: Commands,
 ) {
-    let game_stats = if !PathBuf::from(r"./configs").exists() {
-        std::fs::create_dir("./configs")
-            .expect("Error: User should have permission to the directory location\nPath should not yet exist");
+    commands.insert_resource(load_total_game_stats());
+}
 
+/// Reads stats.json, writing out fresh stats first when there is none yet
+pub fn load_total_game_stats() -> TotalGameStats {
+    let stats_path = data_path("stats.json");
+
+    if !stats_path.exists() {
         let tgame_stats = TotalGameStats {
             record_level: 1,
             ..Default::default()
         };
-
-        let json = serde_json::to_string_pretty(&tgame_stats)
-            .expect("Error: Implementation of Serialize must not decide to fail\nT should contain a map with string keys");
-
-        std::fs::write(PathBuf::from(r"./configs/stats.json"), json)
-            .expect("Error: Directory ./configs must exist");
+        write_total_game_stats(&tgame_stats);
 
         tgame_stats
     } else {
-        let json = std::fs::read_to_string("./configs/stats.json")
+        let json = std::fs::read_to_string(&stats_path)
             .expect("Error: Path must exist\nContents of the file must be valid utf8");
 
         let tgame_stats: TotalGameStats = serde_json::from_str(&json)
             .expect("Error: the data must be possible to be parsed into TotalGameStats");
 
         tgame_stats
-    };
+    }
+}
+
+/// Counts every trapped and escaped rustacean, overall and for the difficulty that is played
+pub fn count_rustacean_outcomes(
+    mut trapped_events: EventReader<RustaceanTrapped>,
+    mut escaped_events: EventReader<RustaceanEscaped>,
+    difficulty: Res<ActiveDifficulty>,
+    mut game_stats: ResMut<TotalGameStats>,
+) {
+    let trapped = trapped_events.read().count() as u64;
+    let escaped = escaped_events.read().count() as u64;
+    if trapped == 0 && escaped == 0 {
+        return;
+    }
 
-    commands.insert_resource(game_stats);
+    record_rustaceans(&mut game_stats, difficulty.kind, trapped, escaped);
 }
 
 pub fn save_total_game_stats(
     game_stats: Res<TotalGameStats>
 ) {
-    let json = serde_json::to_string_pretty(game_stats.into_inner())
+    write_total_game_stats(game_stats.into_inner());
+}
+
+pub fn write_total_game_stats(
+    game_stats: &TotalGameStats
+) {
+    ensure_data_dir();
+
+    let json = serde_json::to_string_pretty(game_stats)
         .expect("Error: Implementation of Serialize must not decide to fail\nT should contain a map with string keys");
 
-    std::fs::write(PathBuf::from(r"./configs/stats.json"), json)
+    std::fs::write(data_path("stats.json"), json)
         .context("Writing game stats to json file at end of level")
-        .expect("Error: Directory ./configs must exist");
+        .expect("Error: The data directory must exist");
 }
 
 fn reset_current_level(
     mut commands: Commands,
+    options: Res<LaunchOptions>,
 ) {
-    commands.insert_resource(CurrentLevel(1));
+    commands.insert_resource(CurrentLevel(options.start_level.unwrap_or(1)));
 }
 
 fn start_new_level(
     mut next_level_state: ResMut<NextState<LevelState>>,
-    mut game_stats: ResMut<TotalGameStats>
+    mut game_stats: ResMut<TotalGameStats>,
+    difficulty: Res<ActiveDifficulty>,
 ) {
     next_level_state.set(LevelState::InLevel);
-    game_stats.games_played += 1;
+    record_game_started(&mut game_stats, difficulty.kind);
+}
+
+/// Spawns the board for the current level, replacing the previous one if the difficulty grows the grid
+pub fn ensure_level_grid(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    rules: Res<LevelRules>,
+    grid_q: Query<(Entity, &GridSize)>,
+) {
+    let grid_size = rules.grid_size;
+
+    let mut has_grid = false;
+    for (entity, existing_size) in grid_q.iter() {
+        if *existing_size == grid_size && !has_grid {
+            has_grid = true;
+        } else {
+            debug!("ensure_level_grid: despawning {:?} grid", existing_size);
+            commands.entity(entity).despawn();
+        }
+    }
+
+    if !has_grid {
+        debug!("ensure_level_grid: spawning {:?} grid", grid_size);
+        spawn_hexgrid(
+            &mut commands,
+            &asset_server,
+            Vec3::new(0., 0., 0.),
+            grid_size,
+            HexGridOrientation::Vertical,
+            HextileF2FSize(90.0),
+            Some("hex.png")
+        );
+    }
 }
 
 fn add_clicking_observers_to_tiles(
-    tile_q: Query<Entity, With<GridTilePos>>,
+    tile_q: Query<Entity, Added<GridTilePos>>,
     mut commands: Commands,
 ) {
     for entity in tile_q {
         debug!("preparing tiles: adding observers to tile");
-        commands.entity(entity).observe(tile_observer());
+        commands.entity(entity)
+            .observe(tile_click_observer())
+            .observe(tile_observer())
+            .observe(rustacean_move_observer())
+            .observe(tile_hover_observer())
+            .observe(tile_unhover_observer());
     }
 }
 
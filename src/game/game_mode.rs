@@ -0,0 +1,88 @@
+use bevy::ecs::{resource::Resource, system::{Commands, Res}};
+use crate::{network::session::NetSession, settings::{launch::LaunchOptions, settings::Settings}};
+
+/// Ways to play, selectable from the main menu
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum GameMode {
+    /// Levels get harder until the rustacean escapes once
+    #[default]
+    Classic,
+    /// Trap as many rustaceans as possible before a global countdown runs out
+    TimeAttack,
+    /// Fixed boards from ./assets/puzzles that have to be solved within a tap budget
+    Puzzle,
+    /// Two players on one board, one traps while the other moves the rustacean, then they swap
+    Hotseat,
+    /// Host and client on two machines, started from the multiplayer lobby instead of the mode button
+    Online,
+}
+
+impl GameMode {
+    /// Modes the main menu button cycles through, online games are started from the lobby
+    pub const ALL: [GameMode; 4] = [
+        GameMode::Classic,
+        GameMode::TimeAttack,
+        GameMode::Puzzle,
+        GameMode::Hotseat,
+    ];
+
+    /// Localization key of the mode's display name
+    pub fn key(self) -> &'static str {
+        match self {
+            GameMode::Classic => "mode.classic",
+            GameMode::TimeAttack => "mode.time_attack",
+            GameMode::Puzzle => "mode.puzzle",
+            GameMode::Hotseat => "mode.hotseat",
+            GameMode::Online => "mode.online",
+        }
+    }
+
+    /// The mode after this one, wrapping around, used by the main menu button
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|mode| *mode == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Seconds the finished board stays up before the next level is set up
+    pub fn level_reset_secs(self) -> f32 {
+        match self {
+            GameMode::Classic | GameMode::Puzzle | GameMode::Hotseat | GameMode::Online => 3.0,
+            // time attack chains levels as fast as possible
+            GameMode::TimeAttack => 0.0,
+        }
+    }
+
+    /// Seconds the last board stays up before going back to the main menu
+    pub fn game_over_secs(self) -> f32 {
+        match self {
+            // leave time to read who won the match
+            GameMode::Hotseat => 5.0,
+            _ => 2.0,
+        }
+    }
+}
+
+/// The mode the current game was started with
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct ActiveGameMode(pub GameMode);
+
+pub fn select_game_mode(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    session: Option<Res<NetSession>>,
+    options: Res<LaunchOptions>,
+) {
+    // a game started from the lobby is always played online
+    let mode = match session {
+        Some(_) => GameMode::Online,
+        None => options.game_mode.unwrap_or(settings.game_mode),
+    };
+    commands.insert_resource(ActiveGameMode(mode));
+}
+
+/// Run condition that is true while a game of `mode` is being played
+pub fn game_mode_is(mode: GameMode) -> impl FnMut(Option<Res<ActiveGameMode>>) -> bool + Clone {
+    move |active_mode: Option<Res<ActiveGameMode>>| {
+        active_mode.is_some_and(|active_mode| active_mode.0 == mode)
+    }
+}
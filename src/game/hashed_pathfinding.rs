@@ -0,0 +1,158 @@
+//! The searches of [`crate::game::pathfinding`] as they were before open tiles moved into flat arrays.
+//!
+//! Open tiles are a `HashSet` and costs a `HashMap` here. They are kept to check that the array versions
+//! pick the same paths, and so `cargo bench --bench pathfinding` can time both side by side.
+
+use std::{cmp::Reverse, collections::{BinaryHeap, HashMap, HashSet}};
+
+use crate::{game::{pathfinding::{in_bounds, Path, RustaceanMove}, tiles::{TileKind, TileKinds, MUD_COST}}, utils::hexgrid_utils::{get_hex_horizontal_neighbor_pos, GridSize}};
+
+/// A board with its open tiles in a `HashSet`, like [`crate::game::pathfinding::BoardView`] used to have
+pub struct HashedBoard<'a> {
+    pub grid_size: GridSize,
+    /// Tiles without a trap, rock or rustacean on them
+    pub open_tiles: &'a HashSet<(i32, i32)>,
+    pub tiles: &'a TileKinds,
+}
+
+impl HashedBoard<'_> {
+    /// Where a rustacean stepping into `pos` ends up, teleporters send it on to their free pair
+    pub fn landing(&self, pos: (i32, i32)) -> (i32, i32) {
+        match self.tiles.kind(pos) {
+            Some(TileKind::Teleporter { pair }) if self.open_tiles.contains(&pair) => pair,
+            _ => pos,
+        }
+    }
+
+    /// Every move a rustacean on `pos` can make, with what it costs the weighted search
+    pub fn moves(&self, pos: (i32, i32)) -> Vec<(RustaceanMove, u32)> {
+        let neighbors = match self.tiles.kind(pos) {
+            Some(TileKind::OneWay { direction }) => vec![direction.step(pos.0, pos.1)],
+            _ => get_hex_horizontal_neighbor_pos(pos.0, pos.1).to_vec(),
+        };
+
+        neighbors.into_iter()
+            .filter_map(|target| self.move_onto(target))
+            .collect()
+    }
+
+    /// The move that ends on `(x, y)`, if the tile can be entered
+    fn move_onto(&self, (x, y): (i32, i32)) -> Option<(RustaceanMove, u32)> {
+        if !in_bounds((x, y), &self.grid_size) {
+            // exits close the edge of the board
+            return (!self.tiles.has_exits()).then_some((RustaceanMove::Escape(x, y), 1));
+        }
+        if !self.open_tiles.contains(&(x, y)) {
+            return None;
+        }
+        Some(match self.tiles.kind((x, y)) {
+            Some(TileKind::Exit) => (RustaceanMove::Escape(x, y), 1),
+            Some(TileKind::Mud) => (RustaceanMove::Hop(x, y), MUD_COST),
+            _ => (RustaceanMove::Hop(x, y), 1),
+        })
+    }
+}
+
+/// Cost from every open tile to the nearest way out, tiles that cant reach one are missing
+pub fn exit_distances(board: &HashedBoard) -> HashMap<(i32, i32), u32> {
+    let mut distances: HashMap<(i32, i32), u32> = HashMap::new();
+    let mut incoming: HashMap<(i32, i32), Vec<((i32, i32), u32)>> = HashMap::new();
+    let mut heap = BinaryHeap::new();
+
+    // tiles next to a way out are one move from leaving, the rest is searched backwards from them
+    for &pos in board.open_tiles {
+        for (next_move, cost) in board.moves(pos) {
+            match next_move {
+                RustaceanMove::Escape(..) => {
+                    if distances.get(&pos).is_none_or(|distance| cost < *distance) {
+                        distances.insert(pos, cost);
+                        heap.push(Reverse((cost, pos)));
+                    }
+                }
+                RustaceanMove::Hop(x, y) => incoming.entry(board.landing((x, y))).or_default().push((pos, cost)),
+                RustaceanMove::Trapped => {}
+            }
+        }
+    }
+
+    while let Some(Reverse((distance, current))) = heap.pop() {
+        if distances.get(&current).is_some_and(|best| *best < distance) {
+            continue;
+        }
+        for &(from, cost) in incoming.get(&current).into_iter().flatten() {
+            let through = distance + cost;
+            if distances.get(&from).is_none_or(|best| through < *best) {
+                distances.insert(from, through);
+                heap.push(Reverse((through, from)));
+            }
+        }
+    }
+
+    distances
+}
+
+/// Cheapest way out for the rustacean on `start`, the path ends on the tile it leaves through.
+///
+/// Among equally cheap paths the one found first wins, on a board without special tiles that is a breadth first search.
+pub fn pathfind(start: (i32, i32), board: &HashedBoard) -> Path {
+    if let Some((RustaceanMove::Escape(x, y), _)) = board.moves(start).into_iter()
+        .find(|(next_move, _)| matches!(next_move, RustaceanMove::Escape(..))) {
+        return Path::Escaped(x, y);
+    }
+
+    /// A tile the search got to, `entered` is what the move names and `landing` where it ends up
+    struct Step {
+        entered: (i32, i32),
+        landing: (i32, i32),
+        previous: Option<usize>,
+        leaves: bool,
+    }
+
+    let mut steps = vec![Step { entered: start, landing: start, previous: None, leaves: false }];
+    let mut best: HashMap<(i32, i32), u32> = HashMap::from([(start, 0)]);
+    // ordered by cost, then by the order the steps were found in
+    let mut heap = BinaryHeap::from([Reverse((0_u32, 0_usize))]);
+
+    while let Some(Reverse((cost, index))) = heap.pop() {
+        if steps[index].leaves {
+            let mut path = Vec::new();
+            let mut current = Some(index);
+            while let Some(step_index) = current {
+                let step = &steps[step_index];
+                if step.previous.is_some() {
+                    path.push(step.entered);
+                }
+                current = step.previous;
+            }
+            path.reverse();
+
+            return Path::Found(path);
+        }
+
+        let current = steps[index].landing;
+        if best.get(&current).is_some_and(|best| *best < cost) {
+            continue;
+        }
+
+        for (next_move, move_cost) in board.moves(current) {
+            let through = cost + move_cost;
+            let (entered, leaves) = match next_move {
+                RustaceanMove::Hop(x, y) => ((x, y), false),
+                RustaceanMove::Escape(x, y) => ((x, y), true),
+                RustaceanMove::Trapped => continue,
+            };
+            let landing = if leaves { entered } else { board.landing(entered) };
+
+            if !leaves {
+                if best.get(&landing).is_some_and(|best| *best <= through) {
+                    continue;
+                }
+                best.insert(landing, through);
+            }
+            steps.push(Step { entered, landing, previous: Some(index), leaves });
+            heap.push(Reverse((through, steps.len() - 1)));
+        }
+    }
+
+    Path::NotFound
+}
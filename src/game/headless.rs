@@ -0,0 +1,207 @@
+use crate::{game::{endless::{EndlessModifier, LevelRules}, level_setup::{generate_trap_positions, rustacean_starts}, pathfinding::{BoardView, RustaceanMove, TileSet}, rating::{generated_level_par, LevelPar}, rules::{level_outcome, move_effect, next_to_move, open_tiles, ready_jumps, tick_cooldown}, rustaceans::{trapped_rustaceans, RustaceanAbility, RustaceanVariant}, tiles::{generate_special_tiles, TileKinds}}, utils::hexgrid_utils::GridSize};
+
+/// A rustacean on a board without a window
+#[derive(Clone, Copy, Debug)]
+pub struct HeadlessRustacean {
+    pub pos: (i32, i32),
+    pub variant: Option<RustaceanVariant>,
+    /// Stepped into mud, so it sits out the next rustacean turn
+    pub resting: bool,
+}
+
+/// A generated level played by the same rules as on screen, without any entities, the turn and outcome rules come from [`crate::game::rules`].
+///
+/// Only regular traps are placed, the limited trap kinds and the player abilities are left out.
+#[derive(Clone, Debug)]
+pub struct HeadlessLevel {
+    pub rules: LevelRules,
+    pub tiles: TileKinds,
+    pub traps: TileSet,
+    /// Every rustacean, in turn order
+    pub rustaceans: Vec<HeadlessRustacean>,
+    pub taps: u32,
+    /// Trap layouts the generator threw away because they walled a rustacean in from the start
+    pub rejected_layouts: u32,
+}
+
+impl HeadlessLevel {
+    /// Lays out a board the way a generated level is set up on screen
+    pub fn generate(rules: LevelRules) -> Self {
+        let grid_size = rules.grid_size;
+        let starts = rustacean_starts(rules.rustacean_count, &grid_size, rules.has(EndlessModifier::HeadStart));
+        let special_tiles = generate_special_tiles(&rules.special_tiles, &grid_size, &starts);
+        let tiles = TileKinds::from_special_tiles(&special_tiles);
+
+        let trappable: Vec<(i32, i32)> = (0..grid_size.cols)
+            .flat_map(|x| (0..grid_size.rows).map(move |y| (x, y)))
+            .filter(|pos| !tiles.is_rock(*pos))
+            .collect();
+        // traps stay off the rustaceans and the special tiles
+        let reserved: Vec<(i32, i32)> = special_tiles.iter()
+            .map(|tile| tile.pos)
+            .chain(starts.iter().copied())
+            .collect();
+        let (traps, rejected_layouts) = generate_trap_positions(
+            rules.trap_count, &grid_size, &trappable, &tiles, &reserved, &starts, rules.has(EndlessModifier::OpenEdges),
+        );
+
+        let rustaceans = starts.into_iter()
+            .enumerate()
+            .map(|(order, pos)| HeadlessRustacean {
+                pos,
+                variant: rules.rustacean_abilities.get(order).copied().flatten().map(RustaceanVariant::new),
+                resting: false,
+            })
+            .collect();
+
+        HeadlessLevel {
+            rules,
+            tiles,
+            traps: TileSet::from_tiles(grid_size, traps),
+            rustaceans,
+            taps: 0,
+            rejected_layouts,
+        }
+    }
+
+    pub fn grid_size(&self) -> GridSize {
+        self.rules.grid_size
+    }
+
+    /// Tiles without a trap or rock, the rustaceans own tiles included
+    fn free_tiles(&self) -> TileSet {
+        let grid_size = self.grid_size();
+        TileSet::from_tiles(grid_size, (0..grid_size.cols)
+            .flat_map(|x| (0..grid_size.rows).map(move |y| (x, y)))
+            .filter(|pos| !self.traps.contains(pos) && !self.tiles.is_rock(*pos)))
+    }
+
+    /// Tiles without a trap, rock or rustacean on them, rustaceans that left through an exit dont block it
+    pub fn open_tiles(&self) -> TileSet {
+        let positions: Vec<(i32, i32)> = self.rustaceans.iter().map(|rustacean| rustacean.pos).collect();
+        open_tiles(self.free_tiles(), &positions, &self.tiles, &self.grid_size())
+    }
+
+    pub fn escaped(&self, index: usize) -> bool {
+        self.tiles.escaped(self.rustaceans[index].pos, &self.grid_size())
+    }
+
+    /// Rustaceans that got off the board
+    pub fn escaped_count(&self) -> usize {
+        (0..self.rustaceans.len()).filter(|index| self.escaped(*index)).count()
+    }
+
+    /// Taps the board should take, worked out like on screen
+    pub fn par(&self) -> LevelPar {
+        let positions: Vec<(i32, i32)> = self.rustaceans.iter().map(|rustacean| rustacean.pos).collect();
+        LevelPar(generated_level_par(&self.rules, &self.open_tiles(), &self.tiles, &positions))
+    }
+
+    /// Which rustaceans are trapped for good, in turn order
+    pub fn trapped(&self) -> Vec<bool> {
+        let positions: Vec<(i32, i32)> = self.rustaceans.iter().map(|rustacean| rustacean.pos).collect();
+        let jumpers: Vec<bool> = self.rustaceans.iter()
+            .map(|rustacean| rustacean.variant.is_some_and(|variant| variant.ability == RustaceanAbility::Jumper))
+            .collect();
+        let free_tiles = self.free_tiles();
+        trapped_rustaceans(&positions, &jumpers, &self.traps, &BoardView { grid_size: self.grid_size(), open_tiles: &free_tiles, tiles: &self.tiles })
+    }
+
+    /// Whether a trap can go on `pos`, the same taps the board on screen takes
+    pub fn can_tap(&self, pos: (i32, i32)) -> bool {
+        self.open_tiles().contains(&pos)
+    }
+
+    /// Puts a regular trap on `pos`, false if the tap isnt a valid one
+    pub fn tap(&mut self, pos: (i32, i32)) -> bool {
+        if !self.can_tap(pos) {
+            return false;
+        }
+        self.traps.insert(pos);
+        self.taps += 1;
+        true
+    }
+
+    /// Moves every rustacean once, in turn order, with `choose` picking the move of each.
+    ///
+    /// `choose` gets the rustacean, the board it moves on and the jumps it has ready.
+    pub fn rustacean_turn(&mut self, mut choose: impl FnMut(&HeadlessRustacean, &BoardView, &[(RustaceanMove, u32)]) -> RustaceanMove) {
+        // the ones that stepped into mud last turn sit this one out
+        let mut moved: Vec<bool> = self.rustaceans.iter().map(|rustacean| rustacean.resting).collect();
+        for rustacean in self.rustaceans.iter_mut() {
+            rustacean.resting = false;
+        }
+
+        while let Some(index) = self.current(&moved) {
+            let open_tiles = self.open_tiles();
+            let board = BoardView { grid_size: self.grid_size(), open_tiles: &open_tiles, tiles: &self.tiles };
+            let rustacean = self.rustaceans[index];
+            let jumps = ready_jumps(rustacean.pos, rustacean.variant, &self.traps, &board);
+            let next_move = choose(&rustacean, &board, &jumps);
+            let effect = move_effect(next_move, rustacean.variant, &jumps, &board);
+
+            let rustacean = &mut self.rustaceans[index];
+            if let Some(to) = effect.to {
+                rustacean.pos = to;
+            }
+            rustacean.resting = effect.rests;
+            if let Some(variant) = rustacean.variant.as_mut().filter(|_| effect.uses_ability()) {
+                variant.use_ability();
+            }
+            if effect.turn_over() {
+                moved[index] = true;
+            }
+        }
+
+        for variant in self.rustaceans.iter_mut().filter_map(|rustacean| rustacean.variant.as_mut()) {
+            tick_cooldown(variant);
+        }
+    }
+
+    /// The rustacean whose move is next this turn, if any is left that can move
+    fn current(&self, moved: &[bool]) -> Option<usize> {
+        let open_tiles = self.open_tiles();
+        let board = BoardView { grid_size: self.grid_size(), open_tiles: &open_tiles, tiles: &self.tiles };
+        let positions: Vec<(i32, i32)> = self.rustaceans.iter().map(|rustacean| rustacean.pos).collect();
+        let variants: Vec<Option<RustaceanVariant>> = self.rustaceans.iter().map(|rustacean| rustacean.variant).collect();
+        next_to_move(&positions, &variants, moved, &self.traps, &board)
+    }
+
+    /// Whether the level is won or lost under its win condition, None while it goes on
+    pub fn outcome(&self) -> Option<bool> {
+        let trapped = self.trapped().iter().filter(|trapped| **trapped).count();
+        level_outcome(&self.rules, self.rustaceans.len(), trapped, self.escaped_count(), self.taps)
+    }
+
+    /// The first rustacean that is neither trapped nor gone, the one a trapper goes after
+    pub fn target(&self) -> Option<(i32, i32)> {
+        let trapped = self.trapped();
+        (0..self.rustaceans.len())
+            .find(|index| !trapped[*index] && !self.escaped(*index))
+            .map(|index| self.rustaceans[index].pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{difficulty::{DifficultyKind, DifficultyProfiles}, endless::generated_level_rules, pathfinding::{pathfind, Path}, rustaceans::WinCondition, tiles::TileKind};
+    use super::*;
+
+    #[test]
+    fn a_rustacean_that_left_through_an_exit_doesnt_block_it() {
+        let profiles = DifficultyProfiles::default();
+        let rules = generated_level_rules(profiles.get(DifficultyKind::Easy), 1, Some(GridSize::new(7, 7)), 2, WinCondition::TrapAll);
+        let mut level = HeadlessLevel::generate(rules);
+        let exit = (0, 3);
+        level.tiles = TileKinds::new([(exit, TileKind::Exit)]);
+        level.traps = TileSet::new(level.grid_size());
+        level.rustaceans[0].pos = exit;
+        level.rustaceans[1].pos = (3, 3);
+
+        assert_eq!(level.escaped_count(), 1);
+        let open_tiles = level.open_tiles();
+        assert!(open_tiles.contains(&exit));
+        let board = BoardView { grid_size: level.grid_size(), open_tiles: &open_tiles, tiles: &level.tiles };
+        assert!(matches!(pathfind((3, 3), &board), Path::Found(path) if path.last() == Some(&exit)));
+    }
+}
@@ -0,0 +1,355 @@
+use bevy::{asset::AssetServer, color::Alpha, ecs::{component::Component, entity::Entity, event::{Event, EventReader, EventWriter}, observer::Trigger, query::{With, Without}, resource::Resource, system::{Commands, Query, Res, ResMut, Single}, world::World}, log::debug, math::Vec2, picking::{events::{Click, Pointer}, Pickable}, sprite::Sprite, state::state::State, text::TextSpan, transform::components::Transform, ui::{Node, PositionType, Val}, utils::default};
+use crate::{game::{endless::FixedBoard, rustaceans::{RustaceanOrder, RustaceanVariant}, game_mode::{game_mode_is, ActiveGameMode, GameMode}, level_setup::{RustaceanPos, TileTapped, TrapTile}, levels::{CurrentLevelTaps, LevelState, spawn_hud_text, TurnState}, pathfinding::{coord_to_world, in_bounds, RustaceanMove, RustaceanMover}, tile_feedback::{shake_tile, InvalidTapShake}, tile_lookup::TileLookup, tiles::{SpecialTile, TileKind}}, settings::{locale::Localization, theme::Theme}, utils::hexgrid_utils::{GridSize, GridTilePos, HexGridOrientation, HextileF2FSize}, network::session::{is_net_client, NetSession}};
+
+/// Rounds in a hotseat match, the players swap roles after every round
+const HOTSEAT_ROUNDS: usize = 2;
+
+/// Two players sharing one board, one places traps while the other moves the rustacean
+#[derive(Resource, Default)]
+pub struct HotseatMatch {
+    /// Taps the trapper of every finished round needed, `None` when the rustacean escaped
+    pub results: Vec<Option<u32>>,
+    /// Board of the first round, replayed in the second so both players get the same start
+    pub board: Option<FixedBoard>,
+}
+
+/// Who won a finished hotseat match
+pub enum HotseatOutcome {
+    /// Index of the winning player, 0 or 1
+    Winner(usize),
+    Draw,
+}
+
+impl HotseatMatch {
+    /// Index of the round being played, or of the last one once the match is over
+    pub fn round(&self) -> usize {
+        self.results.len().min(HOTSEAT_ROUNDS - 1)
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.results.len() >= HOTSEAT_ROUNDS
+    }
+
+    /// Player placing traps in `round`, player 0 starts and the roles swap every round
+    pub fn trapper(round: usize) -> usize {
+        round % 2
+    }
+
+    /// Player moving the rustacean in `round`
+    pub fn rustacean_player(round: usize) -> usize {
+        (round + 1) % 2
+    }
+
+    /// Taps player `player` needed as the trapper, `None` if they have not trapped yet or failed
+    pub fn taps_of(&self, player: usize) -> Option<u32> {
+        self.results.iter()
+            .enumerate()
+            .find(|(round, _)| Self::trapper(*round) == player)
+            .and_then(|(_, taps)| *taps)
+    }
+
+    /// Fewer taps wins, letting the rustacean escape counts as losing the round
+    pub fn outcome(&self) -> Option<HotseatOutcome> {
+        if !self.is_over() {
+            return None;
+        }
+
+        Some(match (self.taps_of(0), self.taps_of(1)) {
+            (Some(first), Some(second)) if first < second => HotseatOutcome::Winner(0),
+            (Some(first), Some(second)) if second < first => HotseatOutcome::Winner(1),
+            (Some(_), None) => HotseatOutcome::Winner(0),
+            (None, Some(_)) => HotseatOutcome::Winner(1),
+            _ => HotseatOutcome::Draw,
+        })
+    }
+}
+
+/// Run condition that is true while a hotseat round has finished and the second one is still to come
+pub fn hotseat_next_round(
+    active_mode: Option<Res<ActiveGameMode>>,
+    hotseat: Option<Res<HotseatMatch>>,
+) -> bool {
+    game_mode_is(GameMode::Hotseat)(active_mode)
+        && hotseat.is_some_and(|hotseat| !hotseat.results.is_empty() && !hotseat.is_over())
+}
+
+/// Run condition that is true once both hotseat rounds have been played
+pub fn hotseat_match_over(
+    active_mode: Option<Res<ActiveGameMode>>,
+    hotseat: Option<Res<HotseatMatch>>,
+) -> bool {
+    game_mode_is(GameMode::Hotseat)(active_mode)
+        && hotseat.is_some_and(|hotseat| hotseat.is_over())
+}
+
+/// Run condition that is true when the rustacean is moved by a player at this machine
+pub fn rustacean_controlled_here(
+    active_mode: Option<Res<ActiveGameMode>>,
+    session: Option<Res<NetSession>>,
+) -> bool {
+    game_mode_is(GameMode::Hotseat)(active_mode) || is_net_client(session)
+}
+
+/// A move picked by a human rustacean, carried out by whoever has the final say over the board
+#[derive(Event, Clone, Copy, Debug)]
+pub struct RustaceanMoveChosen(pub RustaceanMove);
+
+/// Marks the tiles the human rustacean may move to this turn, with the move picking the tile makes
+#[derive(Component)]
+pub struct LegalMove(pub RustaceanMove);
+
+/// Clickable marker just off the board for a way out the human rustacean may take
+#[derive(Component)]
+pub struct EscapeEdge {
+    pub x: i32,
+    pub y: i32,
+}
+
+pub fn start_hotseat_match(
+    mut commands: Commands,
+) {
+    commands.insert_resource(HotseatMatch::default());
+}
+
+/// Keeps the first round's board so the second round is played on the same one
+pub fn remember_hotseat_board(
+    mut hotseat: ResMut<HotseatMatch>,
+    rustacean_q: Query<(&RustaceanOrder, &RustaceanPos, Option<&RustaceanVariant>)>,
+    trap_q: Query<&GridTilePos, With<TrapTile>>,
+    special_q: Query<(&GridTilePos, &TileKind)>,
+) {
+    if hotseat.board.is_some() {
+        return;
+    }
+
+    let mut rustaceans: Vec<(&RustaceanOrder, &RustaceanPos, Option<&RustaceanVariant>)> = rustacean_q.iter().collect();
+    rustaceans.sort_unstable_by_key(|(order, _, _)| **order);
+
+    hotseat.board = Some(FixedBoard {
+        rustaceans: rustaceans.iter().map(|(_, pos, _)| (pos.x, pos.y)).collect(),
+        traps: trap_q.iter().map(|pos| (pos.x, pos.y)).collect(),
+        special_tiles: special_q.iter().map(|(pos, kind)| SpecialTile { pos: (pos.x, pos.y), kind: *kind }).collect(),
+        abilities: rustaceans.iter().map(|(_, _, variant)| variant.map(|variant| variant.ability)).collect(),
+    });
+}
+
+pub fn record_hotseat_round(
+    mut hotseat: ResMut<HotseatMatch>,
+    level_state: Res<State<LevelState>>,
+    level_taps: Res<CurrentLevelTaps>,
+) {
+    let result = match level_state.get() {
+        LevelState::LevelWin => Some(level_taps.0),
+        _ => None,
+    };
+    debug!("hotseat: round {} ended with {:?}", hotseat.results.len() + 1, result);
+    hotseat.results.push(result);
+}
+
+/// Ends the turn when no human controlled rustacean has anywhere to go, decided by whoever owns the board
+pub fn end_turn_if_rustacean_stuck(
+    mut mover: RustaceanMover,
+) {
+    if mover.current().is_none() {
+        debug!("hotseat: no rustacean has anywhere to go");
+        mover.finish_turn();
+    }
+}
+
+/// Highlights the tiles the rustacean whose move is next may move to and the ways off the board next to it
+pub fn highlight_rustacean_moves(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    theme: Res<Theme>,
+    grid_q: Single<(&Transform, &GridSize, &HextileF2FSize, &HexGridOrientation), Without<RustaceanPos>>,
+    tiles: TileLookup,
+    legal_q: Query<Entity, With<LegalMove>>,
+    escape_q: Query<Entity, With<EscapeEdge>>,
+    mover: RustaceanMover,
+) {
+    // the highlights of the rustacean that moved before are stale
+    clear_legal_moves(commands.reborrow(), legal_q, escape_q);
+
+    let (grid_transform, grid_size, f2f_size, orientation) = grid_q.into_inner();
+    let mut edges = Vec::new();
+
+    for next_move in mover.moves() {
+        let (RustaceanMove::Hop(x, y) | RustaceanMove::Escape(x, y)) = next_move else {
+            continue;
+        };
+        if !in_bounds((x, y), grid_size) {
+            edges.push((x, y));
+        } else if let Some(entity) = tiles.entity((x, y)) {
+            commands.entity(entity).insert(LegalMove(next_move));
+        }
+    }
+
+    let texture = asset_server.load("hex.png");
+    for (x, y) in edges {
+        let mut transform = Transform::from_translation(
+            grid_transform.translation + coord_to_world(x, y, grid_size, f2f_size, orientation).extend(0.)
+        );
+        transform.translation.z = 0.05;
+
+        commands.spawn((
+            EscapeEdge { x, y },
+            transform,
+            Pickable::default(),
+            Sprite {
+                image: texture.clone(),
+                color: theme.legal_move.with_alpha(0.6),
+                custom_size: Some(Vec2::new(f2f_size.0, f2f_size.to_height())),
+                ..default()
+            },
+        )).observe(escape_edge_observer());
+    }
+}
+
+pub fn clear_legal_moves(
+    mut commands: Commands,
+    legal_q: Query<Entity, With<LegalMove>>,
+    escape_q: Query<Entity, With<EscapeEdge>>,
+) {
+    for entity in legal_q.iter() {
+        commands.entity(entity).remove::<LegalMove>();
+    }
+    for entity in escape_q.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Picks a highlighted tile as the human rustacean's move, any other tile shakes
+pub fn rustacean_move_observer() -> impl FnMut(
+    Trigger<TileTapped>,
+    Query<(&GridTilePos, &Transform, Option<&LegalMove>, Option<&InvalidTapShake>)>,
+    Option<Res<ActiveGameMode>>,
+    Option<Res<NetSession>>,
+    Res<State<LevelState>>,
+    Res<State<TurnState>>,
+    EventWriter<RustaceanMoveChosen>,
+    Commands
+) {
+    move |
+        trigger: Trigger<TileTapped>,
+        tile_query: Query<(&GridTilePos, &Transform, Option<&LegalMove>, Option<&InvalidTapShake>)>,
+        game_mode: Option<Res<ActiveGameMode>>,
+        session: Option<Res<NetSession>>,
+        level_state: Res<State<LevelState>>,
+        turn_state: Res<State<TurnState>>,
+        mut move_events: EventWriter<RustaceanMoveChosen>,
+        mut commands: Commands
+    | {
+        // the trapper's taps are handled by tile_observer
+        if !rustacean_controlled_here(game_mode, session)
+            || *level_state != LevelState::InLevel
+            || *turn_state != TurnState::RustaceanTurn {
+            return;
+        }
+
+        let tile_entity = trigger.target();
+        let Ok((tile_pos, transform, legal_move, shake)) = tile_query.get(tile_entity) else {
+            return;
+        };
+
+        let Some(LegalMove(next_move)) = legal_move else {
+            debug!("rustacean_move_observer: illegal move to ({}, {})", tile_pos.x, tile_pos.y);
+            shake_tile(&mut commands, tile_entity, transform.translation, shake);
+            return;
+        };
+
+        move_events.write(RustaceanMoveChosen(*next_move));
+    }
+}
+
+fn escape_edge_observer() -> impl FnMut(Trigger<Pointer<Click>>, Query<&EscapeEdge>, Res<State<TurnState>>, EventWriter<RustaceanMoveChosen>) {
+    move |
+        trigger: Trigger<Pointer<Click>>,
+        edge_q: Query<&EscapeEdge>,
+        turn_state: Res<State<TurnState>>,
+        mut move_events: EventWriter<RustaceanMoveChosen>
+    | {
+        if *turn_state != TurnState::RustaceanTurn {
+            return;
+        }
+
+        if let Ok(edge) = edge_q.get(trigger.target()) {
+            move_events.write(RustaceanMoveChosen(RustaceanMove::Escape(edge.x, edge.y)));
+        }
+    }
+}
+
+/// Carries out the moves picked on this machine when the board is played locally
+pub fn apply_chosen_rustacean_moves(
+    mut move_events: EventReader<RustaceanMoveChosen>,
+    turn_state: Res<State<TurnState>>,
+    mut mover: RustaceanMover,
+) {
+    // picks arriving in the same frame were all made for the same rustacean, only one of them counts
+    let Some(RustaceanMoveChosen(next_move)) = move_events.read().last().copied() else {
+        return;
+    };
+    if *turn_state == TurnState::RustaceanTurn {
+        mover.apply(next_move);
+    }
+}
+
+/// Span of the HUD showing the round, the roles and whose move it is
+#[derive(Component)]
+pub struct HotseatText;
+
+pub fn spawn_hotseat_text(
+    world: &mut World,
+) {
+    spawn_hud_text(world, Node {
+        position_type: PositionType::Absolute,
+        top: Val::Px(0.0),
+        right: Val::Px(20.0),
+        ..default()
+    }, HotseatText);
+}
+
+pub fn hotseat_text_update(
+    hotseat: Res<HotseatMatch>,
+    turn_state: Res<State<TurnState>>,
+    localization: Res<Localization>,
+    mut query: Query<&mut TextSpan, With<HotseatText>>,
+) {
+    let player = |index: usize| (index + 1).to_string();
+    let round = hotseat.round();
+
+    let mut lines = vec![
+        localization.format("hotseat.round", &[("n", &(round + 1).to_string()), ("total", &HOTSEAT_ROUNDS.to_string())]),
+        localization.format("hotseat.trapper", &[("player", &player(HotseatMatch::trapper(round)))]),
+        localization.format("hotseat.rustacean", &[("player", &player(HotseatMatch::rustacean_player(round)))]),
+    ];
+
+    match hotseat.outcome() {
+        None => {
+            let mover = match turn_state.get() {
+                TurnState::PlayerTurn => HotseatMatch::trapper(round),
+                TurnState::RustaceanTurn => HotseatMatch::rustacean_player(round),
+            };
+            lines.push(localization.format("hotseat.turn", &[("player", &player(mover))]));
+        }
+        Some(outcome) => {
+            for index in 0..2 {
+                let score = match hotseat.taps_of(index) {
+                    Some(taps) => localization.plural("hotseat.taps", taps as u64),
+                    None => localization.text("hotseat.escaped"),
+                };
+                lines.push(localization.format("hotseat.score", &[("player", &player(index)), ("score", &score)]));
+            }
+            lines.push(match outcome {
+                HotseatOutcome::Winner(index) => localization.format("hotseat.winner", &[("player", &player(index))]),
+                HotseatOutcome::Draw => localization.text("hotseat.draw"),
+            });
+        }
+    }
+
+    let text = lines.join("\n");
+    for mut span in &mut query {
+        if text != span.0 {
+            **span = text.clone()
+        }
+    }
+}
@@ -1,40 +1,57 @@
-use bevy::{color::Color, ecs::{entity::Entity, query::With, system::{Commands, Query, Res, ResMut, Single}}, log::debug, sprite::Sprite, state::state::NextState, time::Time};
-use crate::{game::{game::{CurrentLevel, TotalGameStats}, level_setup::{RustaceanPos, TrapTile}, levels::{LevelEndTimer, LevelState}}, utils::hexgrid_utils::GridTilePos};
+use bevy::{ecs::{entity::Entity, query::With, system::{Commands, Query, Res, ResMut}}, log::debug, sprite::Sprite, state::state::{NextState, State}, time::Time};
+use crate::{game::{abilities::RevealedPath, difficulty::ActiveDifficulty, game::{CurrentLevel, TotalGameStats}, game_mode::{ActiveGameMode, GameMode}, level_setup::{RustaceanPos, TrapTile}, levels::{LevelEndTimer, LevelState}, rules::record_reached_level, tiles::TileKind, traps::{DecayingTrap, ReinforcedTrap}}, settings::theme::Theme, utils::hexgrid_utils::GridTilePos};
 
 pub fn reset_level(
     tile_q: Query<(Entity, &mut Sprite), With<GridTilePos>>,
-    rustacean_q: Single<Entity, With<RustaceanPos>>,
+    rustacean_q: Query<Entity, With<RustaceanPos>>,
     mut commands: Commands,
     mut total_stats: ResMut<TotalGameStats>,
     mut current_level: ResMut<CurrentLevel>,
+    difficulty: Res<ActiveDifficulty>,
+    game_mode: Res<ActiveGameMode>,
+    level_state: Res<State<LevelState>>,
     mut level_end_timer: ResMut<LevelEndTimer>,
     mut level_st: ResMut<NextState<LevelState>>,
     time: Res<Time>,
+    theme: Res<Theme>,
 ) {
     level_end_timer.0.tick(time.delta());
 
     
     debug!("checking timer");
-    if level_end_timer.0.elapsed_secs() >= 3.0 {
+    if level_end_timer.0.elapsed_secs() >= game_mode.0.level_reset_secs() {
         commands.remove_resource::<LevelEndTimer>();
-        for (entity, mut sprite) in tile_q {
-            if sprite.color != Color::WHITE {
-                sprite.color = Color::WHITE;
-            }
-                commands.entity(entity).remove::<TrapTile>();
-        }
-
-        let rustacean_entity = rustacean_q.into_inner();
-
-        commands.entity(rustacean_entity).despawn(); 
+        clear_board(&mut commands, tile_q, rustacean_q, &theme);
 
-        debug!("adding level");
-        current_level.0 += 1;
+        // an escape in time attack only costs time, the same level is played again on a fresh board
+        if *level_state == LevelState::LevelWin {
+            debug!("adding level");
+            current_level.0 += 1;
+        }
 
-        if current_level.0 as u64 > total_stats.record_level {
-            total_stats.record_level = current_level.0 as u64
+        // record levels belong to classic games, time attack keeps its own best score
+        if game_mode.0 == GameMode::Classic {
+            record_reached_level(&mut total_stats, difficulty.kind, current_level.0);
         }
-        
+
         level_st.set(LevelState::InLevel);
     }
-}
\ No newline at end of file
+}
+/// Takes every trap, special tile and rustacean off the board so the next level can be set up on it
+pub fn clear_board(
+    commands: &mut Commands,
+    tile_q: Query<(Entity, &mut Sprite), With<GridTilePos>>,
+    rustacean_q: Query<Entity, With<RustaceanPos>>,
+    theme: &Theme,
+) {
+    for (entity, mut sprite) in tile_q {
+        if sprite.color != theme.tile {
+            sprite.color = theme.tile;
+        }
+        commands.entity(entity).remove::<(TrapTile, DecayingTrap, ReinforcedTrap, TileKind, RevealedPath)>();
+    }
+
+    for entity in rustacean_q.iter() {
+        commands.entity(entity).despawn();
+    }
+}
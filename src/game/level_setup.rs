@@ -1,7 +1,7 @@
-use bevy::{asset::AssetServer, color::{palettes::css::DARK_GRAY, Color}, ecs::{component::Component, entity::Entity, observer::Trigger, query::Without, system::{Commands, Query, Res, ResMut, Single}}, math::Vec2, picking::events::{Click, Pointer}, sprite::Sprite, state::state::{NextState, State}, transform::components::GlobalTransform};
+use bevy::{asset::AssetServer, ecs::{component::Component, entity::Entity, event::{Event, EventWriter}, observer::Trigger, query::Has, system::{Commands, Query, Res, ResMut, Single}}, math::Vec2, picking::{events::{Click, Pointer}, Pickable}, sprite::Sprite, state::state::{NextState, State}, transform::components::Transform};
 use log::debug;
 
-use crate::{game::{game::{CurrentLevel, TotalGameStats}, levels::{CurrentLevelTaps, TurnState}}, utils::hexgrid_utils::{get_hex_horizontal_neighbor_pos, GridSize, GridTilePos, HextileF2FSize}};
+use crate::{game::{abilities::PlayerAbilities, difficulty::ActiveDifficulty, endless::{EndlessModifier, LevelRules}, events::TrapPlaced, game::TotalGameStats, game_mode::{ActiveGameMode, GameMode}, levels::{CurrentLevelTaps, LevelState, TurnState}, pathfinding::{BoardView, TileSet}, rules::record_tap, rustaceans::{trapped_rustaceans, RustaceanOrder, RustaceanVariant}, tile_feedback::{shake_tile, InvalidTapShake}, tile_lookup::TileLookup, tiles::{TileKind, TileKinds}, traps::{place_trap, TrapInventory, TrapKind}}, network::session::NetSession, settings::theme::Theme, utils::{hexgrid_utils::{get_hex_horizontal_neighbor_pos, GridSize, GridTilePos, HexGridOrientation, HextileF2FSize}, rng_utils}};
 
 
 #[derive(Component)]
@@ -15,10 +15,6 @@ impl RustaceanPos {
     pub fn new(x: i32, y: i32) -> Self {
         RustaceanPos { x, y }
     }
-
-    pub fn get_neighbor_pos(&self) -> [(i32, i32); 6] {
-        get_hex_horizontal_neighbor_pos(self.x, self.y)
-    }
 }
 
 #[derive(Component)]
@@ -27,11 +23,11 @@ pub struct TrapTile;
 pub fn spawn_rustacean(
     mut commands : Commands,
     asset_server: Res<AssetServer>,
-    grid_query: Single<(&HextileF2FSize, &GridSize)>,
-    tile_query: Query<(&GridTilePos, &GlobalTransform)>
+    grid_query: Single<(&Transform, &HextileF2FSize, &GridSize, &HexGridOrientation)>,
+    rules: Res<LevelRules>,
 ) {
     debug!("spawning_rustacean: getting grid size");
-    let (f2f_size, grid_size) = grid_query.into_inner();
+    let (grid_transform, f2f_size, grid_size, orientation) = grid_query.into_inner();
 
     let texture = asset_server.load("rustacean.png");
 
@@ -41,62 +37,102 @@ pub fn spawn_rustacean(
         (f2f_size.0 * size_mult) * 0.667_f32
     );
 
-    debug!("spawning_rustacean: getting tile positions");
-    for (tile_pos, global_transform) in tile_query.iter() {
-        if tile_pos.x == grid_size.cols/2 && tile_pos.y == (grid_size.rows/2) {
-            let mut transform = global_transform.compute_transform();
-            transform.translation.z = 0.1;
-
-            debug!("spawning_rustacean: spawning rustacean");
-
-            commands.spawn((
-                RustaceanPos::new(tile_pos.x as i32, tile_pos.y as i32),
-                transform,
-                Sprite{
-                    image: texture.clone(),
-                    custom_size: Some(size),
-                    ..Default::default()
-                }
-            ));
+    let starts = match &rules.fixed_board {
+        Some(board) => board.rustaceans.clone(),
+        None => rustacean_starts(rules.rustacean_count, grid_size, rules.has(EndlessModifier::HeadStart)),
+    };
+    let abilities = match &rules.fixed_board {
+        Some(board) => &board.abilities,
+        None => &rules.rustacean_abilities,
+    };
+
+    debug!("spawning_rustacean: spawning {} rustaceans", starts.len());
+
+    for (order, (x, y)) in starts.into_iter().enumerate() {
+        let tile_pos = GridTilePos::new(x, y);
+        // the grid may have been spawned this frame, so its tiles have no global transform yet
+        let mut transform = Transform::from_translation(
+            grid_transform.translation + tile_pos.coord_to_world(grid_size, f2f_size, orientation).extend(0.)
+        );
+        transform.translation.z = 0.1;
+
+        let mut rustacean = commands.spawn((
+            RustaceanPos::new(tile_pos.x, tile_pos.y),
+            RustaceanOrder(order),
+            transform,
+            // let clicks through to the tile underneath so taps on it can be rejected
+            Pickable::IGNORE,
+            Sprite{
+                image: texture.clone(),
+                custom_size: Some(size),
+                ..Default::default()
+            }
+        ));
+        if let Some(Some(ability)) = abilities.get(order) {
+            rustacean.insert(RustaceanVariant::new(*ability));
+        }
+    }
+}
+
+/// Start tiles of a generated board, the first rustacean in the center and the others on random inner tiles
+pub fn rustacean_starts(count: usize, grid_size: &GridSize, head_start: bool) -> Vec<(i32, i32)> {
+    let mut first = GridTilePos::new(grid_size.cols/2, grid_size.rows/2);
+    if head_start {
+        let neighbors = first.get_neighbor_pos();
+        let (x, y) = neighbors[rng_utils::random_range(0..neighbors.len())];
+        first = GridTilePos::new(x, y);
+    }
+    let mut starts = vec![(first.x, first.y)];
+
+    // starting on the border would be an instant escape, and there may be fewer inner tiles than rustaceans
+    let inner_tiles = ((grid_size.cols - 2) * (grid_size.rows - 2)).max(1) as usize;
+    while starts.len() < count.min(inner_tiles) {
+        let pos = (
+            rng_utils::random_range(1..grid_size.cols - 1),
+            rng_utils::random_range(1..grid_size.rows - 1),
+        );
+        if !starts.contains(&pos) {
+            starts.push(pos);
         }
     }
+
+    starts
 }
 
 pub fn prepare_tile_traps(
     mut commands : Commands,
-    current_level: Res<CurrentLevel>,
-    rustacean_pos: Single<&RustaceanPos>,
+    rules: Res<LevelRules>,
+    rustacean_q: Query<&RustaceanPos>,
     grid_size: Single<&GridSize>,
-    mut tile_query: Query<(Entity, &GridTilePos, &mut Sprite)>
+    mut tile_query: Query<(Entity, &GridTilePos, &mut Sprite, Option<&TileKind>)>,
+    tiles: TileLookup,
+    theme: Res<Theme>,
 ) {
     
-    debug!("preparing tiles: getting grid size");
-    let total_tile_amount = grid_size.count();
-
     debug!("preparing tiles: calculating trap number");
-    let num_of_traps = ((total_tile_amount as f32 * 0.25974).round() as u32 - 
-    (if current_level.0 < 21 { 
-        current_level.0 - 1
-    } 
-    else { 
-        20 
-    })) as usize;
-
-
-
+    let num_of_traps = rules.trap_count;
+    let open_edges = rules.has(EndlessModifier::OpenEdges);
 
     debug!("preparing tiles: calculating trap positions");
-    let trap_positions = loop {
-
-        let trap_positions = find_suitable_trap_positions(num_of_traps, &grid_size, &rustacean_pos);
-        // check if the rustacean is blocked in 
-        if rustacean_pos.get_neighbor_pos().iter().all(| (x, y) | trap_positions.contains(&(*x, *y)) ) {
-            debug!("Invalid!!!\nNeighbors: {:?}", rustacean_pos.get_neighbor_pos());
-            debug!("Traps: {:?}\n> continuing", trap_positions);
-            continue
-        } else {
-            break trap_positions
-        }
+    let trap_positions = if let Some(board) = &rules.fixed_board {
+        board.traps.clone()
+    } else {
+        let rustaceans: Vec<(i32, i32)> = rustacean_q.iter().map(|pos| (pos.x, pos.y)).collect();
+        let special_tiles = TileKinds::new(tile_query.iter().filter_map(|(_, pos, _, kind)| kind.map(|kind| ((pos.x, pos.y), *kind))));
+        let tiles: Vec<(i32, i32)> = tile_query.iter()
+            .filter(|(_, pos, _, _)| !special_tiles.is_rock((pos.x, pos.y)))
+            .map(|(_, pos, _, _)| (pos.x, pos.y))
+            .collect();
+        // traps stay off the rustaceans and the special tiles
+        let reserved: Vec<(i32, i32)> = tile_query.iter()
+            .filter(|(_, _, _, kind)| kind.is_some())
+            .map(|(_, pos, _, _)| (pos.x, pos.y))
+            .chain(rustaceans.iter().copied())
+            .collect();
+
+        let (trap_positions, rejected) = generate_trap_positions(num_of_traps, &grid_size, &tiles, &special_tiles, &reserved, &rustaceans, open_edges);
+        debug!("preparing tiles: {rejected} trap layouts rejected");
+        trap_positions
     };
 
 
@@ -105,80 +141,181 @@ pub fn prepare_tile_traps(
 
 
     debug!("preparing tiles: getting the tile entities");
-    for (entity, pos, mut sprite) in tile_query.iter_mut() {
-
-        debug!("preparing tiles: adding traps to tile ({}, {})",pos.x, pos.y);
-        // add traps to some
-        if trap_positions.contains(&(pos.x, pos.y)) {
-            sprite.color = Color::Srgba(DARK_GRAY);
-            commands.entity(entity).insert(
-                TrapTile
-            );
-        }
+    for (x, y) in trap_positions {
+        let Some(entity) = tiles.entity((x, y)) else {
+            continue;
+        };
+        let Ok((_, _, mut sprite, _)) = tile_query.get_mut(entity) else {
+            continue;
+        };
+
+        debug!("preparing tiles: adding traps to tile ({}, {})", x, y);
+        sprite.color = theme.trap;
+        commands.entity(entity).insert(
+            TrapTile
+        );
+    }
+}
+
+/// Fired on a tile entity when the player selects it, either by clicking it or through the tile cursor
+#[derive(Event)]
+pub struct TileTapped;
 
+pub fn tile_click_observer() -> impl FnMut(Trigger<Pointer<Click>>, Commands) {
+    move | trigger: Trigger<Pointer<Click>>, mut commands: Commands | {
+        commands.trigger_targets(TileTapped, trigger.target());
     }
 }
 
 pub fn tile_observer() -> impl FnMut(
-    Trigger<Pointer<Click>>,
-    Query<(&mut Sprite, &GridTilePos), Without<TrapTile>>,
-    Single<&RustaceanPos>,
+    Trigger<TileTapped>,
+    Query<(Entity, &GridTilePos, &Transform, Has<TrapTile>, Option<&TileKind>, Option<&InvalidTapShake>)>,
+    TileLookup,
+    Query<&RustaceanPos>,
     ResMut<TotalGameStats>,
+    Res<ActiveDifficulty>,
+    Res<ActiveGameMode>,
+    Option<Res<NetSession>>,
     ResMut<CurrentLevelTaps>,
+    ResMut<TrapInventory>,
+    ResMut<PlayerAbilities>,
+    Res<State<LevelState>>,
     Res<State<TurnState>>,  
     ResMut<NextState<TurnState>>,
+    EventWriter<TrapPlaced>,
     Commands
 ) {
     move |  
-        trigger: Trigger<Pointer<Click>>,
-        mut tile_query: Query<(&mut Sprite, &GridTilePos), Without<TrapTile>>,
-        rustacean_pos: Single<&RustaceanPos>,
+        trigger: Trigger<TileTapped>,
+        tile_query: Query<(Entity, &GridTilePos, &Transform, Has<TrapTile>, Option<&TileKind>, Option<&InvalidTapShake>)>,
+        tiles: TileLookup,
+        rustacean_q: Query<&RustaceanPos>,
         mut game_statistics: ResMut<TotalGameStats>,
+        difficulty: Res<ActiveDifficulty>,
+        game_mode: Res<ActiveGameMode>,
+        session: Option<Res<NetSession>>,
         mut level_taps: ResMut<CurrentLevelTaps>,
+        mut inventory: ResMut<TrapInventory>,
+        mut abilities: ResMut<PlayerAbilities>,
+        level_state: Res<State<LevelState>>,
         turn_state: Res<State<TurnState>>,  
         mut next_state: ResMut<NextState<TurnState>>,
+        mut trap_events: EventWriter<TrapPlaced>,
         mut commands: Commands
     | {
-        // check if its out turn
-        if *turn_state != TurnState::PlayerTurn{
+        // taps between levels are not feedback worthy
+        if *level_state != LevelState::InLevel {
+            return;
+        }
+
+        // online only the host traps, and only while the other player is connected
+        let is_client = session.as_ref().is_some_and(|session| session.is_client());
+        let waiting_online = session.as_ref().is_some_and(|session| session.is_client() || !session.connected);
+
+        // a human rustacean's taps belong to rustacean_move_observer
+        if (game_mode.0 == GameMode::Hotseat || is_client) && *turn_state == TurnState::RustaceanTurn {
             return;
         }
 
         let tile_entity = trigger.target();
-        let tile_query_result = tile_query.get_mut(tile_entity);
-
-        match tile_query_result {
-            Ok((mut spr, tile_pos)) => {
-
-                // if the player doesnt tap the tile that the rustacean is on
-                if !((tile_pos.x as i32, tile_pos.y as i32) == (rustacean_pos.x, rustacean_pos.y)) {
-                    // insert traptile component and color gray
-                    commands.entity(tile_entity).insert(TrapTile);
-                        spr.color = Color::Srgba(DARK_GRAY);
-                    // switch to RustaceanTurn turn state 
-                    next_state.set(TurnState::RustaceanTurn);
-                    // alter game stats
-                    game_statistics.tiles_tapped += 1;
-                    level_taps.0 += 1;
-                }
-            }
-            Err(_) => {}
-        } 
+        let Ok((_, tile_pos, transform, is_trap, kind, shake)) = tile_query.get(tile_entity) else {
+            return;
+        };
+
+        // the tap is invalid if its not our turn, the tile is trapped or a rock, or a rustacean is on it
+        if *turn_state != TurnState::PlayerTurn
+            || waiting_online
+            || is_trap
+            || kind == Some(&TileKind::Rock)
+            || rustacean_q.iter().any(|pos| (pos.x, pos.y) == (tile_pos.x, tile_pos.y)) {
+            debug!("tile_observer: invalid tap on ({}, {})", tile_pos.x, tile_pos.y);
+            shake_tile(&mut commands, tile_entity, transform.translation, shake);
+            return;
+        }
+
+        // an area trap also takes every neighbor a trap could go on
+        let kind = inventory.take();
+        let mut trapped_tiles = vec![(tile_entity, tile_pos.x, tile_pos.y)];
+        if kind == TrapKind::Area {
+            trapped_tiles.extend(get_hex_horizontal_neighbor_pos(tile_pos.x, tile_pos.y).into_iter()
+                .filter(|pos| tiles.state(*pos).is_some_and(|state| !state.trapped && state.kind != Some(TileKind::Rock)))
+                .filter(|pos| !rustacean_q.iter().any(|rustacean| (rustacean.x, rustacean.y) == *pos))
+                .filter_map(|(x, y)| tiles.entity((x, y)).map(|entity| (entity, x, y))));
+        }
+        debug!("tile_observer: {:?} trap on {} tiles", kind, trapped_tiles.len());
+
+        // insert traptile component and let the placement animation color it
+        let entities: Vec<Entity> = trapped_tiles.iter().map(|(entity, _, _)| *entity).collect();
+        place_trap(&mut commands, kind, &entities);
+        // switch to RustaceanTurn turn state, unless a double trap leaves another one to place
+        if abilities.extra_traps > 0 {
+            abilities.extra_traps -= 1;
+        } else {
+            next_state.set(TurnState::RustaceanTurn);
+        }
+        // alter game stats
+        record_tap(&mut game_statistics, difficulty.kind);
+        level_taps.0 += 1;
+        for (_, x, y) in trapped_tiles {
+            trap_events.write(TrapPlaced { x, y });
+        }
     }
 }
 
-pub fn find_suitable_trap_positions(num_of_traps: usize, grid_size: &GridSize, rustacean_pos: &RustaceanPos) -> Vec<(i32, i32)> {
+/// Random trap tiles that leave every rustacean a way to move, and how many layouts were thrown away first.
+///
+/// `tiles` are the tiles a trap may go on, rocks left out. Jumping only ever gets a rustacean further
+/// so it isnt taken into account.
+pub fn generate_trap_positions(
+    num_of_traps: usize,
+    grid_size: &GridSize,
+    tiles: &[(i32, i32)],
+    special_tiles: &TileKinds,
+    reserved: &[(i32, i32)],
+    rustaceans: &[(i32, i32)],
+    open_edges: bool,
+) -> (Vec<(i32, i32)>, u32) {
+    let mut rejected = 0;
+
+    loop {
+        let trap_positions = find_suitable_trap_positions(num_of_traps, grid_size, reserved, open_edges);
+        // check if any rustacean is blocked in
+        let open_tiles = TileSet::from_tiles(*grid_size, tiles.iter().copied().filter(|pos| !trap_positions.contains(pos)));
+        let board = BoardView { grid_size: *grid_size, open_tiles: &open_tiles, tiles: special_tiles };
+        if trapped_rustaceans(rustaceans, &[], &TileSet::new(*grid_size), &board).contains(&true) {
+            debug!("Invalid!!!\nRustaceans: {:?}", rustaceans);
+            debug!("Traps: {:?}\n> continuing", trap_positions);
+            rejected += 1;
+            continue
+        }
+        return (trap_positions, rejected);
+    }
+}
+
+/// Random trap tiles, never on a `reserved` tile
+pub fn find_suitable_trap_positions(num_of_traps: usize, grid_size: &GridSize, reserved: &[(i32, i32)], open_edges: bool) -> Vec<(i32, i32)> {
+
+    // with open edges only the inner tiles can take traps, so there may be fewer spots than traps
+    let num_of_traps = if open_edges {
+        num_of_traps.min(((grid_size.cols - 2) * (grid_size.rows - 2)) as usize / 2)
+    } else {
+        num_of_traps
+    };
+    // special tiles take room too, leave at least half of what is left open
+    let free_tiles = grid_size.count().saturating_sub(reserved.len());
+    let num_of_traps = num_of_traps.min(free_tiles / 2);
 
-    let mut trap_positions: Vec<(i32, i32)> = Vec::with_capacity(num_of_traps as usize);
+    let mut trap_positions: Vec<(i32, i32)> = Vec::with_capacity(num_of_traps);
 
     while trap_positions.len() < num_of_traps { 
-        let x = rand::random_range(0..grid_size.cols);
-        let y = rand::random_range(0..grid_size.rows);
+        let x = rng_utils::random_range(0..grid_size.cols);
+        let y = rng_utils::random_range(0..grid_size.rows);
 
         // check is selected position is the same as some previous position, 
-        // or if its in the position of the rustacean
+        // or if its in the position of a rustacean or special tile
         if trap_positions.contains(&(x, y)) 
-            || (x == rustacean_pos.x && y == rustacean_pos.y) {
+            || reserved.contains(&(x, y))
+            || (open_edges && GridTilePos::new(x, y).is_border(grid_size)) {
             debug!("Invalid!!!\nAttempted position: {x}, {y}\n> continuing");
             continue
         }
@@ -1,5 +1,5 @@
-use bevy::{ ecs::{ component::Component, entity::Entity, query::With, resource::Resource, system::{Commands, Query, Res, ResMut, Single}, world::World }, state::state::{ NextState, States}, text::{TextFont, TextSpan}, time::{Stopwatch, Time}, ui::widget::Text, utils::default};
-use crate::{game::{game::{AppState, CurrentLevel}, level_setup::RustaceanPos}, utils::hexgrid_utils::GridSize};
+use bevy::{ ecs::{ bundle::Bundle, component::Component, entity::Entity, event::EventWriter, query::With, resource::Resource, system::{Commands, Query, Res, ResMut, Single}, world::{EntityWorldMut, World} }, state::state::{ NextState, State, States}, text::{TextColor, TextSpan}, time::{Stopwatch, Time}, ui::{widget::Text, Node}};
+use crate::{game::{endless::EndlessText, events::{GameOver, LevelStarted}, game::{AppState, CurrentLevel}, game_mode::ActiveGameMode, level_setup::RustaceanPos, rating::LevelTapsText, rustaceans::{RustaceanAbilityText, RustaceansText}, traps::TrapInventoryText}, settings::{locale::Localization, theme::Theme}, utils::hexgrid_utils::GridSize};
 
 #[derive(Component)]
 pub struct LevelText;
@@ -29,20 +29,83 @@ pub enum TurnState {
 pub fn spawn_current_stats_text(
     world: &mut World,
 ) {
+    let hud_text = world.resource::<Theme>().hud_text;
+    let localization = world.resource::<Localization>();
+    let label = localization.text("hud.level");
+    let (label_font, value_font) = (localization.text_font(42.0), localization.text_font(33.0));
+
     world.spawn((
-        Text::new("Level: "),        
-        TextFont {
-            font_size: 42.0,
-            ..default()
-        },
+        Text::new(label),        
+        label_font,
+        TextColor(hud_text),
         LevelText,
+    )).with_children(|parent| {
+        parent.spawn((
+            TextSpan::default(),
+            value_font.clone(),
+            TextColor(hud_text),
+            LevelText
+        ));
+        parent.spawn((
+            TextSpan::default(),
+            value_font.clone(),
+            TextColor(hud_text),
+            LevelTapsText
+        ));
+        parent.spawn((
+            TextSpan::default(),
+            value_font.clone(),
+            TextColor(hud_text),
+            EndlessText
+        ));
+        parent.spawn((
+            TextSpan::default(),
+            value_font.clone(),
+            TextColor(hud_text),
+            RustaceansText
+        ));
+        parent.spawn((
+            TextSpan::default(),
+            value_font.clone(),
+            TextColor(hud_text),
+            RustaceanAbilityText
+        ));
+        parent.spawn((
+            TextSpan::default(),
+            value_font,
+            TextColor(hud_text),
+            TrapInventoryText
+        ));
+    });
+}
+
+/// Spawns a part of the HUD, it is despawned together with the rest of the level HUD
+pub fn spawn_hud_node(
+    world: &mut World,
+    bundle: impl Bundle,
+) -> EntityWorldMut<'_> {
+    world.spawn((bundle, LevelText))
+}
+
+/// Spawns a line of HUD text laid out by `node`, the span that gets updated carries `marker`
+pub fn spawn_hud_text(
+    world: &mut World,
+    node: Node,
+    marker: impl Component,
+) {
+    let hud_text = world.resource::<Theme>().hud_text;
+    let font = world.resource::<Localization>().text_font(42.0);
+
+    spawn_hud_node(world, (
+        Text::default(),
+        font.clone(),
+        TextColor(hud_text),
+        node,
     )).with_child((
         TextSpan::default(),
-        TextFont {
-            font_size: 33.0,
-            ..default()
-        },
-        LevelText
+        font,
+        TextColor(hud_text),
+        marker,
     ));
 }
 
@@ -63,7 +126,7 @@ pub fn level_text_update(
     let level = current_level.0.to_string();
     for mut span in &mut query {
         if level != span.0 {
-            **span = format!("{level}")
+            **span = level.clone()
         }
     }
 
@@ -75,6 +138,21 @@ pub fn run_levelend_timer(
     commands.insert_resource(LevelEndTimer(Stopwatch::new()));
 }
 
+pub fn announce_level_start(
+    current_level: Res<CurrentLevel>,
+    mut level_events: EventWriter<LevelStarted>,
+) {
+    level_events.write(LevelStarted { level: current_level.0 });
+}
+
+/// Ends the run, on the same level ends that [`goto_main_menu`] goes back to the main menu after
+pub fn announce_game_over(
+    level_state: Res<State<LevelState>>,
+    mut game_over_events: EventWriter<GameOver>,
+) {
+    game_over_events.write(GameOver { won: *level_state.get() == LevelState::LevelWin });
+}
+
 pub fn set_player_turn(
     mut turn_st: ResMut<NextState<TurnState>>,
 ) {
@@ -90,19 +168,22 @@ pub(crate) fn reset_current_level_taps(
 pub fn goto_main_menu(
     mut commands: Commands,
     grid_q: Single<Entity, With<GridSize>>,
-    rustacean_q: Single<Entity, With<RustaceanPos>>,
+    rustacean_q: Query<Entity, With<RustaceanPos>>,
     mut level_st: ResMut<NextState<LevelState>>,
     mut app_st: ResMut<NextState<AppState>>,
     mut level_end_timer: ResMut<LevelEndTimer>,
+    game_mode: Res<ActiveGameMode>,
     time: Res<Time>,
 ) {
     level_end_timer.0.tick(time.delta());
 
-    if level_end_timer.0.elapsed_secs() >= 2.0 {
+    if level_end_timer.0.elapsed_secs() >= game_mode.0.game_over_secs() {
         commands.remove_resource::<LevelEndTimer>();
         
         commands.entity(grid_q.into_inner()).despawn();
-        commands.entity(rustacean_q.into_inner()).despawn();
+        for entity in rustacean_q.iter() {
+            commands.entity(entity).despawn();
+        }
 
         level_st.set(LevelState::OutOfLevel);
         app_st.set(AppState::MainMenu);
@@ -1,5 +1,29 @@
 pub mod game;
+pub mod events;
+pub mod difficulty;
+pub mod endless;
+pub mod game_mode;
+pub mod time_attack;
+pub mod puzzle;
+pub mod score;
+pub mod rating;
+pub mod hotseat;
+pub mod rustaceans;
+pub mod tiles;
+pub mod traps;
+pub mod abilities;
+pub mod solver;
+pub mod strategy;
+pub mod rules;
+pub mod headless;
+pub mod simulation;
 pub mod levels;
 pub mod level_setup;
 pub mod pathfinding;
-pub mod level_reset;
\ No newline at end of file
+#[cfg(any(test, feature = "bench-reference"))]
+pub mod hashed_pathfinding;
+pub mod level_reset;
+pub mod tile_feedback;
+pub mod replay;
+pub mod tile_cursor;
+pub mod tile_lookup;
\ No newline at end of file
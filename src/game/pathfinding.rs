@@ -1,7 +1,7 @@
-use std::{collections::{HashMap, HashSet, VecDeque}, fmt::Display};
+use std::{cmp::Reverse, collections::BinaryHeap, fmt::Display};
 use anyhow::Context;
-use bevy::{color::{palettes::css::{LIGHT_GREEN, RED}, Color}, ecs::{query::{With, Without}, system::{Query, ResMut}}, math::Vec2, log::error, sprite::Sprite, state::state::NextState, transform::components::{GlobalTransform, Transform}};
-use crate::{game::{game::TotalGameStats, level_setup::{RustaceanPos, TrapTile}, levels::{LevelState, TurnState}}, utils::hexgrid_utils::{get_hex_horizontal_neighbor_pos, GridSize, GridTilePos, HexGridOrientation, HextileF2FSize}};
+use bevy::{ecs::{change_detection::DetectChangesMut, entity::Entity, event::EventWriter, query::Has, system::{Query, Res, ResMut, SystemParam}}, math::Vec2, log::{debug, error}, state::state::NextState, transform::components::Transform};
+use crate::{game::{difficulty::RustaceanAi, endless::LevelRules, events::{LevelEnded, RustaceanEscaped, RustaceanMoved, RustaceanTrapped}, level_setup::{RustaceanPos, TrapTile}, levels::{CurrentLevelTaps, LevelState, TurnState}, rules::{level_outcome, move_effect, next_to_move, open_tiles, ready_jumps}, rustaceans::{trapped_rustaceans, RustaceanAbility, RustaceanOrder, RustaceanTurnProgress, RustaceanVariant}, strategy::StrategyRegistry, tile_lookup::TileLookup, tiles::{hex_distance, HexDirection, TileKind, TileKinds, MUD_COST}, traps::ReinforcedTrap}, utils::{hexgrid_utils::{get_hex_horizontal_neighbor_pos, GridSize, GridTilePos, HexGridOrientation, HextileF2FSize}, rng_utils}};
 
 pub fn coord_to_world(x: i32, y: i32, grid_size: &GridSize, hextile_f2f_size: &HextileF2FSize, orientation: &HexGridOrientation) -> Vec2 {
     match orientation {
@@ -40,7 +40,7 @@ pub fn coord_to_world(x: i32, y: i32, grid_size: &GridSize, hextile_f2f_size: &H
                 }
             );
             
-            return Vec2::new(x,y);
+            Vec2::new(x,y)
         },
         HexGridOrientation::Horizontal => {
             error!("Error: not functional yet, fucking wait");
@@ -49,39 +49,14 @@ pub fn coord_to_world(x: i32, y: i32, grid_size: &GridSize, hextile_f2f_size: &H
     }
 }
 
-/// Get the coordiantes of all the out of bound pseudo-tiles which are adjascent to the grid border tiles 
-pub fn escape_targets(grid_size: &GridSize) -> Vec<(i32, i32)> {
-    let mut escapes = Vec::new();
-
-    for x in 0..grid_size.cols {
-        for y in 0..grid_size.rows {
-            let pos = GridTilePos::new(x, y);
-            // iterates over x y and creates a GridTilePos to then check if .is_border()
-            if pos.is_border(grid_size) {
-                // if it .is_border() -> we iterate over its neighbors
-                for n in pos.get_neighbor_pos() {
-                    if !in_bounds(n, grid_size) {
-                        // if its neighbors are out of grid bounds we push them into the escapes
-                        escapes.push(n);
-                    }
-                }
-            }
-        }
-    }
-
-    // remove duplicates just in case
-    escapes.sort_unstable();
-    escapes.dedup();
-    escapes
-}
-
 pub fn in_bounds(pos: (i32, i32), grid_size: &GridSize) -> bool {
     pos.0 >= 0
-        && pos.0 < grid_size.cols as i32
+        && pos.0 < grid_size.cols
         && pos.1 >= 0
-        && pos.1 < grid_size.rows as i32
+        && pos.1 < grid_size.rows
 }
 
+#[derive(Debug, PartialEq, Eq)]
 pub enum Path {
     Escaped(i32,i32),
     Found(Vec<(i32,i32)>),
@@ -105,173 +80,837 @@ impl Display for Path {
     }
 }
 
-/// BFS that treats "escape positions" (which may be out-of-bounds) as valid goals.
-/// When a neighbor is an escape, we immediately reconstruct a path that *ends* in that escape
-/// coordinate and return it.
-pub fn pathfind(
-    rustacean_pos: &RustaceanPos,
-    grid_size: &GridSize,
-    tile_q: Query<&GridTilePos, Without<TrapTile>>,
-) -> (Path, [(i32, i32); 6], Option<HashSet<(i32,i32)>>) {
-    // starting position of rustacean
-    let start = (rustacean_pos.x, rustacean_pos.y);
-    // gets an array of coordinates of all adjascent hexagons (whether in bounds or not)
-    let start_neighbors = rustacean_pos.get_neighbor_pos();
-    // we take the out of bounds neighbor tiles, and put them in a hashset
-    let escape_set: HashSet<_> = escape_targets(grid_size).into_iter().collect();
-
-    // quick check: if any immediate neighbor of the rustacean at the start is an escape, return that (escaped)
-    for &(nx, ny) in &start_neighbors {
-        if escape_set.contains(&(nx, ny)) {
-            return (Path::Escaped(nx, ny), start_neighbors, None);
+/// What the rustacean does on its turn
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum RustaceanMove {
+    Hop(i32, i32),
+    Escape(i32, i32),
+    Trapped,
+}
+
+/// A set of tiles on one board, one flag per tile in [`GridTilePos::to_index`] order.
+///
+/// The searches look a tile up for every move they try, an index into a flat array is far cheaper than a hash.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TileSet {
+    grid_size: GridSize,
+    flags: Vec<bool>,
+    len: usize,
+}
+
+impl TileSet {
+    /// An empty set on a board of `grid_size`
+    pub fn new(grid_size: GridSize) -> Self {
+        TileSet { grid_size, flags: vec![false; grid_size.count()], len: 0 }
+    }
+
+    /// The set of `tiles`, the ones off the board are left out
+    pub fn from_tiles(grid_size: GridSize, tiles: impl IntoIterator<Item = (i32, i32)>) -> Self {
+        let mut set = TileSet::new(grid_size);
+        for pos in tiles {
+            set.insert(pos);
         }
+        set
     }
 
-    // collect open tiles
-    let open_tiles: HashSet<(i32, i32)> =
-        tile_q.iter().map(|p| (p.x, p.y) ).collect();
+    fn index(&self, pos: (i32, i32)) -> Option<usize> {
+        in_bounds(pos, &self.grid_size).then(|| GridTilePos::new(pos.0, pos.1).to_index(&self.grid_size))
+    }
 
-    // BFS init: mark start visited
-    let mut queue: VecDeque<(i32, i32)> = VecDeque::new();
-    // collection of all arrivals and Option<departures>
-    let mut came_from: HashMap<(i32, i32), Option<(i32, i32)>> = HashMap::new();
-    came_from.insert(start, None);
+    pub fn contains(&self, pos: &(i32, i32)) -> bool {
+        self.index(*pos).is_some_and(|index| self.flags[index])
+    }
 
-    // enqueue neighbors of start first (so we explore immediate moves first)
-    // they all go into the came_from hashmap, but with different lineages
-    for &(nx, ny) in &start_neighbors {
-        // only enqueue if it's walkable (in open_tiles) and not visited
-        if open_tiles.contains(&(nx, ny)) && !came_from.contains_key(&(nx, ny)) {
-            came_from.insert((nx, ny), Some(start));
-            queue.push_back((nx, ny));
+    /// Adds `pos`, false if it was in the set already or is off the board
+    pub fn insert(&mut self, pos: (i32, i32)) -> bool {
+        match self.index(pos) {
+            Some(index) if !self.flags[index] => {
+                self.flags[index] = true;
+                self.len += 1;
+                true
+            }
+            _ => false,
         }
     }
 
-    // BFS loop
-    while let Some(current) = queue.pop_front() {
-        // pop the queue and get the current coordinates
-        for n in get_hex_horizontal_neighbor_pos(current.0, current.1).iter().copied() {
-            // get neighbors of the current coordinate
-            if escape_set.contains(&n) {
-                // If a neighbor is in the escape coords list, end the operation and return the reconstructed path ending in that escape coord
-                came_from.insert(n, Some(current));
-                // set departure for the escape node in came_from so reconstruction works
-
-                // reconstruct path from start -> ... -> current -> escape(n)
-                let mut path = Vec::new();
-                let mut cur = Some(n);
-                while let Some(p) = cur {
-                    path.push(p);
-                    cur = if !(came_from[&p] == Some(start)) {
-                        came_from[&p]
-                    } else {
-                        None
-                    }
+    /// Takes `pos` out, false if it wasnt in the set
+    pub fn remove(&mut self, pos: &(i32, i32)) -> bool {
+        match self.index(*pos) {
+            Some(index) if self.flags[index] => {
+                self.flags[index] = false;
+                self.len -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Every tile in the set, row by row from the bottom
+    pub fn iter(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.flags.iter()
+            .enumerate()
+            .filter(|(_, flag)| **flag)
+            .map(|(index, _)| {
+                let pos = GridTilePos::from_index(index, &self.grid_size);
+                (pos.x, pos.y)
+            })
+    }
+}
+
+/// The board as far as the rustacean's moves are concerned
+#[derive(Clone, Copy)]
+pub struct BoardView<'a> {
+    pub grid_size: GridSize,
+    /// Tiles without a trap, rock or rustacean on them
+    pub open_tiles: &'a TileSet,
+    pub tiles: &'a TileKinds,
+}
+
+impl BoardView<'_> {
+    /// Where a rustacean stepping into `pos` ends up, teleporters send it on to their free pair
+    pub fn landing(&self, pos: (i32, i32)) -> (i32, i32) {
+        match self.tiles.kind(pos) {
+            Some(TileKind::Teleporter { pair }) if self.open_tiles.contains(&pair) => pair,
+            _ => pos,
+        }
+    }
+
+    /// Every move a rustacean on `pos` can make, with what it costs the weighted search
+    pub fn moves(&self, pos: (i32, i32)) -> Vec<(RustaceanMove, u32)> {
+        let neighbors = match self.tiles.kind(pos) {
+            Some(TileKind::OneWay { direction }) => vec![direction.step(pos.0, pos.1)],
+            _ => get_hex_horizontal_neighbor_pos(pos.0, pos.1).to_vec(),
+        };
+
+        neighbors.into_iter()
+            .filter_map(|target| self.move_onto(target))
+            .collect()
+    }
+
+    /// Jumps a rustacean on `pos` can make over a neighboring trap in `jumpable`, onto the tile right behind it
+    pub fn jumps(&self, pos: (i32, i32), jumpable: &TileSet) -> Vec<(RustaceanMove, u32)> {
+        let directions = match self.tiles.kind(pos) {
+            Some(TileKind::OneWay { direction }) => vec![direction],
+            _ => HexDirection::ALL.to_vec(),
+        };
+
+        directions.into_iter()
+            .filter_map(|direction| {
+                let over = direction.step(pos.0, pos.1);
+                if !jumpable.contains(&over) {
+                    return None;
                 }
-                path.reverse();
+                self.move_onto(direction.step(over.0, over.1))
+            })
+            .collect()
+    }
+
+    /// The move that ends on `(x, y)`, if the tile can be entered
+    fn move_onto(&self, (x, y): (i32, i32)) -> Option<(RustaceanMove, u32)> {
+        if !in_bounds((x, y), &self.grid_size) {
+            // exits close the edge of the board
+            return (!self.tiles.has_exits()).then_some((RustaceanMove::Escape(x, y), 1));
+        }
+        if !self.open_tiles.contains(&(x, y)) {
+            return None;
+        }
+        Some(match self.tiles.kind((x, y)) {
+            Some(TileKind::Exit) => (RustaceanMove::Escape(x, y), 1),
+            Some(TileKind::Mud) => (RustaceanMove::Hop(x, y), MUD_COST),
+            _ => (RustaceanMove::Hop(x, y), 1),
+        })
+    }
+}
+
+/// Picks the rustacean's next move according to its AI tier
+pub fn choose_move(
+    ai: RustaceanAi,
+    start: (i32, i32),
+    board: &BoardView,
+) -> RustaceanMove {
+    let moves = board.moves(start);
+
+    // every tier takes the exit when it is standing next to one
+    if let Some((escape, _)) = moves.iter().find(|(next_move, _)| matches!(next_move, RustaceanMove::Escape(..))) {
+        return *escape;
+    }
 
-                return (Path::Found(path), start_neighbors, None);
-                // I am the best programmer
+    let hops: Vec<((i32, i32), u32)> = moves.iter()
+        .filter_map(|(next_move, cost)| match next_move {
+            RustaceanMove::Hop(x, y) => Some(((*x, *y), *cost)),
+            _ => None,
+        })
+        .collect();
+
+    if hops.is_empty() {
+        return RustaceanMove::Trapped;
+    }
+
+    let (x, y) = match ai {
+        RustaceanAi::Wanderer => hops[rng_utils::random_range(0..hops.len())].0,
+        RustaceanAi::Greedy => hops.iter()
+            .min_by_key(|(pos, _)| exit_estimate(board.landing(*pos), board))
+            .expect("Error: hops was checked to not be empty")
+            .0,
+        RustaceanAi::Pathfinder => match pathfind(start, board) {
+            Path::Found(path) => path[0],
+            // no way out, so just keep moving while there is room
+            Path::NotFound | Path::Escaped(..) => hops[0].0,
+        },
+        RustaceanAi::Strategist => strategic_hop(&hops, board),
+    };
+
+    RustaceanMove::Hop(x, y)
+}
+
+/// Like [`choose_move`] for a rustacean that may also take one of `jumps` this turn.
+///
+/// A jump is only taken when it leaves right away or lands closer to a way out than any regular move does,
+/// or when there is nothing else to do, otherwise the rustacean saves it and moves as its AI tier would.
+pub fn choose_move_or_jump(
+    ai: RustaceanAi,
+    start: (i32, i32),
+    board: &BoardView,
+    jumps: &[(RustaceanMove, u32)],
+) -> RustaceanMove {
+    let moves = board.moves(start);
+    if jumps.is_empty() || moves.iter().any(|(next_move, _)| matches!(next_move, RustaceanMove::Escape(..))) {
+        return choose_move(ai, start, board);
+    }
+    if let Some((escape, _)) = jumps.iter().find(|(next_move, _)| matches!(next_move, RustaceanMove::Escape(..))) {
+        return *escape;
+    }
+    if moves.is_empty() || ai == RustaceanAi::Wanderer && rng_utils::random_range(0..moves.len() + jumps.len()) < jumps.len() {
+        return jumps[rng_utils::random_range(0..jumps.len())].0;
+    }
+
+    let distances = exit_distances(board);
+    let closest = |candidates: &[(RustaceanMove, u32)]| candidates.iter()
+        .filter_map(|(next_move, cost)| match next_move {
+            RustaceanMove::Hop(x, y) => distances.get(board.landing((*x, *y))).map(|distance| (*next_move, cost + distance)),
+            _ => None,
+        })
+        .min_by_key(|(_, distance)| *distance);
+
+    match (closest(jumps), closest(&moves)) {
+        (Some((jump, jumping)), Some((_, walking))) if jumping < walking => jump,
+        (Some((jump, _)), None) => jump,
+        _ => choose_move(ai, start, board),
+    }
+}
+
+/// Picks the hop closest to an exit, and among equally close ones the one with the most
+/// onward tiles that are closer still, so a single trap cant block every shortest route
+fn strategic_hop(
+    hops: &[((i32, i32), u32)],
+    board: &BoardView,
+) -> (i32, i32) {
+    let distances = exit_distances(board);
+
+    let onward_routes = |pos: (i32, i32), distance: u32| {
+        board.moves(pos).iter()
+            .filter(|(next_move, _)| match next_move {
+                RustaceanMove::Hop(x, y) => distances.get(board.landing((*x, *y))).is_some_and(|d| d < distance),
+                _ => true,
+            })
+            .count()
+    };
+
+    let reachable = hops.iter()
+        .filter_map(|(pos, cost)| {
+            let landing = board.landing(*pos);
+            distances.get(landing).map(|distance| (*pos, cost + distance, onward_routes(landing, distance)))
+        })
+        .min_by_key(|(_, distance, onward)| (*distance, Reverse(*onward)));
+
+    match reachable {
+        Some((pos, _, _)) => pos,
+        // walled in: stay on the roomiest tile to last as long as possible
+        None => hops.iter()
+            .max_by_key(|(pos, _)| board.moves(board.landing(*pos)).len())
+            .expect("Error: hops must not be empty")
+            .0,
+    }
+}
+
+/// Cost from every tile to the nearest way out, in [`GridTilePos::to_index`] order
+struct ExitDistances {
+    grid_size: GridSize,
+    /// `u32::MAX` where no way out can be reached
+    distances: Vec<u32>,
+}
+
+impl ExitDistances {
+    fn get(&self, pos: (i32, i32)) -> Option<u32> {
+        in_bounds(pos, &self.grid_size)
+            .then(|| self.distances[GridTilePos::new(pos.0, pos.1).to_index(&self.grid_size)])
+            .filter(|distance| *distance != u32::MAX)
+    }
+}
+
+fn exit_distances(board: &BoardView) -> ExitDistances {
+    let index_of = |(x, y): (i32, i32)| GridTilePos::new(x, y).to_index(&board.grid_size);
+    let mut distances = vec![u32::MAX; board.grid_size.count()];
+    let mut incoming: Vec<Vec<(usize, u32)>> = vec![Vec::new(); board.grid_size.count()];
+    let mut heap = BinaryHeap::new();
+
+    // tiles next to a way out are one move from leaving, the rest is searched backwards from them
+    for pos in board.open_tiles.iter() {
+        let index = index_of(pos);
+        for (next_move, cost) in board.moves(pos) {
+            match next_move {
+                RustaceanMove::Escape(..) => {
+                    if cost < distances[index] {
+                        distances[index] = cost;
+                        heap.push(Reverse((cost, index)));
+                    }
+                }
+                RustaceanMove::Hop(x, y) => incoming[index_of(board.landing((x, y)))].push((index, cost)),
+                RustaceanMove::Trapped => {}
             }
+        }
+    }
 
-            // otherwise, standard BFS into in-bounds open tiles
-            if open_tiles.contains(&n) && !came_from.contains_key(&n) {
-                came_from.insert(n, Some(current));
-                queue.push_back(n);
+    while let Some(Reverse((distance, current))) = heap.pop() {
+        if distances[current] < distance {
+            continue;
+        }
+        for &(from, cost) in &incoming[current] {
+            let through = distance + cost;
+            if through < distances[from] {
+                distances[from] = through;
+                heap.push(Reverse((through, from)));
             }
         }
     }
 
-    return (Path::NotFound, start_neighbors, Some(open_tiles))
+    ExitDistances { grid_size: board.grid_size, distances }
 }
 
-pub fn pathfind_and_move(
-    mut rustacean_pos_q: Query<(&mut RustaceanPos, &mut Transform)>,
-    grid_size_q: Query<(&GridSize, &HextileF2FSize, &HexGridOrientation)>,
-    tile_q: Query<&GridTilePos, Without<TrapTile>>,
-    tile_transform_q: Query<(&GridTilePos, &GlobalTransform)>,
-    mut tile_sprite_q: Query<&mut Sprite, With<GridTilePos>>,
-    mut next_turnstate: ResMut<NextState<TurnState>>,
-    mut next_levelstate: ResMut<NextState<LevelState>>,
-    mut game_stats: ResMut<TotalGameStats>
-) {
-    let (mut rustacean_pos, mut transform) = rustacean_pos_q.single_mut().context("Looking for a single RustaceanPos from query").unwrap();
-    let (grid_size, tile_width, orientation) = grid_size_q.single().context("Looking for a single RustaceanPos from query").unwrap();
+/// Rough number of hops from `pos` to a way out, ignoring traps
+fn exit_estimate(pos: (i32, i32), board: &BoardView) -> i32 {
+    if board.tiles.has_exits() {
+        return board.tiles.exits()
+            .map(|exit| hex_distance(pos, exit))
+            .min()
+            .unwrap_or(i32::MAX);
+    }
 
-    let (path, current_neighbors, open_tiles) = pathfind(&rustacean_pos, grid_size, tile_q);
+    pos.0
+        .min(board.grid_size.cols - 1 - pos.0)
+        .min(pos.1)
+        .min(board.grid_size.rows - 1 - pos.1)
+}
 
-    match path {
-        Path::Found(vec) => {
-            for neighbor in current_neighbors {
-                if vec.contains(&neighbor) {
+/// Cheapest way out for the rustacean on `start`, the path ends on the tile it leaves through.
+///
+/// Mud costs more than a plain hop, and among equally cheap paths the one found first wins,
+/// so on a board without special tiles this picks the same path as a breadth first search.
+pub fn pathfind(
+    start: (i32, i32),
+    board: &BoardView,
+) -> Path {
+    // quick check: if the rustacean can leave right away, return that (escaped)
+    if let Some((RustaceanMove::Escape(x, y), _)) = board.moves(start).into_iter()
+        .find(|(next_move, _)| matches!(next_move, RustaceanMove::Escape(..))) {
+        return Path::Escaped(x, y);
+    }
 
-                    let mut new_transform = Transform::default(); 
+    /// A tile the search got to, `entered` is what the move names and `landing` where it ends up
+    struct Step {
+        entered: (i32, i32),
+        landing: (i32, i32),
+        previous: Option<usize>,
+        leaves: bool,
+    }
 
-                    for (pos, transform) in tile_transform_q {
-                        if pos.x as i32 == neighbor.0 && pos.y as i32 == neighbor.1 {
+    let index_of = |(x, y): (i32, i32)| GridTilePos::new(x, y).to_index(&board.grid_size);
+    let mut steps = vec![Step { entered: start, landing: start, previous: None, leaves: false }];
+    // cheapest cost found so far to every tile, the start is always on the board
+    let mut best = vec![u32::MAX; board.grid_size.count()];
+    best[index_of(start)] = 0;
+    // ordered by cost, then by the order the steps were found in
+    let mut heap = BinaryHeap::from([Reverse((0_u32, 0_usize))]);
+
+    while let Some(Reverse((cost, index))) = heap.pop() {
+        if steps[index].leaves {
+            // reconstruct path from start -> ... -> way out
+            let mut path = Vec::new();
+            let mut current = Some(index);
+            while let Some(step_index) = current {
+                let step = &steps[step_index];
+                if step.previous.is_some() {
+                    path.push(step.entered);
+                }
+                current = step.previous;
+            }
+            path.reverse();
 
-                            new_transform = transform.compute_transform();
-                            new_transform.translation.z = 0.1;
+            return Path::Found(path);
+        }
 
-                        }
-                    };
+        let current = steps[index].landing;
+        if best[index_of(current)] < cost {
+            continue;
+        }
 
-                    transform.translation = new_transform.translation;
-                    rustacean_pos.x = neighbor.0;
-                    rustacean_pos.y = neighbor.1;
-                    next_turnstate.set(TurnState::PlayerTurn)
+        for (next_move, move_cost) in board.moves(current) {
+            let through = cost + move_cost;
+            let (entered, leaves) = match next_move {
+                RustaceanMove::Hop(x, y) => ((x, y), false),
+                RustaceanMove::Escape(x, y) => ((x, y), true),
+                RustaceanMove::Trapped => continue,
+            };
+            let landing = if leaves { entered } else { board.landing(entered) };
+
+            if !leaves {
+                let landing_index = index_of(landing);
+                if best[landing_index] <= through {
+                    continue;
                 }
+                best[landing_index] = through;
             }
-        },
-        Path::NotFound => {
-            for neighbor in current_neighbors {
-                if open_tiles.as_ref().unwrap().contains(&neighbor) {
+            steps.push(Step { entered, landing, previous: Some(index), leaves });
+            heap.push(Reverse((through, steps.len() - 1)));
+        }
+    }
+
+    Path::NotFound
+}
+
+/// Everything needed to carry out rustacean moves, whether the AI or a human picked them.
+///
+/// Every turn the rustaceans move one after another in turn order, rustaceans that cant move are skipped.
+#[derive(SystemParam)]
+pub struct RustaceanMover<'w, 's> {
+    rustacean_q: Query<'w, 's, (Entity, &'static RustaceanOrder, &'static mut RustaceanPos, &'static mut Transform, Option<&'static mut RustaceanVariant>)>,
+    grid_size_q: Query<'w, 's, (&'static GridSize, &'static HextileF2FSize, &'static HexGridOrientation)>,
+    tile_lookup: TileLookup<'w, 's>,
+    tile_q: Query<'w, 's, (&'static GridTilePos, Has<TrapTile>, Option<&'static TileKind>, Has<ReinforcedTrap>)>,
+    progress: ResMut<'w, RustaceanTurnProgress>,
+    next_turnstate: ResMut<'w, NextState<TurnState>>,
+    next_levelstate: ResMut<'w, NextState<LevelState>>,
+    rules: Res<'w, LevelRules>,
+    level_taps: Res<'w, CurrentLevelTaps>,
+    moved_events: EventWriter<'w, RustaceanMoved>,
+    escaped_events: EventWriter<'w, RustaceanEscaped>,
+    trapped_events: EventWriter<'w, RustaceanTrapped>,
+    level_end_events: EventWriter<'w, LevelEnded>,
+}
+
+/// The board the rustaceans move on, gathered in one pass over the tiles for a single step of the turn
+pub struct RustaceanBoard {
+    pub grid_size: GridSize,
+    pub tiles: TileKinds,
+    /// Tiles without a trap or rock, the rustaceans own tiles included
+    pub free_tiles: TileSet,
+    /// Tiles without a trap, rock or rustacean on them
+    pub open_tiles: TileSet,
+    /// Traps a jumper can get over, every one but the reinforced ones
+    pub jumpable: TileSet,
+    /// Every rustacean with its tile and ability, in turn order
+    pub rustaceans: Vec<(Entity, (i32, i32), Option<RustaceanVariant>)>,
+}
 
-                    let mut new_transform = Transform::default(); 
+impl RustaceanBoard {
+    /// Gathers the board from every tile as `(pos, is_trap, kind, is_reinforced)` and the rustaceans in turn order
+    pub fn new(
+        grid_size: GridSize,
+        tile_states: impl IntoIterator<Item = ((i32, i32), bool, Option<TileKind>, bool)>,
+        rustaceans: Vec<(Entity, (i32, i32), Option<RustaceanVariant>)>,
+    ) -> Self {
+        let mut free_tiles = TileSet::new(grid_size);
+        let mut jumpable = TileSet::new(grid_size);
+        let mut special_tiles = Vec::new();
+        for (pos, is_trap, kind, is_reinforced) in tile_states {
+            if let Some(kind) = kind {
+                special_tiles.push((pos, kind));
+            }
+            if is_trap && !is_reinforced {
+                jumpable.insert(pos);
+            } else if !is_trap && kind != Some(TileKind::Rock) {
+                free_tiles.insert(pos);
+            }
+        }
+        let tiles = TileKinds::new(special_tiles);
 
-                    for (pos, transform) in tile_transform_q {
-                        if pos.x as i32 == neighbor.0 && pos.y as i32 == neighbor.1 {
+        let positions: Vec<(i32, i32)> = rustaceans.iter().map(|(_, pos, _)| *pos).collect();
+        let open_tiles = open_tiles(free_tiles.clone(), &positions, &tiles, &grid_size);
 
-                            new_transform = transform.compute_transform();
-                            new_transform.translation.z = 0.1;
+        RustaceanBoard { grid_size, tiles, free_tiles, open_tiles, jumpable, rustaceans }
+    }
 
-                        }
-                    };
-                    transform.translation = new_transform.translation;
+    pub fn view(&self) -> BoardView<'_> {
+        BoardView { grid_size: self.grid_size, open_tiles: &self.open_tiles, tiles: &self.tiles }
+    }
 
-                    rustacean_pos.x = neighbor.0;
-                    rustacean_pos.y = neighbor.1;
+    pub fn positions(&self) -> Vec<(i32, i32)> {
+        self.rustaceans.iter().map(|(_, pos, _)| *pos).collect()
+    }
 
-                    next_turnstate.set(TurnState::PlayerTurn);
+    /// Jumps the rustacean at `index` can make this step
+    pub fn jumps(&self, index: usize) -> Vec<(RustaceanMove, u32)> {
+        let (_, pos, variant) = self.rustaceans[index];
+        ready_jumps(pos, variant, &self.jumpable, &self.view())
+    }
 
-                    return
-                }
-            }
+    /// Which rustaceans are trapped for good, in turn order
+    pub fn trapped(&self) -> Vec<bool> {
+        let jumpers: Vec<bool> = self.rustaceans.iter()
+            .map(|(_, _, variant)| variant.is_some_and(|variant| variant.ability == RustaceanAbility::Jumper))
+            .collect();
+        let board = BoardView { grid_size: self.grid_size, open_tiles: &self.free_tiles, tiles: &self.tiles };
+        trapped_rustaceans(&self.positions(), &jumpers, &self.jumpable, &board)
+    }
+}
+
+impl RustaceanMover<'_, '_> {
+    /// Every rustacean and its tile, in turn order
+    pub fn rustaceans(&self) -> Vec<(Entity, (i32, i32))> {
+        let mut rustaceans: Vec<(RustaceanOrder, Entity, (i32, i32))> = self.rustacean_q.iter()
+            .map(|(entity, order, pos, _, _)| (*order, entity, (pos.x, pos.y)))
+            .collect();
+        rustaceans.sort_unstable_by_key(|(order, _, _)| *order);
+        rustaceans.into_iter().map(|(_, entity, pos)| (entity, pos)).collect()
+    }
+
+    /// Special tiles of the board
+    pub fn tiles(&self) -> TileKinds {
+        TileKinds::new(self.tile_q.iter()
+            .filter_map(|(pos, _, kind, _)| kind.map(|kind| ((pos.x, pos.y), *kind))))
+    }
+
+    /// Tiles without a trap, rock or rustacean on them, rustaceans that left through an exit dont block it
+    pub fn open_tiles(&self) -> TileSet {
+        self.board().open_tiles
+    }
+
+    /// The board as it is right now, build it once per step and hand it to everything the step needs
+    pub fn board(&self) -> RustaceanBoard {
+        let rustaceans = self.rustaceans().into_iter()
+            .map(|(entity, pos)| (entity, pos, self.variant(entity)))
+            .collect();
+        RustaceanBoard::new(
+            self.grid_size(),
+            self.tile_q.iter().map(|(pos, is_trap, kind, is_reinforced)| ((pos.x, pos.y), is_trap, kind.copied(), is_reinforced)),
+            rustaceans,
+        )
+    }
+
+    /// Ability of the rustacean, if it is a variant
+    pub fn variant(&self, entity: Entity) -> Option<RustaceanVariant> {
+        self.rustacean_q.get(entity).ok().and_then(|(_, _, _, _, variant)| variant.copied())
+    }
+
+    /// Index on `board` of the rustacean whose move is next this turn, if any is left that can move
+    pub fn current_on(&self, board: &RustaceanBoard) -> Option<usize> {
+        let variants: Vec<Option<RustaceanVariant>> = board.rustaceans.iter().map(|(_, _, variant)| *variant).collect();
+        let moved: Vec<bool> = board.rustaceans.iter().map(|(entity, _, _)| self.progress.moved.contains(entity)).collect();
+        next_to_move(&board.positions(), &variants, &moved, &board.jumpable, &board.view())
+    }
+
+    /// The rustacean whose move is next this turn, if any is left that can move
+    pub fn current(&self) -> Option<(Entity, (i32, i32))> {
+        let board = self.board();
+        self.current_on(&board).map(|index| {
+            let (entity, pos, _) = board.rustaceans[index];
+            (entity, pos)
+        })
+    }
+
+    /// Moves the rustacean whose move is next can make, its jumps included
+    pub fn moves(&self) -> Vec<RustaceanMove> {
+        let board = self.board();
+        let Some(index) = self.current_on(&board) else {
+            return Vec::new();
+        };
+        board.view().moves(board.rustaceans[index].1).into_iter()
+            .chain(board.jumps(index))
+            .map(|(next_move, _)| next_move)
+            .collect()
+    }
+
+    /// Which rustaceans are trapped for good, in turn order
+    pub fn trapped(&self) -> Vec<bool> {
+        self.board().trapped()
+    }
+
+    pub fn grid_size(&self) -> GridSize {
+        let (grid_size, _, _) = self.grid_size_q.single().context("Looking for a single GridSize from query").unwrap();
+        *grid_size
+    }
+
+    pub fn rules(&self) -> &LevelRules {
+        &self.rules
+    }
+
+    /// Moves the current rustacean, and ends the turn once every rustacean had its go
+    pub fn apply(&mut self, next_move: RustaceanMove) {
+        let board = self.board();
+        let Some(index) = self.current_on(&board) else {
+            self.finish_turn_on(&board);
+            return;
+        };
+        let jumps = board.jumps(index);
+        self.step(&board, index, next_move, &jumps);
+
+        let board = self.board();
+        if self.current_on(&board).is_none() {
+            self.finish_turn_on(&board);
+        }
+    }
+
+    /// Carries out `next_move` of the rustacean at `index` on `board`, `jumps` are the ones it had ready
+    pub fn step(&mut self, board: &RustaceanBoard, index: usize, next_move: RustaceanMove, jumps: &[(RustaceanMove, u32)]) {
+        let (entity, _, variant) = board.rustaceans[index];
+        let effect = move_effect(next_move, variant, jumps, &board.view());
+
+        if let Some(to) = effect.to {
+            self.move_to(entity, to, board.tiles.escaped(to, &board.grid_size));
+        }
+        if effect.rests {
+            self.progress.muddy.push(entity);
+        }
+        if effect.uses_ability() {
+            self.use_ability(entity);
+        }
+        if effect.turn_over() {
+            self.progress.moved.push(entity);
+        }
+    }
+
+    fn use_ability(&mut self, entity: Entity) {
+        if let Ok((_, _, _, _, Some(mut variant))) = self.rustacean_q.get_mut(entity) {
+            debug!("rustacean {:?} uses its {:?} ability", entity, variant.ability);
+            variant.use_ability();
+        }
+    }
 
-            for mut sprite in tile_sprite_q.iter_mut() {
-                sprite.color = Color::Srgba(LIGHT_GREEN);
+    /// Puts every rustacean on the tiles another machine has them on and sets their abilities' cooldowns, in turn order
+    pub fn mirror(&mut self, positions: &[(i32, i32)], moved: &[usize], variants: &[Option<RustaceanVariant>]) {
+        let (grid_size, tiles) = (self.grid_size(), self.tiles());
+        let rustaceans = self.rustaceans();
+        for ((entity, pos), target) in rustaceans.iter().zip(positions) {
+            if pos != target {
+                self.move_to(*entity, *target, tiles.escaped(*target, &grid_size));
             }
+        }
+        for ((entity, _), target) in rustaceans.iter().zip(variants) {
+            let (Ok((_, _, _, _, Some(mut variant))), Some(target)) = (self.rustacean_q.get_mut(*entity), target) else {
+                continue;
+            };
+            variant.set_if_neq(*target);
+        }
 
-            next_levelstate.set(LevelState::LevelWin);
-            game_stats.tigers_trapped += 1;
+        self.progress.moved = rustaceans.iter()
+            .enumerate()
+            .filter(|(order, _)| moved.contains(order))
+            .map(|(_, (entity, _))| *entity)
+            .collect();
+    }
 
-        },
-        Path::Escaped(x, y) => {
-            let mut new_translation = coord_to_world(x, y, grid_size, tile_width, orientation)
+    /// Hops a rustacean onto a tile, or off the board if it `escaped` through an exit or out of bounds
+    fn move_to(&mut self, entity: Entity, to: (i32, i32), escaped: bool) {
+        let (grid_size, tile_width, orientation) = self.grid_size_q.single().context("Looking for a single GridSize from query").unwrap();
+        let (grid_size, tile_width, orientation) = (*grid_size, *tile_width, *orientation);
+        let (x, y) = to;
+
+        let new_translation = if in_bounds(to, &grid_size) {
+            let mut new_transform = self.tile_lookup.transform(to).map_or(Transform::default(), |transform| transform.compute_transform());
+            new_transform.translation.z = 0.1;
+            new_transform.translation
+        } else {
+            let mut new_translation = coord_to_world(x, y, &grid_size, &tile_width, &orientation)
                 .extend(0.1);
             new_translation.y += tile_width.to_height() / 2.;
-            transform.translation = new_translation;
+            new_translation
+        };
+
+        let Ok((_, _, mut rustacean_pos, mut transform, _)) = self.rustacean_q.get_mut(entity) else {
+            return;
+        };
+        let from = (rustacean_pos.x, rustacean_pos.y);
+        transform.translation = new_translation;
+        rustacean_pos.x = x;
+        rustacean_pos.y = y;
+
+        if !escaped {
+            self.moved_events.write(RustaceanMoved { from, to });
+        } else {
+            self.escaped_events.write(RustaceanEscaped { x, y });
+        }
+    }
+
+    /// Ends the rustacean turn, or the level if the win condition is met or lost
+    pub fn finish_turn(&mut self) {
+        let board = self.board();
+        self.finish_turn_on(&board);
+    }
 
-            rustacean_pos.x = x;
-            rustacean_pos.y = y;
+    fn finish_turn_on(&mut self, board: &RustaceanBoard) {
+        let positions = board.positions();
+        let trapped = board.trapped();
+        let escaped = positions.iter().filter(|pos| board.tiles.escaped(**pos, &board.grid_size)).count();
 
-            game_stats.tigers_escaped += 1;
+        match level_outcome(&self.rules, positions.len(), trapped.iter().filter(|trapped| **trapped).count(), escaped, self.level_taps.0) {
+            Some(true) => self.win_on(board, &trapped),
+            Some(false) => self.lose(),
+            None => self.next_turnstate.set(TurnState::PlayerTurn),
+        }
+    }
 
-            for mut sprite in tile_sprite_q.iter_mut() {
-                sprite.color = Color::Srgba(RED);
+    /// Ends the level as won, announcing every trapped rustacean
+    pub fn win(&mut self) {
+        let board = self.board();
+        self.win_on(&board, &board.trapped());
+    }
+
+    fn win_on(&mut self, board: &RustaceanBoard, trapped: &[bool]) {
+        self.next_levelstate.set(LevelState::LevelWin);
+
+        for (pos, trapped) in board.positions().iter().zip(trapped) {
+            if *trapped {
+                self.trapped_events.write(RustaceanTrapped { x: pos.0, y: pos.1 });
             }
+        }
+        self.level_end_events.write(LevelEnded { won: true });
+    }
 
-            next_levelstate.set(LevelState::LevelLose);
-        },
+    pub fn lose(&mut self) {
+        self.next_levelstate.set(LevelState::LevelLose);
+        self.level_end_events.write(LevelEnded { won: false });
+    }
+}
+
+pub fn pathfind_and_move(
+    mut mover: RustaceanMover,
+    strategies: Res<StrategyRegistry>,
+) {
+    // every rustacean takes its turn in order, the tiles the others stand on are blocked
+    loop {
+        let board = mover.board();
+        let Some(index) = mover.current_on(&board) else {
+            mover.finish_turn_on(&board);
+            return;
+        };
+        let start = board.rustaceans[index].1;
+        let jumps = board.jumps(index);
+        let next_move = strategies.rustacean_for(mover.rules().rustacean_ai).choose_move(start, &board.view(), &jumps);
+        mover.step(&board, index, next_move, &jumps);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet, VecDeque};
+
+    use crate::game::{difficulty::SpecialTileCounts, hashed_pathfinding::{self, HashedBoard}, tiles::generate_special_tiles};
+    use super::*;
+
+    /// A seeded board with about `trap_share` of its tiles trapped, the center kept free for the rustacean
+    fn seeded_board(seed: u64, cols: i32, rows: i32, trap_share: f64, special_tiles: &SpecialTileCounts) -> (GridSize, TileKinds, HashSet<(i32, i32)>) {
+        rng_utils::seed_game_rng(seed);
+        let grid_size = GridSize::new(cols, rows);
+        let center = (cols / 2, rows / 2);
+        let tiles = TileKinds::from_special_tiles(&generate_special_tiles(special_tiles, &grid_size, &[center]));
+        let open_tiles = (0..cols)
+            .flat_map(|x| (0..rows).map(move |y| (x, y)))
+            .filter(|pos| !tiles.is_rock(*pos) && (*pos == center || rng_utils::random_range(0.0..1.0) >= trap_share))
+            .collect();
+        (grid_size, tiles, open_tiles)
+    }
+
+    /// Textbook breadth first search on a board without special tiles, the first way out it finds wins
+    fn breadth_first_path(start: (i32, i32), grid_size: GridSize, open: &HashSet<(i32, i32)>) -> Path {
+        let mut previous: HashMap<(i32, i32), (i32, i32)> = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+        while let Some(current) = queue.pop_front() {
+            for next in get_hex_horizontal_neighbor_pos(current.0, current.1) {
+                if !in_bounds(next, &grid_size) {
+                    if current == start {
+                        return Path::Escaped(next.0, next.1);
+                    }
+                    let mut path = vec![next, current];
+                    while let Some(step) = previous.get(path.last().unwrap()).filter(|step| **step != start) {
+                        path.push(*step);
+                    }
+                    path.reverse();
+                    return Path::Found(path);
+                }
+                if open.contains(&next) && next != start && !previous.contains_key(&next) {
+                    previous.insert(next, current);
+                    queue.push_back(next);
+                }
+            }
+        }
+        Path::NotFound
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn a_plain_board_gets_the_breadth_first_path() {
+        for seed in 0..8 {
+            let (grid_size, tiles, open) = seeded_board(seed, 15, 15, 0.3, &SpecialTileCounts::default());
+            for start in open.iter().copied() {
+                let mut open = open.clone();
+                open.remove(&start);
+                let open_tiles = TileSet::from_tiles(grid_size, open.iter().copied());
+                assert_eq!(
+                    pathfind(start, &BoardView { grid_size, open_tiles: &open_tiles, tiles: &tiles }),
+                    breadth_first_path(start, grid_size, &open),
+                    "path from {start:?} on seed {seed}",
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn array_searches_match_the_hashed_ones() {
+        let plain = SpecialTileCounts::default();
+        let special = SpecialTileCounts { rocks: 6, mud: 8, teleporter_pairs: 2, one_way: 5, exits: 0 };
+        let with_exits = SpecialTileCounts { exits: 4, ..special };
+
+        for seed in 0..8 {
+            for (cols, rows, trap_share, special_tiles) in [
+                (11, 11, 0.15, &plain),
+                (9, 14, 0.35, &plain),
+                (30, 30, 0.25, &plain),
+                (11, 11, 0.15, &special),
+                (16, 12, 0.2, &with_exits),
+            ] {
+                let (grid_size, tiles, open) = seeded_board(seed, cols, rows, trap_share, special_tiles);
+                let open_tiles = TileSet::from_tiles(grid_size, open.iter().copied());
+                let board = BoardView { grid_size, open_tiles: &open_tiles, tiles: &tiles };
+                let hashed = HashedBoard { grid_size, open_tiles: &open, tiles: &tiles };
+
+                let distances = exit_distances(&board);
+                let hashed_distances = hashed_pathfinding::exit_distances(&hashed);
+                for x in 0..cols {
+                    for y in 0..rows {
+                        assert_eq!(distances.get((x, y)), hashed_distances.get(&(x, y)).copied(), "distance of {:?} on seed {seed} {cols}x{rows}", (x, y));
+                    }
+                }
+
+                // every open tile as the start, the rustaceans tile doesnt count as open for its own search
+                for start in open.iter().copied() {
+                    let mut open_tiles = open_tiles.clone();
+                    open_tiles.remove(&start);
+                    let mut open = open.clone();
+                    open.remove(&start);
+                    assert_eq!(
+                        pathfind(start, &BoardView { grid_size, open_tiles: &open_tiles, tiles: &tiles }),
+                        hashed_pathfinding::pathfind(start, &HashedBoard { grid_size, open_tiles: &open, tiles: &tiles }),
+                        "path from {start:?} on seed {seed} {cols}x{rows}",
+                    );
+                }
+            }
+        }
+    }
+}
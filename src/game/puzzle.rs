@@ -0,0 +1,289 @@
+use std::{collections::BTreeMap, path::{Path, PathBuf}};
+
+use bevy::{ecs::{component::Component, query::With, resource::Resource, system::{Commands, Query, Res, ResMut}, world::World}, log::{debug, warn}, text::TextSpan, ui::{Node, PositionType, Val}, utils::default};
+use crate::{game::{difficulty::RustaceanAi, endless::LevelRules, game::{CurrentLevel, TotalGameStats}, levels::{CurrentLevelTaps, spawn_hud_text}, pathfinding::TileSet, solver::{min_taps_to_trap, SolverBoard}, tiles::{HexDirection, SpecialTile, TileKind, TileKinds}}, settings::{launch::LaunchOptions, locale::Localization}, utils::hexgrid_utils::GridSize};
+
+/// Folder holding one json file per puzzle, played in file name order
+const PUZZLE_DIR: &str = "./assets/puzzles";
+
+/// A puzzle as written in its level file.
+///
+/// `board` lists the rows top to bottom as seen on screen, one character per column:
+/// `.` is an open tile, `#` a trap and `R` the open tile the rustacean starts on.
+/// Special tiles are `O` for a rock, `~` for mud and `E` for an exit, the digits 1 2 3 7 8 9
+/// are one way tiles pointing like on a numpad, and the two tiles sharing a lowercase letter are a teleporter pair.
+#[derive(serde::Deserialize)]
+struct PuzzleFile {
+    name: String,
+    tap_budget: u32,
+    #[serde(default)]
+    rustacean_ai: RustaceanAi,
+    board: Vec<String>,
+}
+
+/// A fixed board that has to be solved within a number of taps
+#[derive(Clone, Debug)]
+pub struct Puzzle {
+    /// File name without extension, used to keep the best stars
+    pub id: String,
+    pub name: String,
+    pub grid_size: GridSize,
+    pub rustacean: (i32, i32),
+    pub traps: Vec<(i32, i32)>,
+    pub special_tiles: Vec<SpecialTile>,
+    pub tap_budget: u32,
+    pub rustacean_ai: RustaceanAi,
+    /// Fewest taps the solver needed, always within the budget
+    pub min_taps: u32,
+}
+
+impl Puzzle {
+    fn parse(id: String, file: PuzzleFile) -> Result<Self, String> {
+        let rows = file.board.len() as i32;
+        let cols = file.board.first().map_or(0, |row| row.chars().count()) as i32;
+        if rows < 3 || cols < 3 {
+            return Err(String::from("the board must be at least 3x3"));
+        }
+        if file.tap_budget == 0 {
+            return Err(String::from("the tap budget must be at least 1"));
+        }
+
+        let mut rustacean = None;
+        let mut traps = Vec::new();
+        let mut special_tiles = Vec::new();
+        let mut teleporters: BTreeMap<char, Vec<(i32, i32)>> = BTreeMap::new();
+        for (row_index, row) in file.board.iter().enumerate() {
+            if row.chars().count() as i32 != cols {
+                return Err(format!("row {} is not {cols} tiles wide", row_index + 1));
+            }
+            let y = rows - 1 - row_index as i32;
+            for (x, tile) in row.chars().enumerate() {
+                let pos = (x as i32, y);
+                match tile {
+                    '.' => {}
+                    '#' => traps.push(pos),
+                    'R' if rustacean.is_none() => rustacean = Some(pos),
+                    'R' => return Err(String::from("the board has more than one rustacean")),
+                    'O' => special_tiles.push(SpecialTile { pos, kind: TileKind::Rock }),
+                    '~' => special_tiles.push(SpecialTile { pos, kind: TileKind::Mud }),
+                    'E' => special_tiles.push(SpecialTile { pos, kind: TileKind::Exit }),
+                    letter if letter.is_ascii_lowercase() => teleporters.entry(letter).or_default().push(pos),
+                    other => match HexDirection::from_numpad(other) {
+                        Some(direction) => special_tiles.push(SpecialTile { pos, kind: TileKind::OneWay { direction } }),
+                        None => return Err(format!("unknown tile '{other}'")),
+                    },
+                }
+            }
+        }
+        for (letter, ends) in teleporters {
+            let &[first, second] = ends.as_slice() else {
+                return Err(format!("teleporter '{letter}' needs exactly two tiles"));
+            };
+            special_tiles.push(SpecialTile { pos: first, kind: TileKind::Teleporter { pair: second } });
+            special_tiles.push(SpecialTile { pos: second, kind: TileKind::Teleporter { pair: first } });
+        }
+        let Some(rustacean) = rustacean else {
+            return Err(String::from("the board has no rustacean"));
+        };
+
+        let grid_size = GridSize::new(cols, rows);
+        let tiles = TileKinds::from_special_tiles(&special_tiles);
+        let open_tiles = TileSet::from_tiles(grid_size, (0..cols)
+            .flat_map(|x| (0..rows).map(move |y| (x, y)))
+            .filter(|pos| !traps.contains(pos) && !tiles.is_rock(*pos)));
+
+        let board = SolverBoard {
+            grid_size,
+            open_tiles: &open_tiles,
+            tiles: &tiles,
+            rustacean,
+            rustacean_ai: file.rustacean_ai,
+        };
+        let Some(min_taps) = min_taps_to_trap(&board, file.tap_budget) else {
+            return Err(format!("the solver found no way to trap the rustacean in {} taps", file.tap_budget));
+        };
+
+        Ok(Puzzle {
+            id,
+            name: file.name,
+            grid_size,
+            rustacean,
+            traps,
+            special_tiles,
+            tap_budget: file.tap_budget,
+            rustacean_ai: file.rustacean_ai,
+            min_taps,
+        })
+    }
+
+    /// Three stars for a solution as short as the solver's, two for one tap more, one otherwise
+    pub fn stars(&self, taps_used: u32) -> u8 {
+        match taps_used.saturating_sub(self.min_taps) {
+            0 => 3,
+            1 => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Every puzzle that loaded and passed the solver
+#[derive(Resource, Default)]
+pub struct PuzzleBook {
+    pub puzzles: Vec<Puzzle>,
+}
+
+impl PuzzleBook {
+    /// Puzzle played on `level`, wrapping around to the first one after the last
+    pub fn for_level(&self, level: u32) -> Option<&Puzzle> {
+        if self.puzzles.is_empty() {
+            return None;
+        }
+        self.puzzles.get(level.saturating_sub(1) as usize % self.puzzles.len())
+    }
+}
+
+fn load_puzzle(path: &Path) -> Result<Puzzle, String> {
+    let json = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let file: PuzzleFile = serde_json::from_str(&json).map_err(|err| err.to_string())?;
+    let id = path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Puzzle::parse(id, file)
+}
+
+/// The puzzle files in `dir`, in the order they are played
+fn puzzle_paths(dir: &str) -> std::io::Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+pub fn setup_puzzle_book(
+    mut commands: Commands,
+) {
+    let paths = puzzle_paths(PUZZLE_DIR).unwrap_or_else(|err| {
+        warn!("Could not read {PUZZLE_DIR}, puzzle mode has no puzzles: {err}");
+        Vec::new()
+    });
+
+    let mut book = PuzzleBook::default();
+    for path in paths {
+        match load_puzzle(&path) {
+            Ok(puzzle) => {
+                debug!("puzzles: {} solvable in {} of {} taps", puzzle.id, puzzle.min_taps, puzzle.tap_budget);
+                book.puzzles.push(puzzle);
+            }
+            // a puzzle that cant be proven solvable is never offered
+            Err(err) => warn!("Skipping puzzle {}: {err}", path.display()),
+        }
+    }
+
+    commands.insert_resource(book);
+}
+
+/// Starts a puzzle game at the first puzzle without any stars, unless the command line picked one
+pub fn start_at_unsolved_puzzle(
+    book: Res<PuzzleBook>,
+    game_stats: Res<TotalGameStats>,
+    options: Res<LaunchOptions>,
+    mut current_level: ResMut<CurrentLevel>,
+) {
+    if options.start_level.is_some() {
+        return;
+    }
+    let unsolved = book.puzzles.iter()
+        .position(|puzzle| !game_stats.puzzle_stars.contains_key(&puzzle.id))
+        .unwrap_or(0);
+    current_level.0 = unsolved as u32 + 1;
+}
+
+/// Keeps the best star rating of the puzzle that was just solved
+pub fn record_puzzle_stars(
+    rules: Res<LevelRules>,
+    level_taps: Res<CurrentLevelTaps>,
+    mut game_stats: ResMut<TotalGameStats>,
+) {
+    let Some(puzzle) = &rules.puzzle else {
+        return;
+    };
+
+    let stars = puzzle.stars(level_taps.0);
+    debug!("puzzles: solved {} in {} taps for {stars} stars", puzzle.id, level_taps.0);
+    let best = game_stats.puzzle_stars.entry(puzzle.id.clone()).or_default();
+    *best = (*best).max(stars);
+}
+
+/// Span of the HUD showing the puzzle name, taps left and best stars
+#[derive(Component)]
+pub struct PuzzleText;
+
+pub fn spawn_puzzle_text(
+    world: &mut World,
+) {
+    spawn_hud_text(world, Node {
+        position_type: PositionType::Absolute,
+        top: Val::Px(0.0),
+        right: Val::Px(20.0),
+        ..default()
+    }, PuzzleText);
+}
+
+pub fn puzzle_text_update(
+    rules: Res<LevelRules>,
+    level_taps: Res<CurrentLevelTaps>,
+    game_stats: Res<TotalGameStats>,
+    localization: Res<Localization>,
+    mut query: Query<&mut TextSpan, With<PuzzleText>>,
+) {
+    let Some(puzzle) = &rules.puzzle else {
+        return;
+    };
+
+    let taps_left = puzzle.tap_budget.saturating_sub(level_taps.0) as u64;
+    let best_stars = game_stats.puzzle_stars.get(&puzzle.id).copied().unwrap_or(0);
+    let text = format!(
+        "{}\n{}\n{}",
+        puzzle.name,
+        localization.plural("hud.taps_left", taps_left),
+        localization.format("hud.best_stars", &[("n", &best_stars.to_string())]),
+    );
+
+    for mut span in &mut query {
+        if text != span.0 {
+            **span = text.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_bundled_puzzle_is_solvable_within_its_budget() {
+        let paths = puzzle_paths(PUZZLE_DIR).expect("the puzzles ship in assets/puzzles");
+        assert!(paths.len() >= 8, "expected the 8 bundled puzzles, found {}", paths.len());
+
+        for path in paths {
+            let puzzle = load_puzzle(&path).unwrap_or_else(|err| panic!("{} does not load: {err}", path.display()));
+            assert!(
+                (1..=puzzle.tap_budget).contains(&puzzle.min_taps),
+                "{} needs {} taps with a budget of {}", puzzle.id, puzzle.min_taps, puzzle.tap_budget,
+            );
+        }
+    }
+
+    #[test]
+    fn a_puzzle_over_its_budget_is_rejected() {
+        let file = PuzzleFile {
+            name: String::from("Open"),
+            tap_budget: 1,
+            rustacean_ai: RustaceanAi::Pathfinder,
+            board: vec![String::from("....."), String::from("..R.."), String::from(".....")],
+        };
+        assert!(Puzzle::parse(String::from("open"), file).is_err());
+    }
+}
@@ -0,0 +1,163 @@
+use bevy::{ecs::{component::Component, entity::Entity, query::With, resource::Resource, system::{Commands, Query, Res, ResMut}, world::World}, log::debug, state::state::State, text::{TextColor, TextSpan}, ui::{widget::Text, JustifyContent, Node, PositionType, Val}, utils::default};
+use crate::{game::{difficulty::ActiveDifficulty, endless::LevelRules, game::TotalGameStats, levels::{CurrentLevelTaps, LevelState, LevelText}, pathfinding::{RustaceanMover, TileSet}, rules::record_stars, rustaceans::WinCondition, solver::{greedy_taps_to_trap, min_taps_to_trap, SolverBoard}, tiles::TileKinds}, settings::{locale::Localization, theme::Theme}};
+
+/// Most taps the exact solver looks for, bigger boards are left to the greedy trapper
+const SOLVER_PAR_TAPS: u32 = 2;
+/// Most taps the greedy trapper plays before giving up
+const GREEDY_PAR_TAPS: u32 = 40;
+/// Par for a rustacean the greedy trapper couldnt catch
+const FALLBACK_PAR_TAPS: u32 = 8;
+/// Taps over par that still earn two stars on a generated board
+const TWO_STAR_SLACK: u32 = 2;
+
+/// Taps the current level should take, worked out when its board is set up
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct LevelPar(pub u32);
+
+impl LevelPar {
+    /// Three stars within par, two a little over it, one for any other win
+    pub fn stars(self, taps: u32) -> u8 {
+        match taps.saturating_sub(self.0) {
+            0 => 3,
+            over if over <= TWO_STAR_SLACK => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Stars of a level that was just won, puzzles keep their own stricter rating
+pub fn level_stars(rules: &LevelRules, par: LevelPar, taps: u32) -> u8 {
+    match &rules.puzzle {
+        Some(puzzle) => puzzle.stars(taps),
+        None => par.stars(taps),
+    }
+}
+
+/// Works out the par of the board that was just set up, one rustacean at a time
+pub fn compute_level_par(
+    mut commands: Commands,
+    mover: RustaceanMover,
+) {
+    let rules = mover.rules();
+    let par = match &rules.puzzle {
+        Some(puzzle) => puzzle.min_taps,
+        None => {
+            let rustaceans: Vec<(i32, i32)> = mover.rustaceans().into_iter().map(|(_, pos)| pos).collect();
+            generated_level_par(rules, &mover.open_tiles(), &mover.tiles(), &rustaceans)
+        }
+    };
+
+    debug!("rating: par is {par} taps");
+    commands.insert_resource(LevelPar(par));
+}
+
+/// Par of a generated board, one rustacean at a time, `open_tiles` leaves out the tiles the rustaceans stand on
+pub fn generated_level_par(rules: &LevelRules, open_tiles: &TileSet, tiles: &TileKinds, rustaceans: &[(i32, i32)]) -> u32 {
+    let pars = rustaceans.iter().map(|&rustacean| {
+        // the other rustaceans stay in the way
+        let mut open_tiles = open_tiles.clone();
+        open_tiles.insert(rustacean);
+        let board = SolverBoard { grid_size: rules.grid_size, open_tiles: &open_tiles, tiles, rustacean, rustacean_ai: rules.rustacean_ai };
+        min_taps_to_trap(&board, SOLVER_PAR_TAPS)
+            .or_else(|| greedy_taps_to_trap(&board, GREEDY_PAR_TAPS))
+            .unwrap_or(FALLBACK_PAR_TAPS)
+    });
+
+    match rules.win_condition {
+        WinCondition::TrapAll => pars.sum(),
+        WinCondition::TrapAny => pars.min().unwrap_or(0),
+    }
+}
+
+/// Adds the stars of the level that was just won to the stats, puzzles keep their best stars per puzzle instead
+pub fn record_level_stars(
+    rules: Res<LevelRules>,
+    par: Res<LevelPar>,
+    level_taps: Res<CurrentLevelTaps>,
+    difficulty: Res<ActiveDifficulty>,
+    mut game_stats: ResMut<TotalGameStats>,
+) {
+    if rules.puzzle.is_some() {
+        return;
+    }
+
+    let stars = par.stars(level_taps.0);
+    debug!("rating: won in {} taps with par {} for {stars} stars", level_taps.0, par.0);
+    record_stars(&mut game_stats, difficulty.kind, stars);
+}
+
+/// Span of the level HUD with the taps made and the par
+#[derive(Component)]
+pub struct LevelTapsText;
+
+pub fn level_taps_text_update(
+    par: Res<LevelPar>,
+    level_taps: Res<CurrentLevelTaps>,
+    localization: Res<Localization>,
+    mut query: Query<&mut TextSpan, With<LevelTapsText>>,
+) {
+    let text = format!("\n{}", localization.format("hud.taps_par", &[
+        ("taps", &localization.number(level_taps.0 as u64)),
+        ("par", &localization.number(par.0 as u64)),
+    ]));
+
+    for mut span in &mut query {
+        if text != span.0 {
+            **span = text.clone()
+        }
+    }
+}
+
+/// Banner over the finished board with its taps and stars
+#[derive(Component)]
+pub struct LevelEndText;
+
+pub fn spawn_level_end_text(
+    world: &mut World,
+) {
+    let hud_text = world.resource::<Theme>().hud_text;
+    let localization = world.resource::<Localization>();
+    let rules = world.resource::<LevelRules>();
+    let par = *world.resource::<LevelPar>();
+    let taps = world.resource::<CurrentLevelTaps>().0;
+    let won = *world.resource::<State<LevelState>>().get() == LevelState::LevelWin;
+
+    let summary = localization.format("level_end.taps", &[
+        ("taps", &localization.number(taps as u64)),
+        ("par", &localization.number(par.0 as u64)),
+    ]);
+    let text = match won {
+        true => format!(
+            "{}\n{summary}",
+            localization.format("level_end.stars", &[("n", &level_stars(rules, par, taps).to_string())]),
+        ),
+        false => format!("{}\n{summary}", localization.text("level_end.escaped")),
+    };
+    let font = localization.text_font(42.0);
+
+    world.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            top: Val::Px(20.0),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        LevelEndText,
+        // an ended game takes it down with the rest of the level HUD
+        LevelText,
+    )).with_child((
+        Text::new(text),
+        font,
+        TextColor(hud_text),
+    ));
+}
+
+pub fn despawn_level_end_text(
+    mut commands: Commands,
+    text_q: Query<Entity, With<LevelEndText>>,
+) {
+    for entity in text_q.iter() {
+        commands.entity(entity).despawn();
+    }
+}
@@ -0,0 +1,198 @@
+use std::path::Path;
+
+use bevy::{ecs::{event::{EventReader, EventWriter}, observer::Trigger, resource::Resource, system::{Commands, Query, Res, ResMut}}, log::{debug, warn}, state::state::State, time::{Time, Timer, TimerMode}};
+use crate::{game::{abilities::{AbilityPicked, PlayerAbility}, difficulty::{ActiveDifficulty, DifficultyKind}, events::AbilityUsed, game::CurrentLevel, game_mode::{ActiveGameMode, GameMode}, level_setup::TileTapped, levels::{LevelState, TurnState}, rustaceans::WinCondition, tile_lookup::TileLookup, traps::{TrapInventory, TrapKind}}, settings::{launch::{data_path, ensure_data_dir, LaunchOptions}, settings::Settings}, utils::{hexgrid_utils::GridTilePos, rng_utils::{random_seed, seed_game_rng}}};
+
+/// Seconds between two played back actions, so the rustacean's moves can be followed
+const REPLAY_STEP_SECS: f32 = 0.6;
+/// Where the last recorded game is written, in the data directory
+pub const LAST_REPLAY_FILE: &str = "last_replay.json";
+
+/// Something the player did that a replay does again
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub enum ReplayAction {
+    /// A tap on the tile at (x, y) with `trap` selected
+    Tap { x: i32, y: i32, trap: TrapKind },
+    Ability(PlayerAbility),
+}
+
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub struct ReplayStep {
+    pub level: u32,
+    pub action: ReplayAction,
+}
+
+/// A recorded game: the seed and setup it was started with and every action of the player.
+///
+/// Boards and rustacean moves all come from the seed, so the actions are enough to play it again.
+#[derive(Resource, Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub game_mode: GameMode,
+    pub difficulty: DifficultyKind,
+    pub start_level: u32,
+    /// Board size forced from the command line
+    pub grid_size: Option<(i32, i32)>,
+    pub rustaceans: u32,
+    pub win_condition: WinCondition,
+    /// Rustacean strategy forced with `--ai`, replays recorded before it existed have none
+    #[serde(default)]
+    pub rustacean_ai: Option<String>,
+    pub steps: Vec<ReplayStep>,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|err| format!("could not read {}: {err}", path.display()))?;
+        serde_json::from_str(&json)
+            .map_err(|err| format!("could not parse {}: {err}", path.display()))
+    }
+}
+
+/// A replay being played back, `next` is the step that comes next
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    pub replay: Replay,
+    pub next: usize,
+    pub timer: Timer,
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay) -> Self {
+        ReplayPlayback {
+            replay,
+            next: 0,
+            timer: Timer::from_seconds(REPLAY_STEP_SECS, TimerMode::Repeating),
+        }
+    }
+}
+
+/// Seeds the game and starts recording it, only games the seed fully decides are recorded
+pub fn start_replay_recording(
+    mut commands: Commands,
+    options: Res<LaunchOptions>,
+    settings: Res<Settings>,
+    difficulty: Res<ActiveDifficulty>,
+    game_mode: Res<ActiveGameMode>,
+    current_level: Res<CurrentLevel>,
+) {
+    let seed = options.seed.unwrap_or_else(random_seed);
+    seed_game_rng(seed);
+    debug!("replay: game seeded with {seed}");
+
+    // time attack runs on the clock, and the other player of a match isnt recorded
+    if !matches!(game_mode.0, GameMode::Classic | GameMode::Puzzle) {
+        commands.remove_resource::<Replay>();
+        return;
+    }
+
+    commands.insert_resource(Replay {
+        seed,
+        game_mode: game_mode.0,
+        difficulty: difficulty.kind,
+        start_level: current_level.0,
+        grid_size: options.grid_size.map(|grid_size| (grid_size.cols, grid_size.rows)),
+        rustaceans: options.rustaceans.unwrap_or(settings.rustaceans),
+        win_condition: options.win_condition.unwrap_or(settings.win_condition),
+        rustacean_ai: options.rustacean_ai.clone(),
+        steps: Vec::new(),
+    });
+}
+
+/// Records the player's taps, runs before the tile's own observer so the trap kind is still selected
+pub fn record_replay_tap(
+    trigger: Trigger<TileTapped>,
+    tile_q: Query<&GridTilePos>,
+    replay: Option<ResMut<Replay>>,
+    inventory: Res<TrapInventory>,
+    current_level: Res<CurrentLevel>,
+    level_state: Res<State<LevelState>>,
+    turn_state: Res<State<TurnState>>,
+) {
+    let (Some(mut replay), Ok(pos)) = (replay, tile_q.get(trigger.target())) else {
+        return;
+    };
+    if *level_state != LevelState::InLevel || *turn_state != TurnState::PlayerTurn {
+        return;
+    }
+
+    replay.steps.push(ReplayStep {
+        level: current_level.0,
+        action: ReplayAction::Tap { x: pos.x, y: pos.y, trap: inventory.selected },
+    });
+}
+
+pub fn record_replay_abilities(
+    mut ability_events: EventReader<AbilityUsed>,
+    replay: Option<ResMut<Replay>>,
+    current_level: Res<CurrentLevel>,
+) {
+    let Some(mut replay) = replay else {
+        ability_events.clear();
+        return;
+    };
+
+    for event in ability_events.read() {
+        replay.steps.push(ReplayStep { level: current_level.0, action: ReplayAction::Ability(event.ability) });
+    }
+}
+
+/// Writes the game that just ended next to the other saves, so it can be passed to --replay
+pub fn save_replay(
+    mut commands: Commands,
+    replay: Option<Res<Replay>>,
+) {
+    let Some(replay) = replay else {
+        return;
+    };
+
+    ensure_data_dir();
+    let json = serde_json::to_string_pretty(replay.into_inner())
+        .expect("Error: Implementation of Serialize must not decide to fail\nT should contain a map with string keys");
+
+    if let Err(err) = std::fs::write(data_path(LAST_REPLAY_FILE), json) {
+        warn!("Could not save the replay: {err}");
+    }
+    commands.remove_resource::<Replay>();
+}
+
+/// Does the next recorded action once the player is to move, one every [`REPLAY_STEP_SECS`]
+pub fn play_back_replay(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut playback: ResMut<ReplayPlayback>,
+    current_level: Res<CurrentLevel>,
+    tiles: TileLookup,
+    mut inventory: ResMut<TrapInventory>,
+    mut ability_events: EventWriter<AbilityPicked>,
+) {
+    if !playback.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    // steps of levels that are already over were never needed, the game went another way
+    while playback.replay.steps.get(playback.next).is_some_and(|step| step.level < current_level.0) {
+        playback.next += 1;
+    }
+    let Some(step) = playback.replay.steps.get(playback.next).copied() else {
+        return;
+    };
+    if step.level > current_level.0 {
+        return;
+    }
+    playback.next += 1;
+    debug!("replay: step {} of {}: {:?}", playback.next, playback.replay.steps.len(), step.action);
+
+    match step.action {
+        ReplayAction::Tap { x, y, trap } => {
+            inventory.select(trap);
+            if let Some(entity) = tiles.entity((x, y)) {
+                commands.trigger_targets(TileTapped, entity);
+            }
+        }
+        ReplayAction::Ability(ability) => {
+            ability_events.write(AbilityPicked(ability));
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{game::{difficulty::DifficultyKind, endless::LevelRules, game::TotalGameStats, pathfinding::{BoardView, RustaceanMove, TileSet}, rustaceans::{RustaceanAbility, RustaceanVariant, WinCondition}, tiles::{TileKind, TileKinds}}, utils::hexgrid_utils::GridSize};
+
+/// `free_tiles` without the rustaceans on `positions`, rustaceans that left through an exit dont block it
+pub fn open_tiles(mut free_tiles: TileSet, positions: &[(i32, i32)], tiles: &TileKinds, grid_size: &GridSize) -> TileSet {
+    for pos in positions.iter().filter(|pos| !tiles.escaped(**pos, grid_size)) {
+        free_tiles.remove(pos);
+    }
+    free_tiles
+}
+
+/// Jumps a rustacean on `pos` can make this turn, none unless it is a jumper with its jump ready
+pub fn ready_jumps(pos: (i32, i32), variant: Option<RustaceanVariant>, jumpable: &TileSet, board: &BoardView) -> Vec<(RustaceanMove, u32)> {
+    match variant {
+        Some(variant) if variant.is_ready_jumper() => board.jumps(pos, jumpable),
+        _ => Vec::new(),
+    }
+}
+
+/// The rustacean whose move is next this turn, if any is left that can move.
+///
+/// That is the first one in turn order that hasnt `moved` yet, is still on the board and has a hop or a jump.
+/// Rustaceans that cant go anywhere are skipped, the others may still free them up.
+pub fn next_to_move(
+    positions: &[(i32, i32)],
+    variants: &[Option<RustaceanVariant>],
+    moved: &[bool],
+    jumpable: &TileSet,
+    board: &BoardView,
+) -> Option<usize> {
+    (0..positions.len())
+        .filter(|index| !moved[*index] && !board.tiles.escaped(positions[*index], &board.grid_size))
+        .find(|index| {
+            let pos = positions[*index];
+            !board.moves(pos).is_empty() || !ready_jumps(pos, variants[*index], jumpable, board).is_empty()
+        })
+}
+
+/// What a move does to the rustacean that makes it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MoveEffect {
+    /// Tile the rustacean ends up on, off the board for an escape, None if it stays put
+    pub to: Option<(i32, i32)>,
+    /// The move was one of the jumps it had ready
+    pub jumped: bool,
+    /// It landed in mud and sits out the next turn
+    pub rests: bool,
+    /// A sprinter with its sprint ready goes again right away
+    pub sprints: bool,
+}
+
+impl MoveEffect {
+    /// The jump or sprint was used, so the ability goes on cooldown
+    pub fn uses_ability(&self) -> bool {
+        self.jumped || self.sprints
+    }
+
+    /// The rustacean is done for this turn
+    pub fn turn_over(&self) -> bool {
+        !self.sprints
+    }
+}
+
+/// What `next_move` does for a rustacean with `variant`, `jumps` are the jumps it had ready this turn
+pub fn move_effect(next_move: RustaceanMove, variant: Option<RustaceanVariant>, jumps: &[(RustaceanMove, u32)], board: &BoardView) -> MoveEffect {
+    let jumped = jumps.iter().any(|(jump, _)| *jump == next_move);
+    match next_move {
+        RustaceanMove::Hop(x, y) => {
+            let landing = board.landing((x, y));
+            let rests = board.tiles.kind(landing) == Some(TileKind::Mud);
+            let sprints = !rests && variant.is_some_and(|variant| variant.ability == RustaceanAbility::Sprinter && variant.ready());
+            MoveEffect { to: Some(landing), jumped, rests, sprints }
+        }
+        RustaceanMove::Escape(x, y) => MoveEffect { to: Some((x, y)), jumped, rests: false, sprints: false },
+        // nowhere to go this turn, the others may still free it up
+        RustaceanMove::Trapped => MoveEffect { to: None, jumped: false, rests: false, sprints: false },
+    }
+}
+
+/// Counts an ability down by the rustacean turn that just ended
+pub fn tick_cooldown(variant: &mut RustaceanVariant) {
+    variant.ready_in = variant.ready_in.saturating_sub(1);
+}
+
+/// Whether the level is won or lost once the rustaceans moved, None while it goes on.
+///
+/// `trapped` and `escaped` count the rustaceans out of the `rustaceans` on the board, `taps` the traps placed this level.
+/// A puzzle is lost once its taps are used up and the rustacean can still move.
+pub fn level_outcome(rules: &LevelRules, rustaceans: usize, trapped: usize, escaped: usize, taps: u32) -> Option<bool> {
+    match rules.win_condition {
+        WinCondition::TrapAll if escaped > 0 => Some(false),
+        WinCondition::TrapAll if trapped == rustaceans => Some(true),
+        WinCondition::TrapAny if trapped > 0 => Some(true),
+        WinCondition::TrapAny if escaped == rustaceans => Some(false),
+        _ if rules.puzzle.as_ref().is_some_and(|puzzle| taps >= puzzle.tap_budget) => Some(false),
+        _ => None,
+    }
+}
+
+/// A new game on `difficulty`, it starts on a level that counts as reached
+pub fn record_game_started(stats: &mut TotalGameStats, difficulty: DifficultyKind) {
+    stats.games_played += 1;
+    let difficulty_stats = stats.for_difficulty(difficulty);
+    difficulty_stats.games_played += 1;
+    difficulty_stats.record_level = difficulty_stats.record_level.max(1);
+}
+
+/// A tap that placed a trap, an area trap counts once
+pub fn record_tap(stats: &mut TotalGameStats, difficulty: DifficultyKind) {
+    stats.tiles_tapped += 1;
+    stats.for_difficulty(difficulty).tiles_tapped += 1;
+}
+
+/// Rustaceans trapped when a level was won and rustaceans that got off the board
+pub fn record_rustaceans(stats: &mut TotalGameStats, difficulty: DifficultyKind, trapped: u64, escaped: u64) {
+    stats.tigers_trapped += trapped;
+    stats.tigers_escaped += escaped;
+    let difficulty_stats = stats.for_difficulty(difficulty);
+    difficulty_stats.tigers_trapped += trapped;
+    difficulty_stats.tigers_escaped += escaped;
+}
+
+/// Stars of a generated level that was won, puzzles keep their best stars per puzzle instead
+pub fn record_stars(stats: &mut TotalGameStats, difficulty: DifficultyKind, stars: u8) {
+    stats.level_stars += stars as u64;
+    stats.for_difficulty(difficulty).level_stars += stars as u64;
+}
+
+/// A classic game got to `level`
+pub fn record_reached_level(stats: &mut TotalGameStats, difficulty: DifficultyKind, level: u32) {
+    stats.record_level = stats.record_level.max(level as u64);
+    let difficulty_stats = stats.for_difficulty(difficulty);
+    difficulty_stats.record_level = difficulty_stats.record_level.max(level as u64);
+}
@@ -0,0 +1,252 @@
+use std::collections::HashSet;
+
+use bevy::{ecs::{component::Component, entity::Entity, query::{With, Without}, resource::Resource, system::{Commands, Query, Res, ResMut}}, text::TextSpan};
+use crate::{game::{endless::LevelRules, level_setup::{RustaceanPos, TrapTile}, pathfinding::{BoardView, RustaceanMove, TileSet}, rules::tick_cooldown, tiles::{TileKind, TileKinds}, traps::ReinforcedTrap}, settings::locale::Localization, utils::{hexgrid_utils::{GridSize, GridTilePos}, rng_utils}};
+
+/// Most rustaceans that can share a board
+pub const MAX_RUSTACEANS: u32 = 3;
+
+/// When a board with several rustaceans counts as won
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum WinCondition {
+    /// Every rustacean has to be trapped, a single escape loses the level
+    #[default]
+    TrapAll,
+    /// Trapping one rustacean is enough, the level is only lost once all of them escaped
+    TrapAny,
+}
+
+impl WinCondition {
+    /// Localization key of the condition's display name
+    pub fn key(self) -> &'static str {
+        match self {
+            WinCondition::TrapAll => "win.trap_all",
+            WinCondition::TrapAny => "win.trap_any",
+        }
+    }
+
+    /// The other condition, used by the settings button
+    pub fn next(self) -> Self {
+        match self {
+            WinCondition::TrapAll => WinCondition::TrapAny,
+            WinCondition::TrapAny => WinCondition::TrapAll,
+        }
+    }
+}
+
+/// Ability a rustacean variant is born with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum RustaceanAbility {
+    /// Jumps over a neighboring trap onto the tile behind it, reinforced traps stop it
+    Jumper,
+    /// Makes two moves in one turn
+    Sprinter,
+}
+
+impl RustaceanAbility {
+    /// Localization key of the variant's display name
+    pub fn key(self) -> &'static str {
+        match self {
+            RustaceanAbility::Jumper => "rustacean.jumper",
+            RustaceanAbility::Sprinter => "rustacean.sprinter",
+        }
+    }
+
+    /// Rustacean turns between two uses
+    pub fn cooldown(self) -> u32 {
+        match self {
+            RustaceanAbility::Jumper => 3,
+            RustaceanAbility::Sprinter => 2,
+        }
+    }
+}
+
+/// A rustacean's ability and the rustacean turns until it can use it, plain rustaceans dont have this component
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RustaceanVariant {
+    pub ability: RustaceanAbility,
+    pub ready_in: u32,
+}
+
+impl RustaceanVariant {
+    /// A fresh variant, it has to wait out its cooldown once before the first use
+    pub fn new(ability: RustaceanAbility) -> Self {
+        RustaceanVariant { ability, ready_in: ability.cooldown() }
+    }
+
+    pub fn ready(&self) -> bool {
+        self.ready_in == 0
+    }
+
+    pub fn is_ready_jumper(&self) -> bool {
+        self.ability == RustaceanAbility::Jumper && self.ready()
+    }
+
+    pub fn use_ability(&mut self) {
+        self.ready_in = self.ability.cooldown();
+    }
+}
+
+/// Counts every rustacean ability down by the turn that just ended
+pub fn tick_rustacean_abilities(
+    mut variant_q: Query<&mut RustaceanVariant>,
+) {
+    for mut variant in variant_q.iter_mut() {
+        // only abilities on cooldown are touched, so the others dont show up as changed
+        if !variant.ready() {
+            tick_cooldown(&mut variant);
+        }
+    }
+}
+
+/// Picks the ability of every rustacean on a generated board, some stay plain
+pub fn random_rustacean_abilities(count: usize, unlocked: &[RustaceanAbility]) -> Vec<Option<RustaceanAbility>> {
+    (0..count)
+        .map(|_| match rng_utils::random_range(0..=unlocked.len()) {
+            0 => None,
+            index => Some(unlocked[index - 1]),
+        })
+        .collect()
+}
+
+/// Place of a rustacean in the turn order, the lowest one moves first
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RustaceanOrder(pub usize);
+
+/// Rustaceans that already moved during the current rustacean turn
+#[derive(Resource, Default)]
+pub struct RustaceanTurnProgress {
+    pub moved: Vec<Entity>,
+    /// Rustaceans that stepped into mud this turn, they sit out the next one
+    pub muddy: Vec<Entity>,
+}
+
+pub fn reset_rustacean_turn_progress(
+    mut commands: Commands,
+) {
+    commands.insert_resource(RustaceanTurnProgress::default());
+}
+
+/// Starts the next rustacean turn with the rustaceans stuck in mud counted as moved
+pub fn rest_muddy_rustaceans(
+    mut progress: ResMut<RustaceanTurnProgress>,
+) {
+    progress.moved = std::mem::take(&mut progress.muddy);
+}
+
+/// Which of the rustaceans at `positions` are trapped for good.
+///
+/// A rustacean is trapped once it has no move left but onto other trapped rustaceans,
+/// so a group that only blocks itself in counts as trapped as a whole.
+/// The rustaceans flagged in `jumpers` also count their jumps over `jumpable` traps, whether or not the jump is ready yet.
+/// The board's open tiles still include the rustaceans' own, rustaceans that escaped are never trapped.
+pub fn trapped_rustaceans(positions: &[(i32, i32)], jumpers: &[bool], jumpable: &TileSet, board: &BoardView) -> Vec<bool> {
+    // start from every rustacean on the board and drop the ones that still have a way to go
+    let mut trapped: Vec<bool> = positions.iter().map(|pos| !board.tiles.escaped(*pos, &board.grid_size)).collect();
+
+    loop {
+        let occupied: HashSet<(i32, i32)> = positions.iter()
+            .zip(&trapped)
+            .filter(|(_, trapped)| **trapped)
+            .map(|(pos, _)| *pos)
+            .collect();
+
+        let freed: Vec<usize> = positions.iter()
+            .enumerate()
+            .filter(|(index, _)| trapped[*index])
+            .filter(|(index, pos)| {
+                let mut moves = board.moves(**pos);
+                if jumpers.get(*index).copied().unwrap_or(false) {
+                    moves.extend(board.jumps(**pos, jumpable));
+                }
+                moves.iter().any(|(next_move, _)| match next_move {
+                    RustaceanMove::Hop(x, y) | RustaceanMove::Escape(x, y) => !occupied.contains(&(*x, *y)),
+                    RustaceanMove::Trapped => false,
+                })
+            })
+            .map(|(index, _)| index)
+            .collect();
+
+        if freed.is_empty() {
+            return trapped;
+        }
+        for index in freed {
+            trapped[index] = false;
+        }
+    }
+}
+
+/// Span of the level HUD counting the rustaceans still loose on the board
+#[derive(Component)]
+pub struct RustaceansText;
+
+pub fn rustaceans_text_update(
+    rules: Res<LevelRules>,
+    localization: Res<Localization>,
+    grid_q: Query<&GridSize>,
+    rustacean_q: Query<(&RustaceanPos, Option<&RustaceanVariant>)>,
+    tile_q: Query<(&GridTilePos, Option<&TileKind>), Without<TrapTile>>,
+    jumpable_q: Query<&GridTilePos, (With<TrapTile>, Without<ReinforcedTrap>)>,
+    mut query: Query<&mut TextSpan, With<RustaceansText>>,
+) {
+    // a single rustacean is either loose or the level is over
+    let text = match grid_q.single() {
+        Ok(grid_size) if rules.rustacean_count > 1 => {
+            let positions: Vec<(i32, i32)> = rustacean_q.iter().map(|(pos, _)| (pos.x, pos.y)).collect();
+            let jumpers: Vec<bool> = rustacean_q.iter()
+                .map(|(_, variant)| variant.is_some_and(|variant| variant.ability == RustaceanAbility::Jumper))
+                .collect();
+            let jumpable = TileSet::from_tiles(*grid_size, jumpable_q.iter().map(|pos| (pos.x, pos.y)));
+            let tiles = TileKinds::new(tile_q.iter().filter_map(|(pos, kind)| kind.map(|kind| ((pos.x, pos.y), *kind))));
+            let open_tiles = TileSet::from_tiles(*grid_size, tile_q.iter()
+                .filter(|(_, kind)| kind.is_none_or(|kind| *kind != TileKind::Rock))
+                .map(|(pos, _)| (pos.x, pos.y)));
+            let trapped = trapped_rustaceans(&positions, &jumpers, &jumpable, &BoardView { grid_size: *grid_size, open_tiles: &open_tiles, tiles: &tiles });
+
+            let free = positions.iter()
+                .zip(trapped)
+                .filter(|(pos, trapped)| !tiles.escaped(**pos, grid_size) && !trapped)
+                .count();
+            format!("\n{}", localization.plural("hud.rustaceans_free", free as u64))
+        }
+        _ => String::new(),
+    };
+
+    for mut span in &mut query {
+        if text != span.0 {
+            **span = text.clone()
+        }
+    }
+}
+
+/// Span of the level HUD with the ability of every rustacean variant and when it can use it next
+#[derive(Component)]
+pub struct RustaceanAbilityText;
+
+pub fn rustacean_ability_text_update(
+    localization: Res<Localization>,
+    rustacean_q: Query<(&RustaceanOrder, &RustaceanVariant)>,
+    mut query: Query<&mut TextSpan, With<RustaceanAbilityText>>,
+) {
+    let mut variants: Vec<(&RustaceanOrder, &RustaceanVariant)> = rustacean_q.iter().collect();
+    variants.sort_unstable_by_key(|(order, _)| **order);
+
+    let text: String = variants.iter()
+        .map(|(_, variant)| {
+            let status = match variant.ready_in {
+                0 => localization.text("ability.ready"),
+                turns => localization.plural("ability.cooling", turns as u64),
+            };
+            format!("\n{}", localization.format("hud.rustacean_ability", &[
+                ("name", &localization.text(variant.ability.key())),
+                ("status", &status),
+            ]))
+        })
+        .collect();
+
+    for mut span in &mut query {
+        if text != span.0 {
+            **span = text.clone()
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::{ecs::{component::Component, event::EventReader, query::With, resource::Resource, system::{Commands, Query, Res, ResMut}, world::World}, input::{keyboard::{Key, KeyboardInput}, ButtonState}, log::{debug, warn}, text::TextSpan, time::Time, ui::{widget::Text, Node, PositionType, Val}, utils::default};
+use crate::{game::{events::AbilityUsed, game::CurrentLevel, levels::{CurrentLevelTaps, spawn_hud_text}, rating::LevelPar}, menu::menu::{MenuButtonAction, MenuButtonPressed}, settings::{launch::{data_path, ensure_data_dir}, locale::Localization, settings::Settings}};
+
+/// Points for every level cleared in a run
+const LEVEL_POINTS: u64 = 1000;
+/// Points for every tap a level was cleared under par
+const TAP_SAVED_POINTS: u64 = 100;
+/// Seconds a level can take before its time bonus is gone
+const PAR_SECS: f32 = 30.0;
+/// Points for every second a level was cleared under PAR_SECS
+const SECOND_POINTS: f32 = 10.0;
+/// Points taken away for every ability used as a hint
+const ABILITY_PENALTY: u64 = 250;
+/// Entries kept in the high score table
+pub const MAX_HIGH_SCORES: usize = 10;
+/// Longest name that can be typed in for a high score
+pub const MAX_NAME_LEN: usize = 12;
+
+/// Score of the classic run being played
+#[derive(Resource, Default, Debug)]
+pub struct RunScore {
+    pub levels_cleared: u32,
+    pub taps_saved: u32,
+    /// Seconds spent on boards over the whole run
+    pub secs: f32,
+    level_secs: f32,
+    time_bonus: u64,
+    pub abilities_used: u32,
+}
+
+impl RunScore {
+    pub fn total(&self) -> u64 {
+        let earned = self.levels_cleared as u64 * LEVEL_POINTS
+            + self.taps_saved as u64 * TAP_SAVED_POINTS
+            + self.time_bonus;
+        earned.saturating_sub(self.abilities_used as u64 * ABILITY_PENALTY)
+    }
+}
+
+/// A finished run in the high score table
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct HighScore {
+    pub name: String,
+    pub score: u64,
+    /// Level the run ended on
+    pub level: u32,
+    /// Day the run was played, as YYYY-MM-DD
+    pub date: String,
+}
+
+/// Best runs on this machine, best first, persisted to high_scores.json in the data directory
+#[derive(Resource, Default, serde::Serialize, serde::Deserialize)]
+pub struct HighScores {
+    pub entries: Vec<HighScore>,
+}
+
+impl HighScores {
+    /// Whether the score would make it into the table
+    pub fn qualifies(&self, score: u64) -> bool {
+        score > 0 && (self.entries.len() < MAX_HIGH_SCORES || self.entries.last().is_some_and(|last| score > last.score))
+    }
+
+    /// Puts the entry in its place and returns its rank, starting at 0
+    pub fn insert(&mut self, entry: HighScore) -> Option<usize> {
+        // ties go below the older entries
+        let rank = self.entries.iter().position(|other| entry.score > other.score).unwrap_or(self.entries.len());
+        self.entries.insert(rank, entry);
+        self.entries.truncate(MAX_HIGH_SCORES);
+        (rank < MAX_HIGH_SCORES).then_some(rank)
+    }
+}
+
+/// The run that just ended, shown on the high score screen until it is left
+#[derive(Resource, Debug)]
+pub struct LastRun {
+    pub score: u64,
+    pub level: u32,
+    /// Name being typed in, starts as the last name used
+    pub name: String,
+    pub qualifies: bool,
+    /// Rank the run was saved at
+    pub saved: Option<usize>,
+}
+
+impl LastRun {
+    /// Whether the player still has to type in a name
+    pub fn entering_name(&self) -> bool {
+        self.qualifies && self.saved.is_none()
+    }
+}
+
+/// Run condition that is true while a name is typed in for a new high score
+pub fn high_score_name_open(
+    last_run: Option<Res<LastRun>>,
+) -> bool {
+    last_run.is_some_and(|last_run| last_run.entering_name())
+}
+
+pub fn setup_high_scores(
+    mut commands: Commands,
+) {
+    let scores_path = data_path("high_scores.json");
+
+    let high_scores = if scores_path.exists() {
+        let json = std::fs::read_to_string(&scores_path)
+            .expect("Error: Path must exist\nContents of the file must be valid utf8");
+
+        serde_json::from_str(&json).unwrap_or_else(|err| {
+            warn!("Could not parse {}, starting an empty table: {err}", scores_path.display());
+            HighScores::default()
+        })
+    } else {
+        HighScores::default()
+    };
+
+    commands.insert_resource(high_scores);
+}
+
+fn save_high_scores(
+    high_scores: &HighScores,
+) {
+    ensure_data_dir();
+
+    let json = serde_json::to_string_pretty(high_scores)
+        .expect("Error: Implementation of Serialize must not decide to fail\nT should contain a map with string keys");
+
+    std::fs::write(data_path("high_scores.json"), json)
+        .expect("Error: The data directory must exist");
+}
+
+/// Today's date as YYYY-MM-DD in UTC
+fn today() -> String {
+    let days = SystemTime::now().duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs() / 86_400) as i64;
+
+    // days since 1970-01-01 to a civil date, counted in 400 year eras starting in march
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+pub fn start_run_score(
+    mut commands: Commands,
+) {
+    commands.insert_resource(RunScore::default());
+}
+
+/// Counts the time spent on the board being played
+pub fn tick_run_clock(
+    time: Res<Time>,
+    mut run: ResMut<RunScore>,
+) {
+    run.level_secs += time.delta_secs();
+}
+
+pub fn count_used_abilities(
+    mut ability_events: EventReader<AbilityUsed>,
+    mut run: ResMut<RunScore>,
+) {
+    run.abilities_used += ability_events.read().count() as u32;
+}
+
+/// Adds the level, the taps saved and the time bonus of the board that was just cleared
+pub fn score_cleared_level(
+    par: Res<LevelPar>,
+    level_taps: Res<CurrentLevelTaps>,
+    mut run: ResMut<RunScore>,
+) {
+    let level_secs = std::mem::take(&mut run.level_secs);
+    run.levels_cleared += 1;
+    run.taps_saved += par.0.saturating_sub(level_taps.0);
+    run.time_bonus += ((PAR_SECS - level_secs).max(0.0) * SECOND_POINTS) as u64;
+    run.secs += level_secs;
+    debug!("score: level cleared in {} taps and {level_secs:.1}s, run at {}", level_taps.0, run.total());
+}
+
+/// Keeps the score of the run that just ended for the high score screen
+pub fn finish_scored_run(
+    mut commands: Commands,
+    mut run: ResMut<RunScore>,
+    current_level: Res<CurrentLevel>,
+    high_scores: Res<HighScores>,
+    settings: Res<Settings>,
+) {
+    let level_secs = std::mem::take(&mut run.level_secs);
+    run.secs += level_secs;
+    let score = run.total();
+    debug!(
+        "score: run over on level {} after {:.0}s with {score} points, {} abilities used",
+        current_level.0, run.secs, run.abilities_used,
+    );
+
+    commands.insert_resource(LastRun {
+        score,
+        level: current_level.0,
+        name: settings.player_name.clone(),
+        qualifies: high_scores.qualifies(score),
+        saved: None,
+    });
+}
+
+/// Types in the name of a new high score, Enter or the save button puts it in the table
+pub fn edit_high_score_name(
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut menu_events: EventReader<MenuButtonPressed>,
+    last_run: Option<ResMut<LastRun>>,
+    mut high_scores: ResMut<HighScores>,
+    mut settings: ResMut<Settings>,
+) {
+    let Some(mut last_run) = last_run.filter(|last_run| last_run.entering_name()) else {
+        keyboard_events.clear();
+        menu_events.clear();
+        return;
+    };
+
+    let mut confirmed = menu_events.read().any(|event| event.0 == MenuButtonAction::SaveHighScore);
+    for event in keyboard_events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Backspace => {
+                last_run.name.pop();
+            }
+            Key::Enter => confirmed = true,
+            Key::Space if last_run.name.chars().count() < MAX_NAME_LEN => last_run.name.push(' '),
+            Key::Character(text) => {
+                for character in text.chars() {
+                    if (character.is_alphanumeric() || character == '-' || character == '_')
+                        && last_run.name.chars().count() < MAX_NAME_LEN {
+                        last_run.name.push(character);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if confirmed {
+        save_last_run(&mut last_run, &mut high_scores, &mut settings);
+    }
+}
+
+/// Leaving the screen without saving still keeps the score, under the name typed so far
+pub fn keep_unsaved_high_score(
+    mut commands: Commands,
+    last_run: Option<ResMut<LastRun>>,
+    mut high_scores: ResMut<HighScores>,
+    mut settings: ResMut<Settings>,
+) {
+    let Some(mut last_run) = last_run else {
+        return;
+    };
+    if last_run.entering_name() {
+        save_last_run(&mut last_run, &mut high_scores, &mut settings);
+    }
+    commands.remove_resource::<LastRun>();
+}
+
+fn save_last_run(
+    last_run: &mut LastRun,
+    high_scores: &mut HighScores,
+    settings: &mut Settings,
+) {
+    let name = match last_run.name.trim() {
+        "" => Settings::default().player_name,
+        name => name.to_string(),
+    };
+    debug!("score: saving {} for {name}", last_run.score);
+
+    last_run.saved = high_scores.insert(HighScore {
+        name: name.clone(),
+        score: last_run.score,
+        level: last_run.level,
+        date: today(),
+    });
+    save_high_scores(high_scores);
+
+    // the next high score starts with the same name
+    if settings.player_name != name {
+        settings.player_name = name;
+    }
+}
+
+/// Line of the high score screen showing the name being typed in
+#[derive(Component)]
+pub struct HighScoreNameText;
+
+pub fn high_score_name_text_update(
+    last_run: Option<Res<LastRun>>,
+    localization: Res<Localization>,
+    mut query: Query<&mut Text, With<HighScoreNameText>>,
+) {
+    let Some(last_run) = last_run else {
+        return;
+    };
+
+    let text = localization.format("high_scores.name", &[("name", &last_run.name)]);
+    for mut name_text in &mut query {
+        if text != name_text.0 {
+            **name_text = text.clone()
+        }
+    }
+}
+
+/// Span of the HUD showing the score of the run
+#[derive(Component)]
+pub struct RunScoreText;
+
+pub fn spawn_run_score_text(
+    world: &mut World,
+) {
+    spawn_hud_text(world, Node {
+        position_type: PositionType::Absolute,
+        top: Val::Px(0.0),
+        right: Val::Px(20.0),
+        ..default()
+    }, RunScoreText);
+}
+
+pub fn run_score_text_update(
+    run: Res<RunScore>,
+    localization: Res<Localization>,
+    mut query: Query<&mut TextSpan, With<RunScoreText>>,
+) {
+    let text = localization.format("hud.score", &[("n", &localization.number(run.total()))]);
+
+    for mut span in &mut query {
+        if text != span.0 {
+            **span = text.clone()
+        }
+    }
+}
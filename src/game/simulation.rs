@@ -0,0 +1,333 @@
+use crate::{game::{difficulty::{load_difficulty_profiles, DifficultyKind}, endless::generated_level_rules, headless::HeadlessLevel, rustaceans::WinCondition, strategy::StrategyRegistry}, settings::launch::{parse_difficulty, parse_grid, parse_level, parse_number}, utils::{hexgrid_utils::GridSize, rng_utils::{random_seed, seed_game_rng}}};
+
+pub const SIMULATE_USAGE: &str = "\
+Usage: simulate [OPTIONS]
+
+Plays classic games without a window and reports how they went.
+
+Options:
+  --games <N>            classic games to play, 1000 by default
+  --trapper <NAME>       random, blocker, greedy or any other registered trapper, greedy by default
+  --ai <NAME>            wanderer, greedy, pathfinder, strategist or any other registered strategy, the difficulty's own by default
+  --difficulty <NAME>    easy, normal, hard or custom, normal by default
+  --rustaceans <N>       rustaceans on every board, 1 by default
+  --win <all|any>        trap all or trap any rustacean to win, all by default
+  --level <N>            level every game starts on, 1 by default
+  --max-levels <N>       levels a game can clear before it counts as done, 40 by default
+  --grid <COLSxROWS>     board size of every level instead of the difficulty's
+  --seed <N>             seed every random pick, the same seed plays the same games
+  --format <csv|json>    how the report is written, csv by default
+  --output <FILE>        write the report to a file instead of stdout
+  --data-dir <PATH>      folder difficulty.json is read from, ./configs by default
+  -h, --help             print this help";
+
+/// What to simulate, read from the command line of the simulate binary
+#[derive(Clone, Debug)]
+pub struct SimConfig {
+    pub games: u32,
+    /// Name of the trapper strategy
+    pub trapper: String,
+    /// Rustacean strategy on every level, the AI tier of the difficulty and endless mode decides when unset
+    pub rustacean_ai: Option<String>,
+    pub difficulty: DifficultyKind,
+    pub rustaceans: u32,
+    pub win_condition: WinCondition,
+    pub start_level: u32,
+    pub max_levels: u32,
+    pub grid_size: Option<GridSize>,
+    pub seed: Option<u64>,
+    pub format: SimFormat,
+    pub output: Option<std::path::PathBuf>,
+    pub data_dir: Option<std::path::PathBuf>,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        SimConfig {
+            games: 1000,
+            trapper: String::from("greedy"),
+            rustacean_ai: None,
+            difficulty: DifficultyKind::Normal,
+            rustaceans: 1,
+            win_condition: WinCondition::TrapAll,
+            start_level: 1,
+            max_levels: 40,
+            grid_size: None,
+            seed: None,
+            format: SimFormat::Csv,
+            output: None,
+            data_dir: None,
+        }
+    }
+}
+
+/// How the report is written
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SimFormat {
+    Csv,
+    Json,
+}
+
+impl SimConfig {
+    /// Reads the config from the command line arguments, without the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut config = SimConfig::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+            match arg.as_str() {
+                "--games" => config.games = parse_number(&arg, &value()?)?,
+                // checked against the registry once the simulation runs, so registered bots can be picked too
+                "--trapper" => config.trapper = value()?.to_lowercase(),
+                "--ai" => config.rustacean_ai = Some(value()?.to_lowercase()),
+                "--difficulty" => config.difficulty = parse_difficulty(&value()?)?,
+                "--rustaceans" => config.rustaceans = parse_number(&arg, &value()?)?,
+                "--win" => {
+                    config.win_condition = match value()?.to_lowercase().as_str() {
+                        "all" => WinCondition::TrapAll,
+                        "any" => WinCondition::TrapAny,
+                        other => return Err(format!("--win expects all or any, got {other}")),
+                    };
+                }
+                "--level" => config.start_level = parse_level(&arg, &value()?)?,
+                "--max-levels" => config.max_levels = parse_number(&arg, &value()?)?,
+                "--grid" => config.grid_size = Some(parse_grid(&arg, &value()?)?),
+                "--seed" => config.seed = Some(parse_number(&arg, &value()?)?),
+                "--format" => {
+                    config.format = match value()?.to_lowercase().as_str() {
+                        "csv" => SimFormat::Csv,
+                        "json" => SimFormat::Json,
+                        other => return Err(format!("--format expects csv or json, got {other}")),
+                    };
+                }
+                "--output" => config.output = Some(std::path::PathBuf::from(value()?)),
+                "--data-dir" => config.data_dir = Some(std::path::PathBuf::from(value()?)),
+                _ => return Err(format!("unknown option {arg}")),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// Totals of every level played on one level number, or on all of them
+#[derive(Clone, Debug, Default, serde::Serialize)]
+pub struct LevelSummary {
+    /// None for the totals over every level
+    pub level: Option<u32>,
+    pub played: u32,
+    pub won: u32,
+    pub win_rate: f64,
+    pub avg_taps: f64,
+    /// Traps the generator placed at the start, the number the trap formula gives
+    pub avg_start_traps: f64,
+    /// Trap layouts generated, the rejected ones included
+    pub layouts: u32,
+    pub rejected_layouts: u32,
+    pub rejection_rate: f64,
+    #[serde(skip)]
+    taps: u64,
+    #[serde(skip)]
+    start_traps: u64,
+}
+
+impl LevelSummary {
+    fn add(&mut self, level: &HeadlessLevel, start_traps: usize, won: bool) {
+        self.played += 1;
+        self.won += won as u32;
+        self.taps += level.taps as u64;
+        self.start_traps += start_traps as u64;
+        self.layouts += level.rejected_layouts + 1;
+        self.rejected_layouts += level.rejected_layouts;
+    }
+
+    fn finish(&mut self) {
+        let ratio = |part: f64, whole: u32| if whole == 0 { 0.0 } else { part / whole as f64 };
+        self.win_rate = ratio(self.won as f64, self.played);
+        self.avg_taps = ratio(self.taps as f64, self.played);
+        self.avg_start_traps = ratio(self.start_traps as f64, self.played);
+        self.rejection_rate = ratio(self.rejected_layouts as f64, self.layouts);
+    }
+}
+
+/// Everything a simulation run found out
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct SimReport {
+    pub trapper: String,
+    /// The forced AI, or "profile" when the difficulty picked it
+    pub rustacean_ai: String,
+    pub difficulty: DifficultyKind,
+    pub seed: u64,
+    pub games: u32,
+    /// Levels cleared per game, a game ends on its first lost level
+    pub avg_game_levels: f64,
+    pub avg_game_taps: f64,
+    pub total: LevelSummary,
+    pub levels: Vec<LevelSummary>,
+}
+
+impl SimReport {
+    /// One row per level and a last one with the totals
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("level,played,won,win_rate,avg_taps,avg_start_traps,layouts,rejected_layouts,rejection_rate,avg_game_levels,avg_game_taps\n");
+        for summary in self.levels.iter().chain([&self.total]) {
+            let level = summary.level.map_or(String::from("all"), |level| level.to_string());
+            // the game averages only mean something on the totals row
+            let game = match summary.level {
+                Some(_) => String::from(","),
+                None => format!("{:.3},{:.3}", self.avg_game_levels, self.avg_game_taps),
+            };
+            csv.push_str(&format!(
+                "{level},{},{},{:.4},{:.3},{:.3},{},{},{:.4},{game}\n",
+                summary.played, summary.won, summary.win_rate, summary.avg_taps, summary.avg_start_traps,
+                summary.layouts, summary.rejected_layouts, summary.rejection_rate,
+            ));
+        }
+        csv
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self)
+            .expect("Error: Implementation of Serialize must not decide to fail\nT should contain a map with string keys")
+    }
+}
+
+/// Plays `config.games` classic games, each from the start level until a level is lost or `max_levels` are cleared.
+///
+/// The trapper and the forced rustacean strategy are looked up in `strategies` by name,
+/// a trapper tapping a tile that cant take a trap ends the run with an error.
+pub fn run_simulation(config: &SimConfig, strategies: &StrategyRegistry) -> Result<SimReport, String> {
+    let trapper = strategies.trapper(&config.trapper)
+        .ok_or_else(|| format!("unknown trapper {}, pick one of {}", config.trapper, strategies.trapper_names().join(", ")))?;
+    let forced_rustacean = match &config.rustacean_ai {
+        Some(name) => Some(strategies.rustacean(name)
+            .ok_or_else(|| format!("unknown rustacean AI {name}, pick one of {}", strategies.rustacean_names().join(", ")))?),
+        None => None,
+    };
+
+    let seed = config.seed.unwrap_or_else(random_seed);
+    seed_game_rng(seed);
+
+    let profiles = load_difficulty_profiles();
+    let profile = profiles.get(config.difficulty);
+
+    let mut total = LevelSummary::default();
+    let mut levels: Vec<LevelSummary> = Vec::new();
+    let (mut game_levels, mut game_taps) = (0u64, 0u64);
+
+    for _ in 0..config.games {
+        for level_number in config.start_level..config.start_level.saturating_add(config.max_levels) {
+            let rules = generated_level_rules(profile, level_number, config.grid_size, config.rustaceans, config.win_condition);
+            let rustacean = forced_rustacean.unwrap_or_else(|| strategies.rustacean_for(rules.rustacean_ai));
+            let mut level = HeadlessLevel::generate(rules);
+            let start_traps = level.traps.len();
+
+            let won = loop {
+                let Some(tap) = trapper.pick_tap(&level) else {
+                    break false;
+                };
+                // a refused tap would hand the rustacean a free move, and could do so forever
+                if !level.tap(tap) {
+                    return Err(format!("trapper {} tapped {:?} on level {level_number}, which cant take a trap", trapper.name(), tap));
+                }
+                level.rustacean_turn(|current, board, jumps| rustacean.choose_move(current.pos, board, jumps));
+                if let Some(won) = level.outcome() {
+                    break won;
+                }
+            };
+
+            let index = (level_number - config.start_level) as usize;
+            if levels.len() <= index {
+                levels.push(LevelSummary { level: Some(level_number), ..Default::default() });
+            }
+            levels[index].add(&level, start_traps, won);
+            total.add(&level, start_traps, won);
+            game_taps += level.taps as u64;
+
+            if !won {
+                break;
+            }
+            game_levels += 1;
+        }
+    }
+
+    total.finish();
+    for summary in levels.iter_mut() {
+        summary.finish();
+    }
+    let games = config.games.max(1) as f64;
+
+    Ok(SimReport {
+        trapper: trapper.name().to_string(),
+        rustacean_ai: forced_rustacean.map_or(String::from("profile"), |rustacean| rustacean.name().to_string()),
+        difficulty: config.difficulty,
+        seed,
+        games: config.games,
+        avg_game_levels: game_levels as f64 / games,
+        avg_game_taps: game_taps as f64 / games,
+        total,
+        levels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{game::{headless::HeadlessLevel, strategy::TrapperStrategy}, settings::launch::use_test_data_dir};
+    use super::*;
+
+    /// Always taps a tile off the board
+    struct OffBoardTrapper;
+
+    impl TrapperStrategy for OffBoardTrapper {
+        fn name(&self) -> &str {
+            "off-board"
+        }
+
+        fn pick_tap(&self, _level: &HeadlessLevel) -> Option<(i32, i32)> {
+            Some((-5, -5))
+        }
+    }
+
+    fn parse(args: &[&str]) -> Result<SimConfig, String> {
+        SimConfig::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn reads_the_simulator_options() {
+        let config = parse(&["--games", "20", "--trapper", "Blocker", "--win", "any", "--format", "json"]).unwrap();
+
+        assert_eq!(config.games, 20);
+        assert_eq!(config.trapper, "blocker");
+        assert_eq!(config.win_condition, WinCondition::TrapAny);
+        assert_eq!(config.format, SimFormat::Json);
+    }
+
+    #[test]
+    fn refuses_bad_values() {
+        for args in [
+            &["--games", "many"][..],
+            &["--win", "some"],
+            &["--format", "xml"],
+            &["--trappers", "greedy"],
+        ] {
+            assert!(parse(args).is_err(), "{args:?} should be refused");
+        }
+    }
+
+    #[test]
+    fn refuses_a_missing_value() {
+        assert_eq!(parse(&["--games", "5", "--trapper"]).unwrap_err(), "--trapper needs a value");
+    }
+
+    #[test]
+    fn a_refused_tap_ends_the_run() {
+        use_test_data_dir();
+        let mut strategies = StrategyRegistry::default();
+        strategies.register_trapper(OffBoardTrapper);
+        let config = SimConfig { games: 1, trapper: String::from("off-board"), seed: Some(3), ..Default::default() };
+
+        let err = run_simulation(&config, &strategies).expect_err("the off board tap must be refused");
+        assert!(err.contains("off-board"), "{err}");
+    }
+}
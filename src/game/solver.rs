@@ -0,0 +1,170 @@
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use crate::{game::{difficulty::RustaceanAi, pathfinding::{choose_move, pathfind, BoardView, Path, RustaceanMove, TileSet}, tiles::{TileKind, TileKinds}}, utils::hexgrid_utils::{get_hex_horizontal_neighbor_pos, GridSize}};
+
+/// A board position the solver searches from, the player is always the one to move
+pub struct SolverBoard<'a> {
+    pub grid_size: GridSize,
+    /// Tiles without a trap or rock
+    pub open_tiles: &'a TileSet,
+    pub tiles: &'a TileKinds,
+    pub rustacean: (i32, i32),
+    pub rustacean_ai: RustaceanAi,
+}
+
+/// Where the search stands: the rustacean's tile, whether it sits out its next move in mud,
+/// the taps made so far and the taps left
+type SearchKey = ((i32, i32), bool, BTreeSet<(i32, i32)>, u32);
+
+/// Fewest taps that trap the rustacean whatever it does, if that can be done within `max_taps`.
+///
+/// A returned count is always a real solution. Taps far away from the rustacean are not tried,
+/// so on rare boards the true minimum can be lower than the count found.
+pub fn min_taps_to_trap(board: &SolverBoard, max_taps: u32) -> Option<u32> {
+    let mut failed = HashSet::new();
+    (1..=max_taps).find(|&taps| {
+        let mut open_tiles = board.open_tiles.clone();
+        can_trap(board, &mut open_tiles, board.rustacean, false, &mut BTreeSet::new(), taps, &mut failed)
+    })
+}
+
+/// Hops around the rustacean the greedy trapper looks at for its next tap
+const GREEDY_REACH: u32 = 2;
+
+/// Taps a greedy trapper needs against a rustacean that always runs for a way out, if it gets there within `max_taps`.
+///
+/// Every tap goes on the tile near the rustacean that leaves it the longest way out. Far quicker than
+/// [`min_taps_to_trap`] on big open boards, but it can take more taps than needed or let the rustacean get away.
+/// A wanderer is played as a pathfinder so the count comes out the same every time.
+pub fn greedy_taps_to_trap(board: &SolverBoard, max_taps: u32) -> Option<u32> {
+    let ai = match board.rustacean_ai {
+        RustaceanAi::Wanderer => RustaceanAi::Pathfinder,
+        ai => ai,
+    };
+    let mut open_tiles = board.open_tiles.clone();
+    let mut rustacean = board.rustacean;
+    let mut resting = false;
+
+    for taps in 1..=max_taps {
+        let tap = greedy_tap(&BoardView { grid_size: board.grid_size, open_tiles: &open_tiles, tiles: board.tiles }, rustacean)?;
+        open_tiles.remove(&tap);
+
+        let view = BoardView { grid_size: board.grid_size, open_tiles: &open_tiles, tiles: board.tiles };
+        match choose_move(ai, rustacean, &view) {
+            RustaceanMove::Trapped => return Some(taps),
+            // the rustacean sits this turn out in the mud
+            _ if resting => resting = false,
+            RustaceanMove::Escape(..) => return None,
+            RustaceanMove::Hop(x, y) => {
+                rustacean = view.landing((x, y));
+                resting = board.tiles.kind(rustacean) == Some(TileKind::Mud);
+            }
+        }
+    }
+
+    None
+}
+
+/// The tap near the rustacean on `rustacean` that leaves it the longest way out, if there is an open tile near it
+pub fn greedy_tap(board: &BoardView, rustacean: (i32, i32)) -> Option<(i32, i32)> {
+    // nearest first, so ties go to the tile closest to the rustacean
+    tap_candidates(&board.grid_size, board.open_tiles, rustacean, GREEDY_REACH).into_iter()
+        .min_by_key(|tap| {
+            let mut after_tap = board.open_tiles.clone();
+            after_tap.remove(tap);
+            let view = BoardView { open_tiles: &after_tap, ..*board };
+            match pathfind(rustacean, &view) {
+                Path::NotFound => 0,
+                Path::Found(path) => u32::MAX - path.len() as u32,
+                Path::Escaped(..) => u32::MAX,
+            }
+        })
+}
+
+/// Every move the rustacean might make, the wanderer picks at random so all of its hops count
+fn possible_moves(ai: RustaceanAi, pos: (i32, i32), board: &BoardView) -> Vec<RustaceanMove> {
+    if ai != RustaceanAi::Wanderer {
+        return vec![choose_move(ai, pos, board)];
+    }
+
+    match choose_move(ai, pos, board) {
+        RustaceanMove::Hop(..) => board.moves(pos).into_iter()
+            .map(|(next_move, _)| next_move)
+            .collect(),
+        other => vec![other],
+    }
+}
+
+/// Depth limited search over the player's taps, `placed` is the memo key for the taps made so far
+fn can_trap(
+    board: &SolverBoard,
+    open_tiles: &mut TileSet,
+    rustacean: (i32, i32),
+    resting: bool,
+    placed: &mut BTreeSet<(i32, i32)>,
+    taps_left: u32,
+    failed: &mut HashSet<SearchKey>,
+) -> bool {
+    let key = (rustacean, resting, placed.clone(), taps_left);
+    if failed.contains(&key) {
+        return false;
+    }
+
+    for tap in tap_candidates(&board.grid_size, open_tiles, rustacean, taps_left) {
+        open_tiles.remove(&tap);
+        placed.insert(tap);
+
+        let view = BoardView { grid_size: board.grid_size, open_tiles, tiles: board.tiles };
+        let moves = possible_moves(board.rustacean_ai, rustacean, &view);
+        // where each move leaves the rustacean, worked out before the board changes again
+        let outcomes: Vec<Option<(i32, i32)>> = moves.iter()
+            .filter_map(|next_move| match *next_move {
+                RustaceanMove::Trapped => None,
+                RustaceanMove::Escape(..) => Some(None),
+                RustaceanMove::Hop(x, y) => Some(Some(view.landing((x, y)))),
+            })
+            .collect();
+        let stuck = outcomes.is_empty();
+
+        let trapped = stuck || if resting {
+            // the rustacean sits this turn out in the mud
+            taps_left > 1 && can_trap(board, open_tiles, rustacean, false, placed, taps_left - 1, failed)
+        } else {
+            outcomes.into_iter().all(|outcome| match outcome {
+                None => false,
+                Some(landing) => taps_left > 1
+                    && can_trap(board, open_tiles, landing, board.tiles.kind(landing) == Some(TileKind::Mud), placed, taps_left - 1, failed),
+            })
+        };
+
+        placed.remove(&tap);
+        open_tiles.insert(tap);
+
+        if trapped {
+            return true;
+        }
+    }
+
+    failed.insert(key);
+    false
+}
+
+/// Open tiles within `taps_left` hops of the rustacean, nearest first, the only taps worth trying
+fn tap_candidates(grid_size: &GridSize, open_tiles: &TileSet, rustacean: (i32, i32), taps_left: u32) -> Vec<(i32, i32)> {
+    let mut seen = TileSet::from_tiles(*grid_size, [rustacean]);
+    let mut queue = VecDeque::from([(rustacean, 0)]);
+    let mut candidates = Vec::new();
+
+    while let Some((current, distance)) = queue.pop_front() {
+        if distance == taps_left {
+            continue;
+        }
+        for n in get_hex_horizontal_neighbor_pos(current.0, current.1) {
+            if open_tiles.contains(&n) && seen.insert(n) {
+                candidates.push(n);
+                queue.push_back((n, distance + 1));
+            }
+        }
+    }
+
+    candidates
+}
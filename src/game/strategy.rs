@@ -0,0 +1,197 @@
+use bevy::ecs::resource::Resource;
+
+use crate::{game::{difficulty::RustaceanAi, headless::HeadlessLevel, pathfinding::{choose_move_or_jump, pathfind, BoardView, Path, RustaceanMove}, solver::greedy_tap}, utils::{hexgrid_utils::get_hex_horizontal_neighbor_pos, rng_utils}};
+
+/// Picks the tiles a trapper bot taps, on a generated level played without entities
+pub trait TrapperStrategy: Send + Sync {
+    /// Name the strategy is registered and picked by
+    fn name(&self) -> &str;
+
+    /// The tile to tap next, None once there is nothing left to tap
+    fn pick_tap(&self, level: &HeadlessLevel) -> Option<(i32, i32)>;
+}
+
+/// Picks the moves of a rustacean, both on screen and on a headless board
+pub trait RustaceanStrategy: Send + Sync {
+    /// Name the strategy is registered and picked by
+    fn name(&self) -> &str;
+
+    /// The move of the rustacean standing on `start`, `jumps` are the jumps it has ready this turn
+    fn choose_move(&self, start: (i32, i32), board: &BoardView, jumps: &[(RustaceanMove, u32)]) -> RustaceanMove;
+}
+
+/// Every AI tier is a strategy of its own, pathfinder is the BFS the game has always used
+impl RustaceanStrategy for RustaceanAi {
+    fn name(&self) -> &str {
+        RustaceanAi::name(*self)
+    }
+
+    fn choose_move(&self, start: (i32, i32), board: &BoardView, jumps: &[(RustaceanMove, u32)]) -> RustaceanMove {
+        choose_move_or_jump(*self, start, board, jumps)
+    }
+}
+
+/// Taps any open tile
+pub struct RandomTrapper;
+
+/// Taps the next tile on the rustacean's cheapest way out
+pub struct BlockerTrapper;
+
+/// Taps the tile that leaves the rustacean the longest way out, like the par is worked out
+pub struct GreedyTrapper;
+
+impl TrapperStrategy for RandomTrapper {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn pick_tap(&self, level: &HeadlessLevel) -> Option<(i32, i32)> {
+        random_tap(level)
+    }
+}
+
+impl TrapperStrategy for BlockerTrapper {
+    fn name(&self) -> &str {
+        "blocker"
+    }
+
+    fn pick_tap(&self, level: &HeadlessLevel) -> Option<(i32, i32)> {
+        let Some(target) = level.target() else {
+            return random_tap(level);
+        };
+        let open_tiles = level.open_tiles();
+        let board = BoardView { grid_size: level.grid_size(), open_tiles: &open_tiles, tiles: &level.tiles };
+
+        let tap = match pathfind(target, &board) {
+            Path::Found(path) => path.first().copied(),
+            // next to a way out, block it if it is an exit tile
+            Path::Escaped(x, y) => Some((x, y)).filter(|pos| open_tiles.contains(pos)),
+            // walled in already, keep taking room away from it
+            Path::NotFound => get_hex_horizontal_neighbor_pos(target.0, target.1).into_iter()
+                .find(|pos| open_tiles.contains(pos)),
+        };
+        tap.or_else(|| random_tap(level))
+    }
+}
+
+impl TrapperStrategy for GreedyTrapper {
+    fn name(&self) -> &str {
+        "greedy"
+    }
+
+    fn pick_tap(&self, level: &HeadlessLevel) -> Option<(i32, i32)> {
+        let Some(target) = level.target() else {
+            return random_tap(level);
+        };
+        let open_tiles = level.open_tiles();
+        let board = BoardView { grid_size: level.grid_size(), open_tiles: &open_tiles, tiles: &level.tiles };
+
+        greedy_tap(&board, target).or_else(|| random_tap(level))
+    }
+}
+
+/// Any open tile, the fallback of every built in trapper
+pub fn random_tap(level: &HeadlessLevel) -> Option<(i32, i32)> {
+    let open_tiles = level.open_tiles();
+    if open_tiles.is_empty() {
+        return None;
+    }
+    open_tiles.iter().nth(rng_utils::random_range(0..open_tiles.len()))
+}
+
+/// The trapper and rustacean strategies that can be picked by name at runtime.
+///
+/// Starts out with the built in trappers and one rustacean strategy per AI tier,
+/// registering one under a name that is taken replaces it.
+#[derive(Resource)]
+pub struct StrategyRegistry {
+    trappers: Vec<Box<dyn TrapperStrategy>>,
+    rustaceans: Vec<Box<dyn RustaceanStrategy>>,
+    /// Strategy every rustacean on screen follows instead of its AI tier
+    rustacean_override: Option<String>,
+}
+
+impl Default for StrategyRegistry {
+    fn default() -> Self {
+        let mut registry = StrategyRegistry { trappers: Vec::new(), rustaceans: Vec::new(), rustacean_override: None };
+        registry.register_trapper(RandomTrapper);
+        registry.register_trapper(BlockerTrapper);
+        registry.register_trapper(GreedyTrapper);
+        for ai in RustaceanAi::ALL {
+            registry.register_rustacean(ai);
+        }
+        registry
+    }
+}
+
+impl StrategyRegistry {
+    pub fn register_trapper(&mut self, strategy: impl TrapperStrategy + 'static) {
+        self.trappers.retain(|trapper| trapper.name() != strategy.name());
+        self.trappers.push(Box::new(strategy));
+    }
+
+    pub fn register_rustacean(&mut self, strategy: impl RustaceanStrategy + 'static) {
+        self.rustaceans.retain(|rustacean| rustacean.name() != strategy.name());
+        self.rustaceans.push(Box::new(strategy));
+    }
+
+    /// Looks a trapper up by name, ignoring case
+    pub fn trapper(&self, name: &str) -> Option<&dyn TrapperStrategy> {
+        self.trappers.iter().find(|trapper| trapper.name().eq_ignore_ascii_case(name)).map(|trapper| trapper.as_ref())
+    }
+
+    /// Looks a rustacean strategy up by name, ignoring case
+    pub fn rustacean(&self, name: &str) -> Option<&dyn RustaceanStrategy> {
+        self.rustaceans.iter().find(|rustacean| rustacean.name().eq_ignore_ascii_case(name)).map(|rustacean| rustacean.as_ref())
+    }
+
+    /// Makes every rustacean on screen follow the strategy registered as `name`, whatever its AI tier
+    pub fn set_rustacean_override(&mut self, name: &str) -> Result<(), String> {
+        let Some(rustacean) = self.rustacean(name) else {
+            return Err(format!("unknown rustacean AI {name}, pick one of {}", self.rustacean_names().join(", ")));
+        };
+        self.rustacean_override = Some(rustacean.name().to_string());
+        Ok(())
+    }
+
+    /// The strategy a rustacean of the `ai` tier follows, the override when one is set
+    pub fn rustacean_for(&self, ai: RustaceanAi) -> &dyn RustaceanStrategy {
+        // strategies are only ever replaced, so an override that was set stays registered
+        self.rustacean_override.as_deref()
+            .and_then(|name| self.rustacean(name))
+            .or_else(|| self.rustacean(ai.name()))
+            .expect("Error: every AI tier is registered from the start and can only be replaced")
+    }
+
+    pub fn trapper_names(&self) -> Vec<&str> {
+        self.trappers.iter().map(|trapper| trapper.name()).collect()
+    }
+
+    pub fn rustacean_names(&self) -> Vec<&str> {
+        self.rustaceans.iter().map(|rustacean| rustacean.name()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_override_is_followed_on_every_ai_tier() {
+        let mut strategies = StrategyRegistry::default();
+        strategies.set_rustacean_override("Strategist").unwrap();
+
+        for ai in RustaceanAi::ALL {
+            assert_eq!(strategies.rustacean_for(ai).name(), RustaceanAi::Strategist.name());
+        }
+    }
+
+    #[test]
+    fn an_unknown_override_is_refused() {
+        let mut strategies = StrategyRegistry::default();
+        let err = strategies.set_rustacean_override("teleporter").unwrap_err();
+
+        assert!(err.contains("unknown rustacean AI teleporter"), "{err}");
+        assert_eq!(strategies.rustacean_for(RustaceanAi::Greedy).name(), RustaceanAi::Greedy.name());
+    }
+}
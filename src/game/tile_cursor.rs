@@ -0,0 +1,156 @@
+use bevy::{ecs::{resource::Resource, system::{Commands, Local, Query, ResMut, Res, Single}}, input::{gamepad::{Gamepad, GamepadButton}, keyboard::KeyCode, ButtonInput}, math::Vec2};
+use crate::{game::{level_setup::TileTapped, pathfinding::in_bounds, tile_lookup::TileLookup}, utils::hexgrid_utils::{GridSize, GridTilePos, HexGridOrientation, HextileF2FSize}};
+
+/// Stick deflection needed to move the cursor by one tile
+const STICK_PRESS: f32 = 0.6;
+/// Stick deflection under which the stick counts as centered again
+const STICK_RELEASE: f32 = 0.3;
+/// How closely a neighbor has to line up with the pressed direction to be picked
+const MIN_ALIGNMENT: f32 = 0.6;
+
+/// Keyboard and gamepad controlled tile selection
+#[derive(Resource, Default)]
+pub struct TileCursor {
+    pub x: i32,
+    pub y: i32,
+    /// Hidden while the mouse is being used, shown again on the first key or button press
+    pub visible: bool,
+}
+
+impl TileCursor {
+    pub fn is_at(&self, pos: &GridTilePos) -> bool {
+        self.visible && self.x == pos.x && self.y == pos.y
+    }
+}
+
+pub fn reset_tile_cursor(
+    mut cursor: ResMut<TileCursor>,
+    grid_size: Single<&GridSize>,
+) {
+    cursor.x = grid_size.cols / 2;
+    cursor.y = grid_size.rows / 2;
+}
+
+/// Direction requested this frame by the keyboard, d-pad or left stick, in world space
+fn requested_direction(
+    keys: &ButtonInput<KeyCode>,
+    gamepads: &Query<&Gamepad>,
+    stick_latched: &mut bool,
+) -> Vec2 {
+    let key_dirs = [
+        ([KeyCode::KeyW, KeyCode::ArrowUp], Vec2::new(0., 1.)),
+        ([KeyCode::KeyS, KeyCode::ArrowDown], Vec2::new(0., -1.)),
+        ([KeyCode::KeyA, KeyCode::ArrowLeft], Vec2::new(-1., 0.)),
+        ([KeyCode::KeyD, KeyCode::ArrowRight], Vec2::new(1., 0.)),
+    ];
+    // diagonals, since a hex tile has six neighbors and arrows only reach four of them
+    let diagonal_dirs = [
+        (KeyCode::KeyQ, Vec2::new(-1., 1.)),
+        (KeyCode::KeyE, Vec2::new(1., 1.)),
+        (KeyCode::KeyZ, Vec2::new(-1., -1.)),
+        (KeyCode::KeyC, Vec2::new(1., -1.)),
+    ];
+    let pad_dirs = [
+        (GamepadButton::DPadUp, Vec2::new(0., 1.)),
+        (GamepadButton::DPadDown, Vec2::new(0., -1.)),
+        (GamepadButton::DPadLeft, Vec2::new(-1., 0.)),
+        (GamepadButton::DPadRight, Vec2::new(1., 0.)),
+    ];
+
+    let mut direction = Vec2::ZERO;
+
+    for (codes, dir) in key_dirs {
+        if keys.any_just_pressed(codes) {
+            direction += dir;
+        }
+    }
+    for (code, dir) in diagonal_dirs {
+        if keys.just_pressed(code) {
+            direction += dir;
+        }
+    }
+
+    for gamepad in gamepads.iter() {
+        for (button, dir) in pad_dirs {
+            if gamepad.just_pressed(button) {
+                direction += dir;
+            }
+        }
+
+        // the stick only moves once per push, it has to return to center before moving again
+        let stick = gamepad.left_stick();
+        if !*stick_latched && stick.length() >= STICK_PRESS {
+            *stick_latched = true;
+            direction += stick;
+        } else if *stick_latched && stick.length() <= STICK_RELEASE {
+            *stick_latched = false;
+        }
+    }
+
+    direction
+}
+
+pub fn move_tile_cursor(
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mut stick_latched: Local<bool>,
+    grid_q: Single<(&GridSize, &HextileF2FSize, &HexGridOrientation)>,
+    mut cursor: ResMut<TileCursor>,
+) {
+    let direction = requested_direction(&keys, &gamepads, &mut stick_latched);
+    if direction == Vec2::ZERO {
+        return;
+    }
+
+    // first press only reveals the cursor where it was left
+    if !cursor.visible {
+        cursor.visible = true;
+        return;
+    }
+
+    let (grid_size, f2f_size, orientation) = grid_q.into_inner();
+    let current = GridTilePos::new(cursor.x, cursor.y);
+    let origin = current.coord_to_world(grid_size, f2f_size, orientation);
+    let direction = direction.normalize();
+
+    // pick the in bounds neighbor that lines up best with the requested direction,
+    // ties go to the first neighbor so holding left/right zigzags along a row
+    let best = current.get_neighbor_pos().into_iter()
+        .filter(|n| in_bounds(*n, grid_size))
+        .map(|(x, y)| {
+            let offset = GridTilePos::new(x, y).coord_to_world(grid_size, f2f_size, orientation) - origin;
+            ((x, y), offset.normalize().dot(direction))
+        })
+        .filter(|(_, alignment)| *alignment >= MIN_ALIGNMENT)
+        .reduce(|best, next| if next.1 > best.1 { next } else { best });
+
+    if let Some(((x, y), _)) = best {
+        cursor.x = x;
+        cursor.y = y;
+    }
+}
+
+pub fn confirm_tile_cursor(
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mut cursor: ResMut<TileCursor>,
+    tiles: TileLookup,
+    mut commands: Commands,
+) {
+    let confirmed = keys.any_just_pressed([KeyCode::Space, KeyCode::Enter])
+        || gamepads.iter().any(|gamepad| gamepad.just_pressed(GamepadButton::South));
+
+    if !confirmed {
+        return;
+    }
+
+    // dont place a trap on a tile the player cant see selected
+    if !cursor.visible {
+        cursor.visible = true;
+        return;
+    }
+
+    if let Some(entity) = tiles.entity((cursor.x, cursor.y)) {
+        commands.trigger_targets(TileTapped, entity);
+    }
+}
@@ -0,0 +1,176 @@
+use std::f32::consts::PI;
+use bevy::{color::Mix, ecs::{component::Component, entity::Entity, observer::Trigger, event::EventReader, query::{Has, With}, system::{Commands, Query, Res, ResMut}}, math::Vec3, picking::events::{Out, Over, Pointer}, sprite::Sprite, time::{Time, Timer, TimerMode}, transform::components::Transform};
+use crate::{game::{abilities::RevealedPath, events::LevelEnded, hotseat::LegalMove, level_setup::TrapTile, tile_cursor::TileCursor, tiles::TileKind, traps::{DecayingTrap, ReinforcedTrap}}, settings::theme::Theme, utils::hexgrid_utils::GridTilePos};
+
+/// Seconds it takes for a freshly placed trap to settle into its final color
+const TRAP_PLACE_SECS: f32 = 0.25;
+/// Seconds an invalid tap keeps shaking its tile
+const INVALID_TAP_SECS: f32 = 0.35;
+/// Maximum horizontal offset of a shaking tile, in world units
+const SHAKE_AMPLITUDE: f32 = 6.0;
+
+/// Marker for the tile currently under the cursor
+#[derive(Component)]
+pub struct TileHovered;
+
+/// Short pop animation played on a tile when a trap is placed on it
+#[derive(Component)]
+pub struct TrapPlaceAnim(pub Timer);
+
+impl TrapPlaceAnim {
+    pub fn new() -> Self {
+        TrapPlaceAnim(Timer::from_seconds(TRAP_PLACE_SECS, TimerMode::Once))
+    }
+}
+
+impl Default for TrapPlaceAnim {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// "Can't place here" shake played on a tile after an invalid tap
+#[derive(Component)]
+pub struct InvalidTapShake {
+    pub timer: Timer,
+    /// Translation of the tile before it started shaking
+    pub origin: Vec3,
+}
+
+impl InvalidTapShake {
+    pub fn new(origin: Vec3) -> Self {
+        InvalidTapShake {
+            timer: Timer::from_seconds(INVALID_TAP_SECS, TimerMode::Once),
+            origin
+        }
+    }
+}
+
+/// Inserts an [`InvalidTapShake`] on the tile, restarting the shake if one is already playing
+pub fn shake_tile(
+    commands: &mut Commands,
+    tile_entity: Entity,
+    translation: Vec3,
+    current_shake: Option<&InvalidTapShake>
+) {
+    // keep the original position so repeated taps dont make the tile drift
+    let origin = match current_shake {
+        Some(shake) => shake.origin,
+        None => translation,
+    };
+    commands.entity(tile_entity).insert(InvalidTapShake::new(origin));
+}
+
+pub fn tile_hover_observer() -> impl FnMut(Trigger<Pointer<Over>>, Query<&GridTilePos>, ResMut<TileCursor>, Commands) {
+    move | 
+        trigger: Trigger<Pointer<Over>>,
+        tile_q: Query<&GridTilePos>,
+        mut cursor: ResMut<TileCursor>,
+        mut commands: Commands
+    | {
+        commands.entity(trigger.target()).insert(TileHovered);
+
+        // the mouse takes over, keyboard navigation resumes from the hovered tile
+        if let Ok(pos) = tile_q.get(trigger.target()) {
+            cursor.x = pos.x;
+            cursor.y = pos.y;
+            cursor.visible = false;
+        }
+    }
+}
+
+pub fn tile_unhover_observer() -> impl FnMut(Trigger<Pointer<Out>>, Commands) {
+    move | trigger: Trigger<Pointer<Out>>, mut commands: Commands | {
+        commands.entity(trigger.target()).remove::<TileHovered>();
+    }
+}
+
+pub fn animate_trap_placement(
+    mut commands: Commands,
+    mut anim_q: Query<(Entity, &mut TrapPlaceAnim, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (entity, mut anim, mut transform) in anim_q.iter_mut() {
+        anim.0.tick(time.delta());
+
+        // pop the tile up slightly and let it settle back
+        let t = anim.0.fraction();
+        transform.scale = Vec3::splat(1.0 + 0.15 * (t * PI).sin());
+
+        if anim.0.finished() {
+            transform.scale = Vec3::ONE;
+            commands.entity(entity).remove::<TrapPlaceAnim>();
+        }
+    }
+}
+
+pub fn animate_invalid_taps(
+    mut commands: Commands,
+    mut shake_q: Query<(Entity, &mut InvalidTapShake, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (entity, mut shake, mut transform) in shake_q.iter_mut() {
+        shake.timer.tick(time.delta());
+
+        // dampened side to side wobble
+        let t = shake.timer.fraction();
+        let offset = (t * PI * 6.0).sin() * SHAKE_AMPLITUDE * (1.0 - t);
+        transform.translation = shake.origin + Vec3::new(offset, 0., 0.);
+
+        if shake.timer.finished() {
+            transform.translation = shake.origin;
+            commands.entity(entity).remove::<InvalidTapShake>();
+        }
+    }
+}
+
+/// Colors the whole board in the win or lose color once the level is over
+pub fn color_board_on_level_end(
+    mut level_end_events: EventReader<LevelEnded>,
+    mut tile_q: Query<&mut Sprite, With<GridTilePos>>,
+    theme: Res<Theme>,
+) {
+    let Some(level_end) = level_end_events.read().last() else {
+        return;
+    };
+    let color = if level_end.won { theme.win } else { theme.lose };
+    for mut sprite in tile_q.iter_mut() {
+        sprite.color = color;
+    }
+}
+
+/// Derives the color of every tile from its kind, trap, hover and animation state
+pub fn update_tile_colors(
+    mut tile_q: Query<
+        (&mut Sprite, &GridTilePos, Option<&TileKind>, Has<TrapTile>, Has<DecayingTrap>, Has<ReinforcedTrap>, Has<TileHovered>, Has<LegalMove>, Has<RevealedPath>, Option<&TrapPlaceAnim>, Option<&InvalidTapShake>),
+    >,
+    cursor: Res<TileCursor>,
+    theme: Res<Theme>,
+) {
+    for (mut sprite, pos, kind, is_trap, is_decaying, is_reinforced, is_hovered, is_legal_move, is_revealed, place_anim, shake) in tile_q.iter_mut() {
+        let open_color = theme.tile_color(kind);
+        let trap_color = match (is_decaying, is_reinforced) {
+            (true, _) => theme.decaying_trap,
+            (_, true) => theme.reinforced_trap,
+            _ => theme.trap,
+        };
+        let is_hovered = is_hovered || cursor.is_at(pos);
+        let mut color = match (is_trap, place_anim) {
+            (true, Some(anim)) => open_color.mix(&trap_color, anim.0.fraction()),
+            (true, None) => trap_color,
+            (false, _) if is_hovered => theme.tile_hover,
+            (false, _) if is_legal_move => theme.legal_move,
+            (false, _) if is_revealed => theme.revealed_path,
+            (false, _) => open_color,
+        };
+
+        if let Some(shake) = shake {
+            // flash red and fade back as the shake dies out
+            color = color.mix(&theme.invalid_tap, 0.6 * (1.0 - shake.timer.fraction()));
+        }
+
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
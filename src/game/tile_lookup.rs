@@ -0,0 +1,36 @@
+use bevy::{ecs::{entity::Entity, query::Has, system::{Query, SystemParam}}, transform::components::GlobalTransform};
+use crate::{game::{level_setup::TrapTile, tiles::TileKind, traps::ReinforcedTrap}, utils::hexgrid_utils::GridTiles};
+
+/// What is on a tile as far as the rules are concerned
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TileState {
+    pub trapped: bool,
+    /// Trapped with a trap a jumper cant get over
+    pub reinforced: bool,
+    pub kind: Option<TileKind>,
+}
+
+/// Finds the tiles of the board by their coordinates, through the [`GridTiles`] index the grid keeps
+#[derive(SystemParam)]
+pub struct TileLookup<'w, 's> {
+    grid_q: Query<'w, 's, &'static GridTiles>,
+    tile_q: Query<'w, 's, (&'static GlobalTransform, Has<TrapTile>, Has<ReinforcedTrap>, Option<&'static TileKind>)>,
+}
+
+impl TileLookup<'_, '_> {
+    /// The tile entity on `pos`, None off the board or while there is no board
+    pub fn entity(&self, pos: (i32, i32)) -> Option<Entity> {
+        self.grid_q.single().ok().and_then(|grid_tiles| grid_tiles.get(pos))
+    }
+
+    /// Where the tile on `pos` is in the world, tiles spawned this frame dont have it yet
+    pub fn transform(&self, pos: (i32, i32)) -> Option<&GlobalTransform> {
+        let (transform, _, _, _) = self.tile_q.get(self.entity(pos)?).ok()?;
+        Some(transform)
+    }
+
+    pub fn state(&self, pos: (i32, i32)) -> Option<TileState> {
+        let (_, trapped, reinforced, kind) = self.tile_q.get(self.entity(pos)?).ok()?;
+        Some(TileState { trapped, reinforced, kind: kind.copied() })
+    }
+}
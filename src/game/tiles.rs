@@ -0,0 +1,271 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::{ecs::{component::Component, entity::Entity, hierarchy::ChildOf, query::With, system::{Commands, Query, Res, Single}}, log::debug, math::Quat, picking::Pickable, text::{Text2d, TextColor}, transform::components::Transform};
+use crate::{game::{difficulty::SpecialTileCounts, endless::LevelRules, level_setup::RustaceanPos, pathfinding::in_bounds, tile_lookup::TileLookup}, settings::{locale::Localization, theme::Theme}, utils::{hexgrid_utils::{get_hex_horizontal_neighbor_pos, GridSize, GridTilePos}, rng_utils}};
+
+/// Search cost of stepping into mud, the hop itself and the turn the rustacean sits out
+pub const MUD_COST: u32 = 2;
+
+/// The six ways off a hex tile as seen on screen
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum HexDirection {
+    Up,
+    UpRight,
+    DownRight,
+    Down,
+    DownLeft,
+    UpLeft,
+}
+
+impl HexDirection {
+    pub const ALL: [HexDirection; 6] = [
+        HexDirection::Up,
+        HexDirection::UpRight,
+        HexDirection::DownRight,
+        HexDirection::Down,
+        HexDirection::DownLeft,
+        HexDirection::UpLeft,
+    ];
+
+    /// Neighbor of (x, y) in this direction, odd columns sit half a tile lower than even ones
+    pub fn step(self, x: i32, y: i32) -> (i32, i32) {
+        let shift = if x % 2 == 0 { 0 } else { -1 };
+        match self {
+            HexDirection::Up => (x, y + 1),
+            HexDirection::Down => (x, y - 1),
+            HexDirection::UpLeft => (x - 1, y + 1 + shift),
+            HexDirection::DownLeft => (x - 1, y + shift),
+            HexDirection::UpRight => (x + 1, y + 1 + shift),
+            HexDirection::DownRight => (x + 1, y + shift),
+        }
+    }
+
+    /// Rotation of the arrow drawn on a one way tile, counter clockwise from pointing up
+    pub fn angle(self) -> f32 {
+        let sixths = match self {
+            HexDirection::Up => 0.,
+            HexDirection::UpLeft => 1.,
+            HexDirection::DownLeft => 2.,
+            HexDirection::Down => 3.,
+            HexDirection::DownRight => 4.,
+            HexDirection::UpRight => 5.,
+        };
+        sixths * std::f32::consts::TAU / 6.
+    }
+
+    /// Direction of a digit laid out like a numpad, 8 is up and 3 is down right
+    pub fn from_numpad(digit: char) -> Option<Self> {
+        match digit {
+            '8' => Some(HexDirection::Up),
+            '9' => Some(HexDirection::UpRight),
+            '3' => Some(HexDirection::DownRight),
+            '2' => Some(HexDirection::Down),
+            '1' => Some(HexDirection::DownLeft),
+            '7' => Some(HexDirection::UpLeft),
+            _ => None,
+        }
+    }
+
+    /// The numpad digit of the direction, the other way around from [`HexDirection::from_numpad`]
+    pub fn numpad(self) -> char {
+        match self {
+            HexDirection::Up => '8',
+            HexDirection::UpRight => '9',
+            HexDirection::DownRight => '3',
+            HexDirection::Down => '2',
+            HexDirection::DownLeft => '1',
+            HexDirection::UpLeft => '7',
+        }
+    }
+}
+
+/// What sets a tile apart from a plain one, plain tiles dont have this component
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum TileKind {
+    /// Can never be entered or trapped
+    Rock,
+    /// Stepping in makes the rustacean sit out its next turn
+    Mud,
+    /// Stepping in carries the rustacean on to the paired teleporter, unless that one is blocked
+    Teleporter { pair: (i32, i32) },
+    /// Can only be left in `direction`
+    OneWay { direction: HexDirection },
+    /// Stepping in leaves the board, once a board has exits its edge no longer does
+    Exit,
+}
+
+/// A special tile as laid out in level files and sent to online clients
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SpecialTile {
+    pub pos: (i32, i32),
+    pub kind: TileKind,
+}
+
+/// Special tiles of a board by position, every other tile is a plain one
+#[derive(Clone, Debug, Default)]
+pub struct TileKinds {
+    kinds: HashMap<(i32, i32), TileKind>,
+    /// Exit tiles, sorted, kept apart since the AIs measure every move against them
+    exits: Vec<(i32, i32)>,
+}
+
+impl TileKinds {
+    pub fn new(tiles: impl IntoIterator<Item = ((i32, i32), TileKind)>) -> Self {
+        let kinds: HashMap<(i32, i32), TileKind> = tiles.into_iter().collect();
+        let mut exits: Vec<(i32, i32)> = kinds.iter()
+            .filter(|(_, kind)| **kind == TileKind::Exit)
+            .map(|(pos, _)| *pos)
+            .collect();
+        exits.sort_unstable();
+        TileKinds { kinds, exits }
+    }
+
+    pub fn from_special_tiles(tiles: &[SpecialTile]) -> Self {
+        Self::new(tiles.iter().map(|tile| (tile.pos, tile.kind)))
+    }
+
+    pub fn kind(&self, pos: (i32, i32)) -> Option<TileKind> {
+        // plain boards skip the hashing, the searches ask about every tile they look at
+        if self.kinds.is_empty() {
+            return None;
+        }
+        self.kinds.get(&pos).copied()
+    }
+
+    pub fn is_rock(&self, pos: (i32, i32)) -> bool {
+        self.kind(pos) == Some(TileKind::Rock)
+    }
+
+    /// Boards with exit tiles can only be left through them
+    pub fn has_exits(&self) -> bool {
+        !self.exits.is_empty()
+    }
+
+    pub fn exits(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.exits.iter().copied()
+    }
+
+    /// Whether a rustacean standing on `pos` has left the board
+    pub fn escaped(&self, pos: (i32, i32), grid_size: &GridSize) -> bool {
+        !in_bounds(pos, grid_size) || self.kind(pos) == Some(TileKind::Exit)
+    }
+}
+
+/// Number of hops between two tiles on an empty board
+pub fn hex_distance(a: (i32, i32), b: (i32, i32)) -> i32 {
+    // odd columns sit lower, so shift every column back onto a straight axis
+    let axial = |(x, y): (i32, i32)| (x, y - (x + 1).div_euclid(2));
+    let ((aq, ar), (bq, br)) = (axial(a), axial(b));
+    let (dq, dr) = (aq - bq, ar - br);
+    dq.abs().max(dr.abs()).max((dq + dr).abs())
+}
+
+/// Random special tiles for a generated board.
+///
+/// The rustaceans' tiles and their neighbors stay plain so no board starts with a rustacean walled in,
+/// exits go on the border.
+pub fn generate_special_tiles(counts: &SpecialTileCounts, grid_size: &GridSize, rustaceans: &[(i32, i32)]) -> Vec<SpecialTile> {
+    let kept_plain: HashSet<(i32, i32)> = rustaceans.iter()
+        .flat_map(|&(x, y)| get_hex_horizontal_neighbor_pos(x, y).into_iter().chain([(x, y)]))
+        .collect();
+
+    let (mut border, mut inner): (Vec<(i32, i32)>, Vec<(i32, i32)>) = (0..grid_size.cols)
+        .flat_map(|x| (0..grid_size.rows).map(move |y| (x, y)))
+        .filter(|pos| !kept_plain.contains(pos))
+        .partition(|pos| GridTilePos::new(pos.0, pos.1).is_border(grid_size));
+
+    rng_utils::shuffle(&mut border);
+    let exit_count = (counts.exits as usize).min(border.len());
+    let mut tiles: Vec<SpecialTile> = border.drain(..exit_count)
+        .map(|pos| SpecialTile { pos, kind: TileKind::Exit })
+        .collect();
+
+    // everything else may go anywhere that is left
+    inner.append(&mut border);
+    rng_utils::shuffle(&mut inner);
+    let mut free_tiles = inner.into_iter();
+
+    let kinds = std::iter::repeat_n(TileKind::Rock, counts.rocks as usize)
+        .chain(std::iter::repeat_n(TileKind::Mud, counts.mud as usize))
+        .chain((0..counts.one_way).map(|_| TileKind::OneWay {
+            direction: HexDirection::ALL[rng_utils::random_range(0..HexDirection::ALL.len())],
+        }));
+    tiles.extend(kinds.zip(free_tiles.by_ref()).map(|(kind, pos)| SpecialTile { pos, kind }));
+
+    for _ in 0..counts.teleporter_pairs {
+        let (Some(first), Some(second)) = (free_tiles.next(), free_tiles.next()) else {
+            break;
+        };
+        tiles.push(SpecialTile { pos: first, kind: TileKind::Teleporter { pair: second } });
+        tiles.push(SpecialTile { pos: second, kind: TileKind::Teleporter { pair: first } });
+    }
+
+    tiles
+}
+
+/// Letter or arrow drawn on a special tile
+#[derive(Component)]
+pub struct TileMarker;
+
+/// Gives the tiles of a new board their kinds, from the level file or at random
+pub fn place_special_tiles(
+    mut commands: Commands,
+    rules: Res<LevelRules>,
+    grid_size: Single<&GridSize>,
+    rustacean_q: Query<&RustaceanPos>,
+    tiles: TileLookup,
+    marker_q: Query<Entity, With<TileMarker>>,
+    localization: Res<Localization>,
+    theme: Res<Theme>,
+) {
+    // the last board's markers are still around when the grid is reused
+    for entity in marker_q.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let special_tiles = match &rules.fixed_board {
+        Some(board) => board.special_tiles.clone(),
+        None => {
+            let rustaceans: Vec<(i32, i32)> = rustacean_q.iter().map(|pos| (pos.x, pos.y)).collect();
+            generate_special_tiles(&rules.special_tiles, &grid_size, &rustaceans)
+        }
+    };
+    debug!("place_special_tiles: {} special tiles", special_tiles.len());
+
+    // both ends of a teleporter pair share a letter
+    let mut teleporters: Vec<(i32, i32)> = special_tiles.iter()
+        .filter_map(|tile| match tile.kind {
+            TileKind::Teleporter { pair } => Some(tile.pos.min(pair)),
+            _ => None,
+        })
+        .collect();
+    teleporters.sort_unstable();
+    teleporters.dedup();
+
+    for tile in &special_tiles {
+        let Some(entity) = tiles.entity(tile.pos) else {
+            continue;
+        };
+        commands.entity(entity).insert(tile.kind);
+
+        let (text, rotation) = match tile.kind {
+            TileKind::Teleporter { pair } => {
+                let index = teleporters.iter().position(|first| *first == tile.pos.min(pair)).unwrap_or(0);
+                (char::from(b'A' + (index % 26) as u8).to_string(), 0.)
+            }
+            TileKind::OneWay { direction } => (String::from("^"), direction.angle()),
+            TileKind::Rock | TileKind::Mud | TileKind::Exit => continue,
+        };
+
+        commands.spawn((
+            TileMarker,
+            Text2d::new(text),
+            localization.text_font(36.0),
+            TextColor(theme.hud_text),
+            Transform::from_xyz(0., 0., 0.05).with_rotation(Quat::from_rotation_z(rotation)),
+            Pickable::IGNORE,
+            ChildOf(entity),
+        ));
+    }
+}
@@ -0,0 +1,123 @@
+use bevy::{ecs::{component::Component, event::{EventReader, EventWriter}, query::With, resource::Resource, system::{Commands, Query, Res, ResMut}, world::World}, log::debug, state::state::NextState, text::TextSpan, time::Time, ui::{Node, PositionType, Val}, utils::default};
+use crate::{game::{difficulty::ActiveDifficulty, events::{LevelEnded, RustaceanEscaped, RustaceanTrapped}, game::TotalGameStats, game_mode::{game_mode_is, ActiveGameMode, GameMode}, levels::{LevelState, spawn_hud_text}}, settings::{locale::Localization}};
+
+/// Seconds on the clock when a time attack game starts
+const START_SECS: f32 = 60.0;
+/// Seconds added for every trapped rustacean
+const TRAP_BONUS_SECS: f32 = 10.0;
+/// Seconds taken away when a rustacean escapes
+const ESCAPE_PENALTY_SECS: f32 = 5.0;
+
+/// Countdown and score of a time attack game
+#[derive(Resource)]
+pub struct TimeAttackClock {
+    pub remaining_secs: f32,
+    pub trapped: u64,
+}
+
+impl TimeAttackClock {
+    pub fn is_running(&self) -> bool {
+        self.remaining_secs > 0.0
+    }
+}
+
+/// Run condition that is true while a time attack game still has time left
+pub fn time_attack_running(
+    active_mode: Option<Res<ActiveGameMode>>,
+    clock: Option<Res<TimeAttackClock>>,
+) -> bool {
+    game_mode_is(GameMode::TimeAttack)(active_mode)
+        && clock.is_some_and(|clock| clock.is_running())
+}
+
+/// Span of the HUD showing the remaining time and score
+#[derive(Component)]
+pub struct TimeAttackText;
+
+pub fn start_time_attack_clock(
+    mut commands: Commands,
+) {
+    commands.insert_resource(TimeAttackClock {
+        remaining_secs: START_SECS,
+        trapped: 0,
+    });
+}
+
+pub fn spawn_time_attack_text(
+    world: &mut World,
+) {
+    spawn_hud_text(world, Node {
+        position_type: PositionType::Absolute,
+        top: Val::Px(0.0),
+        right: Val::Px(20.0),
+        ..default()
+    }, TimeAttackText);
+}
+
+pub fn time_attack_text_update(
+    clock: Res<TimeAttackClock>,
+    localization: Res<Localization>,
+    mut query: Query<&mut TextSpan, With<TimeAttackText>>,
+) {
+    let text = format!(
+        "{}\n{}",
+        localization.format("hud.time", &[("secs", &format!("{:.0}", clock.remaining_secs.ceil()))]),
+        localization.format("hud.trapped", &[("n", &localization.number(clock.trapped))]),
+    );
+
+    for mut span in &mut query {
+        if text != span.0 {
+            **span = text.clone()
+        }
+    }
+}
+
+/// Counts down while a board is being played and ends the game when the clock hits zero
+pub fn tick_time_attack_clock(
+    time: Res<Time>,
+    mut clock: ResMut<TimeAttackClock>,
+    mut next_levelstate: ResMut<NextState<LevelState>>,
+    mut level_end_events: EventWriter<LevelEnded>,
+) {
+    if !clock.is_running() {
+        return;
+    }
+
+    clock.remaining_secs = (clock.remaining_secs - time.delta_secs()).max(0.0);
+
+    if !clock.is_running() {
+        debug!("time attack: time is up with {} rustaceans trapped", clock.trapped);
+        next_levelstate.set(LevelState::LevelLose);
+        level_end_events.write(LevelEnded { won: false });
+    }
+}
+
+/// Adds the bonus for trapped rustaceans and the penalty for escaped ones
+pub fn apply_time_attack_results(
+    mut trapped_events: EventReader<RustaceanTrapped>,
+    mut escaped_events: EventReader<RustaceanEscaped>,
+    mut clock: ResMut<TimeAttackClock>,
+) {
+    for _ in trapped_events.read() {
+        clock.trapped += 1;
+        clock.remaining_secs += TRAP_BONUS_SECS;
+    }
+    for _ in escaped_events.read() {
+        clock.remaining_secs = (clock.remaining_secs - ESCAPE_PENALTY_SECS).max(0.0);
+    }
+}
+
+/// Keeps the best time attack score, overall and for the difficulty that was played
+pub fn record_time_attack_score(
+    clock: Res<TimeAttackClock>,
+    difficulty: Res<ActiveDifficulty>,
+    mut game_stats: ResMut<TotalGameStats>,
+) {
+    if clock.is_running() {
+        return;
+    }
+
+    game_stats.time_attack_best = game_stats.time_attack_best.max(clock.trapped);
+    let difficulty_stats = game_stats.for_difficulty(difficulty.kind);
+    difficulty_stats.time_attack_best = difficulty_stats.time_attack_best.max(clock.trapped);
+}
@@ -0,0 +1,232 @@
+use bevy::{ecs::{component::Component, entity::Entity, hierarchy::ChildOf, query::{Added, With}, resource::Resource, system::{Commands, Query, Res, ResMut}}, input::{keyboard::KeyCode, ButtonInput}, log::debug, picking::Pickable, text::{Text2d, TextColor, TextSpan}, transform::components::Transform};
+use crate::{game::{difficulty::TrapInventoryCounts, endless::LevelRules, level_setup::TrapTile, tile_feedback::TrapPlaceAnim}, settings::{locale::Localization, theme::Theme}};
+
+/// Rustacean turns a decaying trap lasts
+pub const DECAYING_TRAP_TURNS: u32 = 3;
+
+/// Kinds of trap the player can place, picked with the number keys
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum TrapKind {
+    /// Stays for the rest of the level, there is no limit on these
+    #[default]
+    Permanent,
+    /// Vanishes after [`DECAYING_TRAP_TURNS`] rustacean turns
+    Decaying,
+    /// Stays for the rest of the level, and rustacean abilities cant get past it
+    Reinforced,
+    /// Traps the tapped tile and every free neighbor
+    Area,
+}
+
+impl TrapKind {
+    /// In number key order
+    pub const ALL: [TrapKind; 4] = [
+        TrapKind::Permanent,
+        TrapKind::Decaying,
+        TrapKind::Reinforced,
+        TrapKind::Area,
+    ];
+
+    /// Localization key of the kind's display name
+    pub fn key(self) -> &'static str {
+        match self {
+            TrapKind::Permanent => "trap.permanent",
+            TrapKind::Decaying => "trap.decaying",
+            TrapKind::Reinforced => "trap.reinforced",
+            TrapKind::Area => "trap.area",
+        }
+    }
+}
+
+/// A trap that vanishes once `turns_left` more rustacean turns have passed
+#[derive(Component)]
+pub struct DecayingTrap {
+    pub turns_left: u32,
+}
+
+/// A trap rustacean abilities cant get past
+#[derive(Component)]
+pub struct ReinforcedTrap;
+
+/// Turns left of a decaying trap, drawn on its tile
+#[derive(Component)]
+pub struct DecayCountdown;
+
+/// Limited traps the player has left this level, regular traps are never used up
+#[derive(Resource, Debug, Default)]
+pub struct TrapInventory {
+    pub selected: TrapKind,
+    pub left: TrapInventoryCounts,
+    /// Whether the level handed out any limited traps at all, the HUD only lists them then
+    pub offered: bool,
+}
+
+impl TrapInventory {
+    pub fn new(counts: TrapInventoryCounts) -> Self {
+        TrapInventory {
+            selected: TrapKind::Permanent,
+            left: counts,
+            offered: counts != TrapInventoryCounts::NONE,
+        }
+    }
+
+    /// Traps of `kind` left, `None` for the unlimited regular trap
+    pub fn left(&self, kind: TrapKind) -> Option<u32> {
+        match kind {
+            TrapKind::Permanent => None,
+            TrapKind::Decaying => Some(self.left.decaying),
+            TrapKind::Reinforced => Some(self.left.reinforced),
+            TrapKind::Area => Some(self.left.area),
+        }
+    }
+
+    /// Selects `kind` if there is any of it left
+    pub fn select(&mut self, kind: TrapKind) {
+        if self.left(kind) != Some(0) {
+            self.selected = kind;
+        }
+    }
+
+    /// Uses up a trap of the selected kind, going back to regular traps once that kind runs out
+    pub fn take(&mut self) -> TrapKind {
+        let kind = self.selected;
+        let left = match kind {
+            TrapKind::Permanent => return kind,
+            TrapKind::Decaying => &mut self.left.decaying,
+            TrapKind::Reinforced => &mut self.left.reinforced,
+            TrapKind::Area => &mut self.left.area,
+        };
+        *left = left.saturating_sub(1);
+        if *left == 0 {
+            self.selected = TrapKind::Permanent;
+        }
+        kind
+    }
+}
+
+pub fn reset_trap_inventory(
+    mut commands: Commands,
+    rules: Res<LevelRules>,
+) {
+    commands.insert_resource(TrapInventory::new(rules.trap_inventory));
+}
+
+/// Puts traps of `kind` on the tiles, an area trap passes the tapped tile and its free neighbors
+pub fn place_trap(commands: &mut Commands, kind: TrapKind, tiles: &[Entity]) {
+    for tile in tiles {
+        let mut tile_commands = commands.entity(*tile);
+        tile_commands.insert((TrapTile, TrapPlaceAnim::new()));
+        match kind {
+            TrapKind::Decaying => {
+                tile_commands.insert(DecayingTrap { turns_left: DECAYING_TRAP_TURNS });
+            }
+            TrapKind::Reinforced => {
+                tile_commands.insert(ReinforcedTrap);
+            }
+            TrapKind::Permanent | TrapKind::Area => {}
+        }
+    }
+}
+
+pub fn select_trap_kind(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut inventory: ResMut<TrapInventory>,
+) {
+    let slots = [
+        [KeyCode::Digit1, KeyCode::Numpad1],
+        [KeyCode::Digit2, KeyCode::Numpad2],
+        [KeyCode::Digit3, KeyCode::Numpad3],
+        [KeyCode::Digit4, KeyCode::Numpad4],
+    ];
+
+    for (keys_of_slot, kind) in slots.into_iter().zip(TrapKind::ALL) {
+        if keys.any_just_pressed(keys_of_slot) {
+            inventory.select(kind);
+            debug!("traps: selected {:?}", inventory.selected);
+        }
+    }
+}
+
+/// Counts every decaying trap down after a rustacean turn and takes away the ones that ran out
+pub fn decay_traps(
+    mut commands: Commands,
+    mut trap_q: Query<(Entity, &mut DecayingTrap)>,
+) {
+    for (entity, mut trap) in trap_q.iter_mut() {
+        trap.turns_left = trap.turns_left.saturating_sub(1);
+        if trap.turns_left == 0 {
+            debug!("traps: decaying trap ran out");
+            commands.entity(entity).remove::<(TrapTile, DecayingTrap)>();
+        }
+    }
+}
+
+/// Draws the turns left on every decaying trap
+pub fn show_decay_countdowns(
+    mut commands: Commands,
+    new_trap_q: Query<Entity, Added<DecayingTrap>>,
+    trap_q: Query<&DecayingTrap>,
+    mut countdown_q: Query<(Entity, &ChildOf, &mut Text2d), With<DecayCountdown>>,
+    localization: Res<Localization>,
+    theme: Res<Theme>,
+) {
+    for entity in new_trap_q.iter() {
+        commands.spawn((
+            DecayCountdown,
+            Text2d::new(DECAYING_TRAP_TURNS.to_string()),
+            localization.text_font(36.0),
+            TextColor(theme.hud_text),
+            Transform::from_xyz(0., 0., 0.05),
+            Pickable::IGNORE,
+            ChildOf(entity),
+        ));
+    }
+
+    for (entity, parent, mut text) in countdown_q.iter_mut() {
+        match trap_q.get(parent.parent()) {
+            Ok(trap) => {
+                let turns_left = trap.turns_left.to_string();
+                if text.0 != turns_left {
+                    text.0 = turns_left;
+                }
+            }
+            Err(_) => commands.entity(entity).despawn(),
+        }
+    }
+}
+
+/// Span of the level HUD listing the trap kinds and how many are left
+#[derive(Component)]
+pub struct TrapInventoryText;
+
+pub fn trap_inventory_text_update(
+    inventory: Res<TrapInventory>,
+    localization: Res<Localization>,
+    mut query: Query<&mut TextSpan, With<TrapInventoryText>>,
+) {
+    let text: String = if inventory.offered {
+        TrapKind::ALL.iter()
+            .enumerate()
+            .map(|(index, kind)| {
+                let left = match inventory.left(*kind) {
+                    Some(left) => left.to_string(),
+                    None => localization.text("hud.trap_unlimited"),
+                };
+                let marker = if inventory.selected == *kind { "> " } else { "" };
+                format!("\n{marker}{}", localization.format("hud.trap_slot", &[
+                    ("key", &(index + 1).to_string()),
+                    ("name", &localization.text(kind.key())),
+                    ("left", &left),
+                ]))
+            })
+            .collect()
+    } else {
+        String::new()
+    };
+
+    for mut span in &mut query {
+        if text != span.0 {
+            **span = text.clone()
+        }
+    }
+}
@@ -0,0 +1,7 @@
+pub mod utils;
+pub mod menu;
+pub mod game;
+pub mod network;
+pub mod settings;
+pub mod sound;
+pub mod terminal;
@@ -1,15 +1,53 @@
-use bevy::{ prelude::*, window::{WindowResolution} };
+use bevy::{ log::LogPlugin, prelude::*, window::{MonitorSelection, WindowMode, WindowResolution} };
 
-use crate::{ game::game::GamePlugin, menu::menu::MenuPlugin, utils::helper_utils::{scroll_zoom_camera_system, toggle_resolution}};
-
-mod utils;
-mod menu;
-mod game;
+use trap_the_tiger_rs::{ game::{game::GamePlugin, replay::{Replay, ReplayPlayback}, strategy::StrategyRegistry}, menu::menu::MenuPlugin, network::network::NetworkPlugin, settings::{launch::{set_data_dir, LaunchOptions, LaunchWindowMode, USAGE}, settings::SettingsPlugin}, sound::sound::SoundPlugin, utils::helper_utils::{fit_camera_to_grid, scroll_zoom_camera_system, toggle_resolution}};
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{USAGE}");
+        return;
+    }
+
+    let mut options = LaunchOptions::parse(args).unwrap_or_else(|err| {
+        eprintln!("Error: {err}\n\n{USAGE}");
+        std::process::exit(2);
+    });
+    if let Some(data_dir) = &options.data_dir {
+        set_data_dir(data_dir.clone());
+    }
+
     let mut app = App::new();
 
-    let window_resolution = WindowResolution::new(960., 540. );
+    if let Some(replay_path) = options.replay.clone() {
+        let replay = Replay::load(&replay_path).unwrap_or_else(|err| {
+            eprintln!("Error: {err}");
+            std::process::exit(2);
+        });
+        options.follow_replay(&replay);
+        app.insert_resource(ReplayPlayback::new(replay));
+    }
+
+    let (width, height) = options.resolution.unwrap_or((960., 540.));
+    let window_resolution = WindowResolution::new(width, height);
+    let window_mode = match options.window_mode {
+        LaunchWindowMode::Windowed => WindowMode::Windowed,
+        LaunchWindowMode::Fullscreen => WindowMode::BorderlessFullscreen(MonitorSelection::Primary),
+    };
+    let log_plugin = match options.log_level {
+        Some(level) => LogPlugin { level, ..Default::default() },
+        None => LogPlugin::default(),
+    };
+
+    let mut strategies = StrategyRegistry::default();
+    if let Some(name) = &options.rustacean_ai {
+        strategies.set_rustacean_override(name).unwrap_or_else(|err| {
+            eprintln!("Error: {err}\n\n{USAGE}");
+            std::process::exit(2);
+        });
+    }
+    app.insert_resource(strategies);
+    app.insert_resource(options);
 
     app.add_plugins((
         DefaultPlugins
@@ -19,17 +57,22 @@ fn main() {
                         Some(Window{
                             title: "Trap the Tiger".into(),
                             resolution: window_resolution,
+                            mode: window_mode,
                             ..Default::default()
                         }), 
                         ..Default::default()
                 }
             )
-            .set( ImagePlugin::default_nearest() ),
+            .set( ImagePlugin::default_nearest() )
+            .set( log_plugin ),
+        SettingsPlugin,
         MenuPlugin,
-        GamePlugin
+        GamePlugin,
+        NetworkPlugin,
+        SoundPlugin
     ));
 
-    app.add_systems(Update, (toggle_resolution, scroll_zoom_camera_system));
+    app.add_systems(Update, (toggle_resolution, scroll_zoom_camera_system, fit_camera_to_grid).chain());
 
     app.run();
 }
@@ -1,17 +1,12 @@
-use bevy::{app::{AppExit, Plugin, Update}, color::{palettes::css::ORANGE, Color}, ecs::{ component::Component, entity::Entity, event::EventWriter, query::{Changed, With}, schedule::IntoScheduleConfigs, system::{Commands, Query, Res, ResMut}}, prelude::{children, SpawnRelated}, state::{app::AppExtStates, condition::in_state, state::{NextState, OnEnter, OnExit}}, text::{TextColor, TextFont}, ui::{widget::{Button, Text}, AlignItems, BackgroundColor, FlexDirection, Interaction, JustifyContent, Node, UiRect, Val}, utils::default};
-use crate::game::game::{save_total_game_stats, AppState, MenuState, TotalGameStats};
-
-const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
-const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
-const HOVERED_BUTTON: Color = Color::srgb(0.25, 0.25, 0.25);
-const HOVERED_PRESSED_BUTTON: Color = Color::srgb(0.25, 0.65, 0.25);
-const PRESSED_BUTTON: Color = Color::srgb(0.35, 0.75, 0.35);
+use bevy::{app::{AppExit, Plugin, Update}, ecs::{ component::Component, entity::Entity, event::{Event, EventWriter}, hierarchy::ChildSpawnerCommands, query::{Changed, Has, With}, schedule::{common_conditions::{not, resource_changed}, Condition, IntoScheduleConfigs}, system::{Commands, Local, Query, Res, ResMut, SystemParam}}, input::{gamepad::{Gamepad, GamepadButton}, keyboard::KeyCode, ButtonInput}, prelude::{children, SpawnRelated}, state::{app::AppExtStates, condition::in_state, state::{NextState, OnEnter, OnExit}}, text::TextColor, transform::components::GlobalTransform, ui::{widget::{Button, Text}, AlignItems, BackgroundColor, FlexDirection, Interaction, JustifyContent, Node, UiRect, Val}, utils::default};
+use crate::{game::{difficulty::DifficultyKind, game::{save_total_game_stats, AppState, MenuState, TotalGameStats}, score::{high_score_name_open, keep_unsaved_high_score, HighScoreNameText, HighScores, LastRun}}, network::lobby::{LobbyAddressText, LobbyStatusText}, settings::{locale::Localization, settings::{Settings, VolumeChannel}, theme::Theme}};
 
 pub struct MenuPlugin;
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut bevy::app::App) {
 
         app.init_state::<MenuState>();
+        app.add_event::<MenuButtonPressed>();
         
         app.add_systems(
             OnEnter(AppState::MainMenu), 
@@ -21,6 +16,16 @@ impl Plugin for MenuPlugin {
             OnEnter(MenuState::Main), 
             setup_main_menu
         )
+        .add_systems(
+            Update, 
+            refresh_main_menu
+                .run_if(in_state(MenuState::Main))
+                .run_if(
+                    resource_changed::<Settings>
+                        .or(resource_changed::<Theme>)
+                        .or(resource_changed::<Localization>)
+                )
+        )
         .add_systems(
             OnExit(MenuState::Main), 
             despawn_screen::<MainMenuScreen>,
@@ -33,13 +38,64 @@ impl Plugin for MenuPlugin {
             OnExit(MenuState::Stats), 
             despawn_screen::<StatsMenuScreen>,
         )
+        .add_systems(
+            OnEnter(MenuState::Settings), 
+            setup_settings_menu
+        )
+        .add_systems(
+            Update, 
+            refresh_settings_menu
+                .run_if(in_state(MenuState::Settings))
+                .run_if(
+                    resource_changed::<Settings>
+                        .or(resource_changed::<Theme>)
+                        .or(resource_changed::<Localization>)
+                )
+        )
+        .add_systems(
+            OnExit(MenuState::Settings), 
+            despawn_screen::<SettingsMenuScreen>,
+        )
+        .add_systems(
+            OnEnter(MenuState::Lobby), 
+            setup_lobby_menu
+        )
+        .add_systems(
+            OnExit(MenuState::Lobby), 
+            despawn_screen::<LobbyMenuScreen>,
+        )
+        .add_systems(
+            OnEnter(MenuState::HighScores), 
+            setup_high_scores_menu
+        )
+        .add_systems(
+            Update, 
+            refresh_high_scores_menu
+                .run_if(in_state(MenuState::HighScores))
+                .run_if(
+                    resource_changed::<HighScores>
+                        .or(resource_changed::<Theme>)
+                        .or(resource_changed::<Localization>)
+                )
+        )
+        .add_systems(
+            OnExit(MenuState::HighScores), 
+            (keep_unsaved_high_score, despawn_screen::<HighScoresMenuScreen>),
+        )
         .add_systems(
             OnEnter(MenuState::Quit), 
             (save_total_game_stats, exit_game).chain()
         )
         .add_systems(
             Update, 
-            (menu_action, button_system)
+            (
+                // typing a name would move the focus around and press the focused button
+                menu_navigation.run_if(not(high_score_name_open)),
+                menu_confirm.run_if(not(high_score_name_open)),
+                menu_action,
+                button_system,
+            )
+                .chain()
                 .run_if(in_state(AppState::MainMenu))
         );
     }
@@ -52,25 +108,138 @@ pub struct MainMenuScreen;
 pub struct StatsMenuScreen;
 
 #[derive(Component)]
+pub struct SettingsMenuScreen;
+
+#[derive(Component)]
+pub struct LobbyMenuScreen;
+
+#[derive(Component)]
+pub struct HighScoresMenuScreen;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum MenuButtonAction {
     Play,
+    CycleGameMode,
+    CycleDifficulty,
+    Multiplayer,
+    HostGame,
+    JoinGame,
     Stats,
+    HighScores,
+    SaveHighScore,
+    Settings,
     Quit,
     BackToMainMenu,
+    CycleTheme,
+    CycleLanguage,
+    CycleRustaceans,
+    CycleWinCondition,
+    VolumeDown(VolumeChannel),
+    VolumeUp(VolumeChannel),
+    ToggleMute,
+}
+
+/// Sent whenever a menu button is activated, by mouse, keyboard or gamepad
+#[derive(Event, Clone, Copy, Debug)]
+pub struct MenuButtonPressed(pub MenuButtonAction);
+
+// A line of the settings screen, either a single wide button or a value with -/+ buttons
+enum SettingsRow {
+    Button(MenuButtonAction, String),
+    Stepper(String, MenuButtonAction, MenuButtonAction),
+}
+
+// Everything a menu button is allowed to change
+#[derive(SystemParam)]
+struct MenuActionContext<'w> {
+    menu_state: ResMut<'w, NextState<MenuState>>,
+    app_state: ResMut<'w, NextState<AppState>>,
+    settings: ResMut<'w, Settings>,
+    pressed_events: EventWriter<'w, MenuButtonPressed>,
+}
+
+impl MenuActionContext<'_> {
+    fn apply(&mut self, menu_button_action: MenuButtonAction) {
+        self.pressed_events.write(MenuButtonPressed(menu_button_action));
+
+        match menu_button_action {
+            MenuButtonAction::Play => {
+                self.app_state.set(AppState::InGame);
+                self.menu_state.set(MenuState::Disabled);
+            }
+            MenuButtonAction::CycleGameMode => self.settings.game_mode = self.settings.game_mode.next(),
+            MenuButtonAction::CycleDifficulty => self.settings.difficulty = self.settings.difficulty.next(),
+            MenuButtonAction::Multiplayer => self.menu_state.set(MenuState::Lobby),
+            // the network lobby opens the connection when it sees the button press
+            MenuButtonAction::HostGame | MenuButtonAction::JoinGame => {}
+            MenuButtonAction::Stats => self.menu_state.set(MenuState::Stats),
+            MenuButtonAction::HighScores => self.menu_state.set(MenuState::HighScores),
+            // the typed in name is saved when the score module sees the button press
+            MenuButtonAction::SaveHighScore => {}
+            MenuButtonAction::Settings => self.menu_state.set(MenuState::Settings),
+            MenuButtonAction::Quit => self.menu_state.set(MenuState::Quit),
+            MenuButtonAction::BackToMainMenu => self.menu_state.set(MenuState::Main),
+            MenuButtonAction::CycleTheme => self.settings.theme = self.settings.theme.next(),
+            MenuButtonAction::CycleLanguage => self.settings.language = self.settings.language.next(),
+            MenuButtonAction::CycleRustaceans => self.settings.rustaceans = self.settings.next_rustaceans(),
+            MenuButtonAction::CycleWinCondition => self.settings.win_condition = self.settings.win_condition.next(),
+            MenuButtonAction::VolumeDown(channel) => self.settings.adjust_volume(channel, -0.1),
+            MenuButtonAction::VolumeUp(channel) => self.settings.adjust_volume(channel, 0.1),
+            MenuButtonAction::ToggleMute => self.settings.muted = !self.settings.muted,
+        }
+    }
 }
 
 // Tag component used to mark which setting is currently selected
 #[derive(Component)]
 struct SelectedOption;
 
+// Tag component used to mark the button selected by keyboard or gamepad navigation
+#[derive(Component)]
+struct FocusedButton;
+
 fn menu_setup(
     mut menu_state: ResMut<NextState<MenuState>>,
+    last_run: Option<Res<LastRun>>,
 ) {
-    menu_state.set(MenuState::Main);
+    // a scored run ends on the high score table
+    match last_run {
+        Some(_) => menu_state.set(MenuState::HighScores),
+        None => menu_state.set(MenuState::Main),
+    }
 }
 
 fn setup_main_menu(
     mut commands: Commands,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    localization: Res<Localization>,
+) {
+    spawn_main_menu(&mut commands, &settings, &theme, &localization, None);
+}
+
+// Rebuilds the main menu so a newly picked difficulty shows up immediately
+fn refresh_main_menu(
+    mut commands: Commands,
+    screen_query: Query<Entity, With<MainMenuScreen>>,
+    focused_query: Query<&MenuButtonAction, With<FocusedButton>>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    localization: Res<Localization>,
+) {
+    let focused = focused_query.iter().next().copied();
+    for entity in &screen_query {
+        commands.entity(entity).despawn();
+    }
+    spawn_main_menu(&mut commands, &settings, &theme, &localization, focused);
+}
+
+fn spawn_main_menu(
+    commands: &mut Commands,
+    settings: &Settings,
+    theme: &Theme,
+    localization: &Localization,
+    focused: Option<MenuButtonAction>,
 ) {
     let button_node = Node {
             width: Val::Px(300.0),
@@ -80,10 +249,24 @@ fn setup_main_menu(
             align_items: AlignItems::Center,
             ..default()
     };
-    let button_text_font = TextFont {
-        font_size: 33.0,
-        ..default()
-    };
+    let button_text_font = localization.text_font(33.0);
+
+    let buttons = [
+        (MenuButtonAction::Play, localization.text("menu.new_game")),
+        (
+            MenuButtonAction::CycleGameMode,
+            localization.format("menu.game_mode", &[("value", &localization.text(settings.game_mode.key()))]),
+        ),
+        (
+            MenuButtonAction::CycleDifficulty,
+            localization.format("menu.difficulty", &[("value", &localization.text(settings.difficulty.key()))]),
+        ),
+        (MenuButtonAction::Multiplayer, localization.text("menu.multiplayer")),
+        (MenuButtonAction::Stats, localization.text("menu.stats")),
+        (MenuButtonAction::HighScores, localization.text("menu.high_scores")),
+        (MenuButtonAction::Settings, localization.text("menu.settings")),
+        (MenuButtonAction::Quit, localization.text("menu.quit")),
+    ];
 
     commands.spawn((
         Node {
@@ -95,82 +278,67 @@ fn setup_main_menu(
 
         },
         MainMenuScreen,
-        children![(
+    )).with_children(|parent| {
+        parent.spawn((
             Node{
                 flex_direction: FlexDirection::Column,
                 align_items: AlignItems::Center,
                 ..default()
             },
-            BackgroundColor(ORANGE.into()),
-            children![
-                (
-                    Text::new("Trap the Rustlerite"),
-                    TextFont {
-                        font_size: 67.0,
-                        ..default()
-                    },
-                    TextColor(TEXT_COLOR),
-                    Node {
-                        margin: UiRect::all(Val::Px(50.0)),
-                        ..default()
-                    },
+            BackgroundColor(theme.menu_background),
+        )).with_children(|panel| {
+            panel.spawn((
+                Text::new(localization.text("menu.title")),
+                localization.text_font(67.0),
+                TextColor(theme.menu_text),
+                Node {
+                    margin: UiRect::all(Val::Px(50.0)),
+                    ..default()
+                },
+            ));
 
-                ),
-                (
-                    Button,
-                    button_node.clone(),
-                    BackgroundColor(NORMAL_BUTTON),
-                    MenuButtonAction::Play,
-                    children![
-                        (
-                            Text::new("New Game"),
-                            button_text_font.clone(),
-                            TextColor(TEXT_COLOR),
-                        ),
-                    ]
-                ),
-                (
+            for (action, label) in buttons {
+                let mut button = panel.spawn((
                     Button,
                     button_node.clone(),
-                    BackgroundColor(NORMAL_BUTTON),
-                    MenuButtonAction::Stats,
+                    BackgroundColor(theme.button_normal),
+                    action,
                     children![
                         (
-                            Text::new("Stats"),
+                            Text::new(label),
                             button_text_font.clone(),
-                            TextColor(TEXT_COLOR),
+                            TextColor(theme.menu_text),
                         ),
                     ]
-                ),
-                (
-                    Button,
-                    button_node,
-                    BackgroundColor(NORMAL_BUTTON),
-                    MenuButtonAction::Quit,
-                    children![
-                        (
-                            Text::new("Quit"),
-                            button_text_font.clone(),
-                            TextColor(TEXT_COLOR),
-                        ),
-                    ]
-                ),
-            ]
-        )],
-    ));
+                ));
+                if focused == Some(action) {
+                    button.insert(FocusedButton);
+                }
+            }
+        });
+    });
 }
 
 fn setup_stats_menu(
     mut commands: Commands,
-    game_statistics: Res<TotalGameStats>
+    game_statistics: Res<TotalGameStats>,
+    theme: Res<Theme>,
+    localization: Res<Localization>,
 ) {
 
-    let button_text_font = (
-        TextFont {
-            font_size: 33.0,
-            ..default()
-        },
-    );
+    let button_text_font = localization.text_font(33.0);
+
+    // one line for every difficulty that has been played
+    let difficulty_summary = DifficultyKind::ALL.iter()
+        .filter_map(|kind| game_statistics.per_difficulty.get(kind).map(|stats| (kind, stats)))
+        .map(|(kind, stats)| localization.format("stats.difficulty_summary", &[
+            ("difficulty", &localization.text(kind.key())),
+            ("level", &localization.number(stats.record_level)),
+            ("trapped", &localization.number(stats.tigers_trapped)),
+            ("escaped", &localization.number(stats.tigers_escaped)),
+        ]))
+        .collect::<Vec<_>>()
+        .join("\n");
 
     commands.spawn((
         Node {
@@ -187,80 +355,100 @@ fn setup_stats_menu(
                 align_items: AlignItems::Center,
                 ..default()
             },
-            BackgroundColor(ORANGE.into()),
+            BackgroundColor(theme.menu_background),
             children![
                 (
-                    Text::new("Total Statistics:"),
-                    TextFont {
-                        font_size: 45.0,
-                        ..default()
-                    },
-                    TextColor(TEXT_COLOR),
+                    Text::new(localization.text("stats.title")),
+                    localization.text_font(45.0),
+                    TextColor(theme.menu_text),
                     Node {
                         margin: UiRect::all(Val::Px(50.0)),
                         ..default()
                     }
                 ),
                 (
-                    Text::new(format!("Record level: {}", game_statistics.record_level)),
-                    TextFont {
-                        font_size: 35.0,
-                        ..default()
-                    },
-                    TextColor(TEXT_COLOR),
+                    Text::new(localization.format("stats.record_level", &[("n", &localization.number(game_statistics.record_level))])),
+                    localization.text_font(35.0),
+                    TextColor(theme.menu_text),
                     Node {
                         margin: UiRect::all(Val::Px(30.0)),
                         ..default()
                     }
                 ),
                 (
-                    Text::new(format!("Rustaceans trapped: {}", game_statistics.tigers_trapped)),
-                    TextFont {
-                        font_size: 35.0,
-                        ..default()
-                    },
-                    TextColor(TEXT_COLOR),
+                    Text::new(localization.plural("stats.rustaceans_trapped", game_statistics.tigers_trapped)),
+                    localization.text_font(35.0),
+                    TextColor(theme.menu_text),
                     Node {
                         margin: UiRect::all(Val::Px(30.0)),
                         ..default()
                     }
                 ),
                 (
-                    Text::new(format!("Rustaceans escaped: {}", game_statistics.tigers_escaped)),
-                    TextFont {
-                        font_size: 35.0,
+                    Text::new(localization.plural("stats.rustaceans_escaped", game_statistics.tigers_escaped)),
+                    localization.text_font(35.0),
+                    TextColor(theme.menu_text),
+                    Node {
+                        margin: UiRect::all(Val::Px(30.0)),
                         ..default()
-                    },
-                    TextColor(TEXT_COLOR),
+                    }
+                ),
+                (
+                    Text::new(localization.plural("stats.tiles_tapped", game_statistics.tiles_tapped)),
+                    localization.text_font(35.0),
+                    TextColor(theme.menu_text),
                     Node {
                         margin: UiRect::all(Val::Px(30.0)),
                         ..default()
                     }
                 ),
                 (
-                    Text::new(format!("Tiles tapped: {}", game_statistics.tiles_tapped)),
-                    TextFont {
-                        font_size: 35.0,
+                    Text::new(localization.plural("stats.games_played", game_statistics.games_played)),
+                    localization.text_font(35.0),
+                    TextColor(theme.menu_text),
+                    Node {
+                        margin: UiRect::all(Val::Px(30.0)),
                         ..default()
-                    },
-                    TextColor(TEXT_COLOR),
+                    }
+                ),
+                (
+                    Text::new(localization.format("stats.time_attack_best", &[("n", &localization.number(game_statistics.time_attack_best))])),
+                    localization.text_font(35.0),
+                    TextColor(theme.menu_text),
                     Node {
                         margin: UiRect::all(Val::Px(30.0)),
                         ..default()
                     }
                 ),
                 (
-                    Text::new(format!("Games played: {}", game_statistics.games_played)),
-                    TextFont {
-                        font_size: 35.0,
+                    Text::new(localization.format("stats.puzzle_stars", &[
+                        ("n", &localization.number(game_statistics.puzzle_stars.values().map(|stars| *stars as u64).sum())),
+                    ])),
+                    localization.text_font(35.0),
+                    TextColor(theme.menu_text),
+                    Node {
+                        margin: UiRect::all(Val::Px(30.0)),
                         ..default()
-                    },
-                    TextColor(TEXT_COLOR),
+                    }
+                ),
+                (
+                    Text::new(localization.format("stats.level_stars", &[("n", &localization.number(game_statistics.level_stars))])),
+                    localization.text_font(35.0),
+                    TextColor(theme.menu_text),
                     Node {
                         margin: UiRect::all(Val::Px(30.0)),
                         ..default()
                     }
                 ),
+                (
+                    Text::new(difficulty_summary),
+                    localization.text_font(28.0),
+                    TextColor(theme.menu_text),
+                    Node {
+                        margin: UiRect::all(Val::Px(20.0)),
+                        ..default()
+                    }
+                ),
                 (
                     Button,
                     Node {
@@ -271,13 +459,13 @@ fn setup_stats_menu(
                         align_items: AlignItems::Center,
                         ..default()
                     },
-                    BackgroundColor(NORMAL_BUTTON),
+                    BackgroundColor(theme.button_normal),
                     MenuButtonAction::BackToMainMenu,
                     children![
                         (
-                            Text::new("Return to Main Menu"),
+                            Text::new(localization.text("menu.back")),
                             button_text_font.clone(),
-                            TextColor(TEXT_COLOR),
+                            TextColor(theme.menu_text),
                         ),
                     ]
                 ),
@@ -286,6 +474,414 @@ fn setup_stats_menu(
     ));
 }
 
+fn setup_settings_menu(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    localization: Res<Localization>,
+) {
+    spawn_settings_menu(&mut commands, &settings, &theme, &localization, None);
+}
+
+// Rebuilds the settings screen so changed values and a newly picked theme show up immediately
+fn refresh_settings_menu(
+    mut commands: Commands,
+    screen_query: Query<Entity, With<SettingsMenuScreen>>,
+    focused_query: Query<&MenuButtonAction, With<FocusedButton>>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    localization: Res<Localization>,
+) {
+    let focused = focused_query.iter().next().copied();
+    for entity in &screen_query {
+        commands.entity(entity).despawn();
+    }
+    spawn_settings_menu(&mut commands, &settings, &theme, &localization, focused);
+}
+
+fn spawn_settings_menu(
+    commands: &mut Commands,
+    settings: &Settings,
+    theme: &Theme,
+    localization: &Localization,
+    focused: Option<MenuButtonAction>,
+) {
+    let button_node = Node {
+        width: Val::Px(500.0),
+        height: Val::Px(65.0),
+        margin: UiRect::all(Val::Px(20.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_font = localization.text_font(33.0);
+
+    let small_button_node = Node {
+        width: Val::Px(65.0),
+        height: Val::Px(65.0),
+        margin: UiRect::all(Val::Px(20.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+
+    let mut rows = vec![
+        SettingsRow::Button(
+            MenuButtonAction::CycleTheme,
+            localization.format("settings.theme", &[("value", &localization.text(settings.theme.key()))]),
+        ),
+        SettingsRow::Button(
+            MenuButtonAction::CycleLanguage,
+            localization.format("settings.language", &[("value", &localization.text("language.name"))]),
+        ),
+        SettingsRow::Button(
+            MenuButtonAction::CycleRustaceans,
+            localization.format("settings.rustaceans", &[("value", &localization.number(settings.rustaceans as u64))]),
+        ),
+        SettingsRow::Button(
+            MenuButtonAction::CycleWinCondition,
+            localization.format("settings.win_condition", &[("value", &localization.text(settings.win_condition.key()))]),
+        ),
+    ];
+    for channel in [VolumeChannel::Master, VolumeChannel::Music, VolumeChannel::Effects] {
+        let percent = format!("{:.0}", settings.volume(channel) * 100.0);
+        rows.push(SettingsRow::Stepper(
+            localization.format(channel.key(), &[("value", &percent)]),
+            MenuButtonAction::VolumeDown(channel),
+            MenuButtonAction::VolumeUp(channel),
+        ));
+    }
+    let sound_state = if settings.muted { "settings.sound.muted" } else { "settings.sound.on" };
+    rows.push(SettingsRow::Button(
+        MenuButtonAction::ToggleMute,
+        localization.format("settings.sound", &[("value", &localization.text(sound_state))]),
+    ));
+    rows.push(SettingsRow::Button(MenuButtonAction::BackToMainMenu, localization.text("menu.back")));
+
+    let spawn_button = |parent: &mut ChildSpawnerCommands, node: &Node, action: MenuButtonAction, label: String| {
+        let mut button = parent.spawn((
+            Button,
+            node.clone(),
+            BackgroundColor(theme.button_normal),
+            action,
+            children![
+                (
+                    Text::new(label),
+                    button_text_font.clone(),
+                    TextColor(theme.menu_text),
+                ),
+            ]
+        ));
+        if focused == Some(action) {
+            button.insert(FocusedButton);
+        }
+    };
+
+    commands.spawn((
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        SettingsMenuScreen,
+    )).with_children(|parent| {
+        parent.spawn((
+            Node {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(theme.menu_background),
+        )).with_children(|panel| {
+            panel.spawn((
+                Text::new(localization.text("settings.title")),
+                localization.text_font(45.0),
+                TextColor(theme.menu_text),
+                Node {
+                    margin: UiRect::all(Val::Px(50.0)),
+                    ..default()
+                }
+            ));
+
+            for row in rows {
+                match row {
+                    SettingsRow::Button(action, label) => spawn_button(panel, &button_node, action, label),
+                    SettingsRow::Stepper(label, down, up) => {
+                        panel.spawn(Node {
+                            flex_direction: FlexDirection::Row,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        }).with_children(|row| {
+                            spawn_button(row, &small_button_node, down, String::from("-"));
+                            row.spawn((
+                                Text::new(label),
+                                button_text_font.clone(),
+                                TextColor(theme.menu_text),
+                                Node {
+                                    width: Val::Px(300.0),
+                                    justify_content: JustifyContent::Center,
+                                    ..default()
+                                },
+                            ));
+                            spawn_button(row, &small_button_node, up, String::from("+"));
+                        });
+                    }
+                }
+            }
+        });
+    });
+}
+
+fn setup_lobby_menu(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    localization: Res<Localization>,
+) {
+    let button_node = Node {
+        width: Val::Px(400.0),
+        height: Val::Px(65.0),
+        margin: UiRect::all(Val::Px(20.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_font = localization.text_font(33.0);
+
+    let buttons = [
+        (MenuButtonAction::HostGame, localization.text("lobby.host")),
+        (MenuButtonAction::JoinGame, localization.text("lobby.join")),
+        (MenuButtonAction::BackToMainMenu, localization.text("menu.back")),
+    ];
+
+    commands.spawn((
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        LobbyMenuScreen,
+    )).with_children(|parent| {
+        parent.spawn((
+            Node {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(theme.menu_background),
+        )).with_children(|panel| {
+            panel.spawn((
+                Text::new(localization.text("lobby.title")),
+                localization.text_font(45.0),
+                TextColor(theme.menu_text),
+                Node {
+                    margin: UiRect::all(Val::Px(50.0)),
+                    ..default()
+                }
+            ));
+            // both texts are filled in by the network lobby
+            panel.spawn((
+                Text::default(),
+                localization.text_font(35.0),
+                TextColor(theme.menu_text),
+                Node {
+                    margin: UiRect::all(Val::Px(20.0)),
+                    ..default()
+                },
+                LobbyAddressText,
+            ));
+            panel.spawn((
+                Text::default(),
+                localization.text_font(28.0),
+                TextColor(theme.menu_text),
+                Node {
+                    margin: UiRect::all(Val::Px(20.0)),
+                    ..default()
+                },
+                LobbyStatusText,
+            ));
+
+            for (action, label) in buttons {
+                panel.spawn((
+                    Button,
+                    button_node.clone(),
+                    BackgroundColor(theme.button_normal),
+                    action,
+                    children![
+                        (
+                            Text::new(label),
+                            button_text_font.clone(),
+                            TextColor(theme.menu_text),
+                        ),
+                    ]
+                ));
+            }
+        });
+    });
+}
+
+fn setup_high_scores_menu(
+    mut commands: Commands,
+    high_scores: Res<HighScores>,
+    last_run: Option<Res<LastRun>>,
+    theme: Res<Theme>,
+    localization: Res<Localization>,
+) {
+    spawn_high_scores_menu(&mut commands, &high_scores, last_run.as_deref(), &theme, &localization);
+}
+
+// Rebuilds the high score screen so a newly saved score shows up in the table
+fn refresh_high_scores_menu(
+    mut commands: Commands,
+    screen_query: Query<Entity, With<HighScoresMenuScreen>>,
+    high_scores: Res<HighScores>,
+    last_run: Option<Res<LastRun>>,
+    theme: Res<Theme>,
+    localization: Res<Localization>,
+) {
+    for entity in &screen_query {
+        commands.entity(entity).despawn();
+    }
+    spawn_high_scores_menu(&mut commands, &high_scores, last_run.as_deref(), &theme, &localization);
+}
+
+fn spawn_high_scores_menu(
+    commands: &mut Commands,
+    high_scores: &HighScores,
+    last_run: Option<&LastRun>,
+    theme: &Theme,
+    localization: &Localization,
+) {
+    let button_node = Node {
+        width: Val::Px(400.0),
+        height: Val::Px(65.0),
+        margin: UiRect::all(Val::Px(20.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_font = localization.text_font(33.0);
+
+    let mut buttons = Vec::new();
+    if last_run.is_some_and(|last_run| last_run.entering_name()) {
+        buttons.push((MenuButtonAction::SaveHighScore, localization.text("high_scores.save")));
+    }
+    buttons.push((MenuButtonAction::BackToMainMenu, localization.text("menu.back")));
+
+    commands.spawn((
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        HighScoresMenuScreen,
+    )).with_children(|parent| {
+        parent.spawn((
+            Node {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(theme.menu_background),
+        )).with_children(|panel| {
+            panel.spawn((
+                Text::new(localization.text("high_scores.title")),
+                localization.text_font(45.0),
+                TextColor(theme.menu_text),
+                Node {
+                    margin: UiRect::all(Val::Px(40.0)),
+                    ..default()
+                }
+            ));
+
+            if let Some(last_run) = last_run {
+                panel.spawn((
+                    Text::new(localization.format("high_scores.your_score", &[
+                        ("score", &localization.number(last_run.score)),
+                        ("level", &localization.number(last_run.level as u64)),
+                    ])),
+                    localization.text_font(35.0),
+                    TextColor(theme.menu_text),
+                    Node {
+                        margin: UiRect::all(Val::Px(20.0)),
+                        ..default()
+                    }
+                ));
+            }
+            if let Some(last_run) = last_run.filter(|last_run| last_run.entering_name()) {
+                panel.spawn((
+                    Text::new(localization.text("high_scores.new_record")),
+                    localization.text_font(28.0),
+                    TextColor(theme.menu_text),
+                ));
+                // filled in while the name is typed
+                panel.spawn((
+                    Text::new(localization.format("high_scores.name", &[("name", &last_run.name)])),
+                    localization.text_font(35.0),
+                    TextColor(theme.menu_text),
+                    Node {
+                        margin: UiRect::all(Val::Px(20.0)),
+                        ..default()
+                    },
+                    HighScoreNameText,
+                ));
+            }
+
+            if high_scores.entries.is_empty() {
+                panel.spawn((
+                    Text::new(localization.text("high_scores.empty")),
+                    localization.text_font(28.0),
+                    TextColor(theme.menu_text),
+                    Node {
+                        margin: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    }
+                ));
+            }
+            for (rank, entry) in high_scores.entries.iter().enumerate() {
+                // the run that was just saved stands out
+                let saved = last_run.is_some_and(|last_run| last_run.saved == Some(rank));
+                panel.spawn((
+                    Text::new(localization.format("high_scores.row", &[
+                        ("rank", &localization.number(rank as u64 + 1)),
+                        ("name", &entry.name),
+                        ("score", &localization.number(entry.score)),
+                        ("level", &localization.number(entry.level as u64)),
+                        ("date", &entry.date),
+                    ])),
+                    localization.text_font(28.0),
+                    TextColor(if saved { theme.win } else { theme.menu_text }),
+                    Node {
+                        margin: UiRect::all(Val::Px(6.0)),
+                        ..default()
+                    }
+                ));
+            }
+
+            for (action, label) in buttons {
+                panel.spawn((
+                    Button,
+                    button_node.clone(),
+                    BackgroundColor(theme.button_normal),
+                    action,
+                    children![
+                        (
+                            Text::new(label),
+                            button_text_font.clone(),
+                            TextColor(theme.menu_text),
+                        ),
+                    ]
+                ));
+            }
+        });
+    });
+}
+
 /* 
 pub fn main_menu_loop(
     keys: Res<ButtonInput<KeyCode>>,
@@ -306,19 +902,109 @@ fn exit_game(
     app_exit_events.write(AppExit::Success);
 }
 
-// This system handles changing all buttons color based on mouse interaction
+// This system handles changing all buttons color based on mouse interaction and navigation focus
 fn button_system(
     mut interaction_query: Query<
-        (&Interaction, &mut BackgroundColor, Option<&SelectedOption>),
-        (Changed<Interaction>, With<Button>),
+        (&Interaction, &mut BackgroundColor, Option<&SelectedOption>, Has<FocusedButton>),
+        With<Button>,
     >,
+    theme: Res<Theme>,
+) {
+    for (interaction, mut background_color, selected, focused) in &mut interaction_query {
+        // a focused button looks the same as a hovered one
+        let interaction = match *interaction {
+            Interaction::None if focused => Interaction::Hovered,
+            interaction => interaction,
+        };
+        let new_color: BackgroundColor = match (interaction, selected) {
+            (Interaction::Pressed, _) | (Interaction::None, Some(_)) => theme.button_pressed.into(),
+            (Interaction::Hovered, Some(_)) => theme.button_hovered_pressed.into(),
+            (Interaction::Hovered, None) => theme.button_hovered.into(),
+            (Interaction::None, None) => theme.button_normal.into(),
+        };
+        if *background_color != new_color {
+            *background_color = new_color;
+        }
+    }
+}
+
+// Moves the navigation focus between the visible buttons with arrows/WS and the gamepad d-pad or stick
+fn menu_navigation(
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mut stick_latched: Local<bool>,
+    button_query: Query<(Entity, &GlobalTransform, Has<FocusedButton>), (With<Button>, With<MenuButtonAction>)>,
+    mut commands: Commands,
+) {
+    let mut step = 0;
+    if keys.any_just_pressed([KeyCode::ArrowUp, KeyCode::KeyW]) {
+        step -= 1;
+    }
+    if keys.any_just_pressed([KeyCode::ArrowDown, KeyCode::KeyS, KeyCode::Tab]) {
+        step += 1;
+    }
+    for gamepad in gamepads.iter() {
+        if gamepad.just_pressed(GamepadButton::DPadUp) {
+            step -= 1;
+        }
+        if gamepad.just_pressed(GamepadButton::DPadDown) {
+            step += 1;
+        }
+
+        // one step per push of the stick
+        let stick_y = gamepad.left_stick().y;
+        if !*stick_latched && stick_y.abs() >= 0.6 {
+            *stick_latched = true;
+            step += if stick_y > 0. { -1 } else { 1 };
+        } else if *stick_latched && stick_y.abs() <= 0.3 {
+            *stick_latched = false;
+        }
+    }
+
+    if step == 0 {
+        return;
+    }
+
+    // order the buttons top to bottom as they appear on screen
+    let mut buttons: Vec<_> = button_query.iter().collect();
+    if buttons.is_empty() {
+        return;
+    }
+    buttons.sort_by(|a, b| {
+        let (a, b) = (a.1.translation(), b.1.translation());
+        a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x))
+    });
+
+    let count = buttons.len() as i32;
+    let next_index = match buttons.iter().position(|(_, _, focused)| *focused) {
+        Some(index) => {
+            commands.entity(buttons[index].0).remove::<FocusedButton>();
+            (index as i32 + step).rem_euclid(count)
+        }
+        // nothing focused yet: start from the top, or the bottom when going up
+        None => if step > 0 { 0 } else { count - 1 },
+    };
+
+    commands.entity(buttons[next_index as usize].0).insert(FocusedButton);
+}
+
+// Activates the focused button with Enter/Space or gamepad south, and goes back with Escape or gamepad east
+fn menu_confirm(
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    button_query: Query<(&MenuButtonAction, Has<FocusedButton>), With<Button>>,
+    mut context: MenuActionContext,
 ) {
-    for (interaction, mut background_color, selected) in &mut interaction_query {
-        *background_color = match (*interaction, selected) {
-            (Interaction::Pressed, _) | (Interaction::None, Some(_)) => PRESSED_BUTTON.into(),
-            (Interaction::Hovered, Some(_)) => HOVERED_PRESSED_BUTTON.into(),
-            (Interaction::Hovered, None) => HOVERED_BUTTON.into(),
-            (Interaction::None, None) => NORMAL_BUTTON.into(),
+    let confirmed = keys.any_just_pressed([KeyCode::Enter, KeyCode::Space])
+        || gamepads.iter().any(|gamepad| gamepad.just_pressed(GamepadButton::South));
+    let cancelled = keys.just_pressed(KeyCode::Escape)
+        || gamepads.iter().any(|gamepad| gamepad.just_pressed(GamepadButton::East));
+
+    for (menu_button_action, focused) in &button_query {
+        if (confirmed && focused)
+            || (cancelled && matches!(menu_button_action, MenuButtonAction::BackToMainMenu)) {
+            context.apply(*menu_button_action);
+            return;
         }
     }
 }
@@ -329,25 +1015,18 @@ fn menu_action(
         (&Interaction, &MenuButtonAction),
         (Changed<Interaction>, With<Button>),
     >,
-    mut menu_state: ResMut<NextState<MenuState>>,
-    mut app_state: ResMut<NextState<AppState>>,
+    mut context: MenuActionContext,
 ) {
     for (interaction, menu_button_action) in &interaction_query {
         if *interaction == Interaction::Pressed {
-            match menu_button_action {
-                MenuButtonAction::Play => {
-                    app_state.set(AppState::InGame);
-                    menu_state.set(MenuState::Disabled);
-                }
-                MenuButtonAction::Stats => menu_state.set(MenuState::Stats),
-                MenuButtonAction::Quit => menu_state.set(MenuState::Quit),
-                MenuButtonAction::BackToMainMenu => menu_state.set(MenuState::Main),
-            }
+            context.apply(*menu_button_action);
         }
     }
 }
 
 
+
+
 // Generic system that takes a component as a parameter, and will despawn all entities with that component
 fn despawn_screen<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands: Commands) {
     for entity in &to_despawn {
@@ -0,0 +1,133 @@
+use bevy::{ecs::{component::Component, event::EventReader, query::{With, Without}, resource::Resource, system::{Commands, Query, Res, ResMut}}, input::{keyboard::{Key, KeyboardInput}, ButtonState}, log::warn, state::state::NextState, ui::widget::Text};
+use crate::{game::game::{AppState, MenuState}, menu::menu::{MenuButtonAction, MenuButtonPressed}, network::{online::HostSnapshot, session::NetSession}, settings::{locale::Localization, settings::Settings}};
+
+/// Longest address that can be typed into the lobby
+const MAX_ADDRESS_LEN: usize = 64;
+
+/// Why the last attempt to host failed, shown until the next attempt
+#[derive(Resource, Default)]
+pub struct LobbyNotice(pub Option<String>);
+
+/// Text of the lobby showing the address to host on or join
+#[derive(Component)]
+pub struct LobbyAddressText;
+
+/// Text of the lobby showing what the connection is doing
+#[derive(Component)]
+pub struct LobbyStatusText;
+
+/// Opens a session when the host or join button is pressed
+pub fn start_lobby_session(
+    mut commands: Commands,
+    mut pressed_events: EventReader<MenuButtonPressed>,
+    session: Option<Res<NetSession>>,
+    settings: Res<Settings>,
+    mut notice: ResMut<LobbyNotice>,
+) {
+    for MenuButtonPressed(action) in pressed_events.read() {
+        match action {
+            MenuButtonAction::HostGame => {
+                // the old listener has to be gone before the port can be bound again
+                if session.as_ref().is_some_and(|session| !session.is_client()) {
+                    continue;
+                }
+                match NetSession::host(&settings.net_address) {
+                    Ok(session) => {
+                        notice.0 = None;
+                        commands.insert_resource(session);
+                    }
+                    Err(err) => {
+                        warn!("Could not host on {}: {err}", settings.net_address);
+                        notice.0 = Some(err.to_string());
+                    }
+                }
+            }
+            MenuButtonAction::JoinGame => {
+                notice.0 = None;
+                commands.insert_resource(NetSession::join(&settings.net_address));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Starts the match once both sides are there, the client also needs the host's first board
+pub fn start_online_match(
+    session: Res<NetSession>,
+    snapshot: Option<Res<HostSnapshot>>,
+    mut app_state: ResMut<NextState<AppState>>,
+    mut menu_state: ResMut<NextState<MenuState>>,
+) {
+    if session.connected && (!session.is_client() || snapshot.is_some()) {
+        app_state.set(AppState::InGame);
+        menu_state.set(MenuState::Disabled);
+    }
+}
+
+/// Lets the address be typed while no session is open, digits, dots and a colon are enough for ip:port
+pub fn edit_lobby_address(
+    mut keyboard_events: EventReader<KeyboardInput>,
+    session: Option<Res<NetSession>>,
+    mut settings: ResMut<Settings>,
+) {
+    if session.is_some() {
+        keyboard_events.clear();
+        return;
+    }
+
+    for event in keyboard_events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Backspace => {
+                settings.net_address.pop();
+            }
+            Key::Character(text) => {
+                for character in text.chars() {
+                    if (character.is_ascii_digit() || character == '.' || character == ':')
+                        && settings.net_address.len() < MAX_ADDRESS_LEN {
+                        settings.net_address.push(character);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn lobby_text_update(
+    session: Option<Res<NetSession>>,
+    snapshot: Option<Res<HostSnapshot>>,
+    notice: Res<LobbyNotice>,
+    settings: Res<Settings>,
+    localization: Res<Localization>,
+    mut address_q: Query<&mut Text, (With<LobbyAddressText>, Without<LobbyStatusText>)>,
+    mut status_q: Query<&mut Text, With<LobbyStatusText>>,
+) {
+    let address = localization.format("lobby.address", &[("address", &settings.net_address)]);
+    for mut text in &mut address_q {
+        if address != text.0 {
+            **text = address.clone()
+        }
+    }
+
+    let mut status = match session.as_deref() {
+        None => localization.text("lobby.idle"),
+        Some(session) if !session.is_client() => localization.format("lobby.hosting", &[("address", &session.address)]),
+        Some(session) if session.refused => localization.format("lobby.refused", &[("address", &session.address)]),
+        Some(session) if session.connected && snapshot.is_none() => localization.text("lobby.connected"),
+        Some(session) => localization.format("lobby.connecting", &[("address", &session.address)]),
+    };
+    let error = notice.0.as_ref().or(session.as_ref().and_then(|session| session.last_error.as_ref()));
+    if let Some(error) = error {
+        status.push('\n');
+        status.push_str(&localization.format("lobby.error", &[("error", error)]));
+    }
+
+    for mut text in &mut status_q {
+        if status != text.0 {
+            **text = status.clone()
+        }
+    }
+}
@@ -0,0 +1,5 @@
+pub mod network;
+pub mod protocol;
+pub mod session;
+pub mod lobby;
+pub mod online;
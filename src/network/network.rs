@@ -0,0 +1,77 @@
+use bevy::{app::{Plugin, Update}, ecs::schedule::{common_conditions::{not, resource_exists}, IntoScheduleConfigs}, state::{condition::in_state, state::{OnEnter, OnExit}}};
+use crate::{game::{endless::prepare_level_rules, game::{ensure_level_grid, AppState, MenuState}, game_mode::{game_mode_is, select_game_mode, GameMode}, levels::{announce_level_start, LevelState}, tile_feedback::animate_trap_placement}, network::{lobby::{edit_lobby_address, lobby_text_update, start_lobby_session, start_online_match, LobbyNotice}, online::{abandon_online_match, apply_host_snapshot, apply_host_turn, apply_rustacean_move_requests, count_host_board, end_net_session, online_text_update, send_chosen_rustacean_moves, send_host_snapshot, spawn_online_text, start_next_host_board, store_host_snapshot, use_host_board, HostBoardId, ShownBoardId}, session::{is_net_client, is_net_host, poll_net_session, NetMessageReceived, NetSession}}};
+
+pub struct NetworkPlugin;
+impl Plugin for NetworkPlugin {
+    fn build(&self, app: &mut bevy::app::App) {
+
+        app.add_event::<NetMessageReceived>();
+        app.init_resource::<LobbyNotice>()
+            .init_resource::<HostBoardId>()
+            .init_resource::<ShownBoardId>();
+
+        app.add_systems(
+            Update,
+            (poll_net_session, store_host_snapshot.run_if(is_net_client))
+                .chain()
+                .run_if(resource_exists::<NetSession>)
+        );
+
+        app.add_systems(
+            Update,
+            (
+                edit_lobby_address,
+                start_lobby_session,
+                start_online_match.run_if(resource_exists::<NetSession>),
+                lobby_text_update,
+            )
+                .chain()
+                .after(store_host_snapshot)
+                .run_if(in_state(MenuState::Lobby))
+        )
+        // the main menu never keeps a connection open
+        .add_systems(OnEnter(MenuState::Main), end_net_session)
+        .add_systems(OnExit(AppState::InGame), end_net_session);
+
+        app.add_systems(
+            OnEnter(LevelState::InLevel),
+            count_host_board
+                .after(announce_level_start)
+                .run_if(is_net_host)
+        )
+        .add_systems(
+            OnEnter(LevelState::InLevel),
+            use_host_board
+                .after(prepare_level_rules)
+                .before(ensure_level_grid)
+                .run_if(is_net_client)
+        );
+
+        app.add_systems(
+            OnEnter(AppState::InGame),
+            spawn_online_text
+                .after(select_game_mode)
+                .run_if(game_mode_is(GameMode::Online))
+        )
+        .add_systems(
+            Update,
+            (
+                (apply_rustacean_move_requests, send_host_snapshot)
+                    .chain()
+                    .run_if(is_net_host),
+                (start_next_host_board, apply_host_snapshot.run_if(in_state(LevelState::InLevel)), apply_host_turn)
+                    .chain()
+                    .before(animate_trap_placement)
+                    .run_if(is_net_client),
+                send_chosen_rustacean_moves
+                    .run_if(in_state(LevelState::InLevel))
+                    .run_if(is_net_client),
+                online_text_update,
+                abandon_online_match.run_if(not(in_state(LevelState::LevelLose))),
+            )
+                .after(store_host_snapshot)
+                .run_if(in_state(AppState::InGame))
+                .run_if(resource_exists::<NetSession>)
+        );
+    }
+}
@@ -0,0 +1,356 @@
+use bevy::{ecs::{component::Component, entity::Entity, event::{EventReader, EventWriter}, query::{Has, With}, resource::Resource, system::{Commands, Local, Query, Res, ResMut}, world::World}, log::debug, sprite::Sprite, state::state::{NextState, State}, text::TextSpan, ui::{Node, PositionType, Val}, utils::default};
+use crate::{game::{difficulty::TrapInventoryCounts, endless::{FixedBoard, LevelRules}, events::{GameOver, TrapPlaced}, game::{AppState, CurrentLevel}, hotseat::RustaceanMoveChosen, level_reset::clear_board, level_setup::{RustaceanPos, TrapTile}, levels::{LevelEndTimer, LevelState, spawn_hud_text, TurnState}, rustaceans::{RustaceanOrder, RustaceanTurnProgress, RustaceanVariant}, pathfinding::RustaceanMover, tile_feedback::TrapPlaceAnim, tiles::{SpecialTile, TileKind}, traps::ReinforcedTrap}, network::{protocol::{BoardSnapshot, NetMessage, NetOutcome, NetTurn}, session::{NetMessageReceived, NetSession}}, settings::{locale::Localization, theme::Theme}, utils::hexgrid_utils::{GridSize, GridTilePos}};
+
+/// Seconds a match waits for the other player to come back before giving up
+pub const RECONNECT_TIMEOUT_SECS: f32 = 30.0;
+
+/// Id of the board the host set up last
+#[derive(Resource, Default)]
+pub struct HostBoardId(pub u32);
+
+/// Latest board the client got from the host
+#[derive(Resource)]
+pub struct HostSnapshot(pub BoardSnapshot);
+
+/// Id of the host board the client is showing, empty before the first one
+#[derive(Resource, Default)]
+pub struct ShownBoardId(pub Option<u32>);
+
+pub fn count_host_board(
+    mut board_id: ResMut<HostBoardId>,
+) {
+    board_id.0 += 1;
+}
+
+/// Sends the board to the client whenever anything on it changed
+pub fn send_host_snapshot(
+    mut session: ResMut<NetSession>,
+    board_id: Res<HostBoardId>,
+    current_level: Res<CurrentLevel>,
+    rules: Res<LevelRules>,
+    level_state: Res<State<LevelState>>,
+    turn_state: Res<State<TurnState>>,
+    grid_q: Query<&GridSize>,
+    rustacean_q: Query<(Entity, &RustaceanOrder, &RustaceanPos, Option<&RustaceanVariant>)>,
+    progress: Res<RustaceanTurnProgress>,
+    trap_q: Query<(&GridTilePos, Has<ReinforcedTrap>), With<TrapTile>>,
+    special_q: Query<(&GridTilePos, &TileKind)>,
+    mut last_sent: Local<Option<BoardSnapshot>>,
+) {
+    if !session.connected {
+        return;
+    }
+
+    let outcome = match level_state.get() {
+        LevelState::InLevel => None,
+        LevelState::LevelWin => Some(NetOutcome::Trapped),
+        LevelState::LevelLose => Some(NetOutcome::Escaped),
+        LevelState::OutOfLevel => return,
+    };
+    // between two boards there is nothing worth sending
+    let Ok(grid_size) = grid_q.single() else {
+        return;
+    };
+    let mut rustaceans: Vec<(Entity, &RustaceanOrder, &RustaceanPos, Option<&RustaceanVariant>)> = rustacean_q.iter().collect();
+    if rustaceans.is_empty() {
+        return;
+    }
+    rustaceans.sort_unstable_by_key(|(_, order, _, _)| **order);
+
+    let mut traps: Vec<(i32, i32)> = trap_q.iter().map(|(pos, _)| (pos.x, pos.y)).collect();
+    traps.sort_unstable();
+    let mut reinforced: Vec<(i32, i32)> = trap_q.iter()
+        .filter(|(_, is_reinforced)| *is_reinforced)
+        .map(|(pos, _)| (pos.x, pos.y))
+        .collect();
+    reinforced.sort_unstable();
+    let mut special_tiles: Vec<SpecialTile> = special_q.iter()
+        .map(|(pos, kind)| SpecialTile { pos: (pos.x, pos.y), kind: *kind })
+        .collect();
+    special_tiles.sort_unstable_by_key(|tile| tile.pos);
+
+    let snapshot = BoardSnapshot {
+        board_id: board_id.0,
+        level: current_level.0,
+        grid_size: *grid_size,
+        rustaceans: rustaceans.iter().map(|(_, _, pos, _)| (pos.x, pos.y)).collect(),
+        moved: rustaceans.iter()
+            .filter(|(entity, _, _, _)| progress.moved.contains(entity))
+            .map(|(_, order, _, _)| order.0)
+            .collect(),
+        variants: rustaceans.iter().map(|(_, _, _, variant)| variant.copied()).collect(),
+        win_condition: rules.win_condition,
+        traps,
+        reinforced,
+        special_tiles,
+        turn: match turn_state.get() {
+            TurnState::PlayerTurn => NetTurn::Trapper,
+            TurnState::RustaceanTurn => NetTurn::Rustacean,
+        },
+        outcome,
+    };
+
+    if session.resync || last_sent.as_ref() != Some(&snapshot) {
+        session.send(&NetMessage::Snapshot { board: snapshot.clone() });
+        session.resync = false;
+        *last_sent = Some(snapshot);
+    }
+}
+
+/// Checks the client's moves against the board and carries out the legal ones
+pub fn apply_rustacean_move_requests(
+    mut message_events: EventReader<NetMessageReceived>,
+    mut session: ResMut<NetSession>,
+    level_state: Res<State<LevelState>>,
+    turn_state: Res<State<TurnState>>,
+    mut mover: RustaceanMover,
+) {
+    let mut moved = false;
+
+    for NetMessageReceived(message) in message_events.read() {
+        let NetMessage::Move { rustacean_move } = message else {
+            continue;
+        };
+
+        // the move belongs to the rustacean whose turn it is, only the host decides that it is stuck
+        let legal = !moved
+            && *level_state == LevelState::InLevel
+            && *turn_state == TurnState::RustaceanTurn
+            && mover.moves().contains(rustacean_move);
+
+        if legal {
+            debug!("online: client moves {:?}", rustacean_move);
+            mover.apply(*rustacean_move);
+            moved = true;
+        } else {
+            debug!("online: refusing client move {:?}", rustacean_move);
+            session.send(&NetMessage::Rejected { reason: String::from("that move is not allowed right now") });
+            session.resync = true;
+        }
+    }
+}
+
+pub fn store_host_snapshot(
+    mut commands: Commands,
+    mut message_events: EventReader<NetMessageReceived>,
+) {
+    for NetMessageReceived(message) in message_events.read() {
+        if let NetMessage::Snapshot { board } = message {
+            commands.insert_resource(HostSnapshot(board.clone()));
+        }
+    }
+}
+
+/// Sets the level up as the host's board instead of a generated one
+pub fn use_host_board(
+    snapshot: Res<HostSnapshot>,
+    mut rules: ResMut<LevelRules>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut shown: ResMut<ShownBoardId>,
+) {
+    let board = &snapshot.0;
+    debug!("online: showing host board {} of level {}", board.board_id, board.level);
+
+    rules.grid_size = board.grid_size;
+    rules.trap_count = board.traps.len();
+    rules.rustacean_count = board.rustaceans.len();
+    rules.win_condition = board.win_condition;
+    // the client never places traps
+    rules.trap_inventory = TrapInventoryCounts::NONE;
+    rules.endless_tier = 0;
+    rules.modifiers.clear();
+    rules.puzzle = None;
+    rules.fixed_board = Some(FixedBoard {
+        rustaceans: board.rustaceans.clone(),
+        traps: board.traps.clone(),
+        special_tiles: board.special_tiles.clone(),
+        abilities: board.variants.iter().map(|variant| variant.map(|variant| variant.ability)).collect(),
+    });
+
+    current_level.0 = board.level;
+    shown.0 = Some(board.board_id);
+}
+
+/// Swaps in the host's next board once it arrives
+pub fn start_next_host_board(
+    mut commands: Commands,
+    snapshot: Option<Res<HostSnapshot>>,
+    shown: Res<ShownBoardId>,
+    level_state: Res<State<LevelState>>,
+    mut next_levelstate: ResMut<NextState<LevelState>>,
+    tile_q: Query<(Entity, &mut Sprite), With<GridTilePos>>,
+    rustacean_q: Query<Entity, With<RustaceanPos>>,
+    theme: Res<Theme>,
+) {
+    let Some(snapshot) = snapshot else {
+        return;
+    };
+    // the first board is set up when the game starts
+    if shown.0.is_none() || shown.0 == Some(snapshot.0.board_id) {
+        return;
+    }
+
+    match level_state.get() {
+        // entering the level state it is already in would not set anything up, so leave it first
+        LevelState::InLevel => next_levelstate.set(LevelState::OutOfLevel),
+        LevelState::LevelWin | LevelState::LevelLose | LevelState::OutOfLevel => {
+            clear_board(&mut commands, tile_q, rustacean_q, &theme);
+            commands.remove_resource::<LevelEndTimer>();
+            next_levelstate.set(LevelState::InLevel);
+        }
+    }
+}
+
+/// Mirrors the traps, rustacean and outcome of the host's board
+pub fn apply_host_snapshot(
+    mut commands: Commands,
+    snapshot: Option<Res<HostSnapshot>>,
+    shown: Res<ShownBoardId>,
+    tile_q: Query<(Entity, &GridTilePos, Has<TrapTile>, Has<ReinforcedTrap>)>,
+    mut trap_events: EventWriter<TrapPlaced>,
+    mut mover: RustaceanMover,
+) {
+    let Some(snapshot) = snapshot else {
+        return;
+    };
+    let board = &snapshot.0;
+    if shown.0 != Some(board.board_id) {
+        return;
+    }
+
+    for (entity, pos, is_trap, is_reinforced) in tile_q.iter() {
+        let trapped = board.traps.contains(&(pos.x, pos.y));
+        // only jumpers care, but the client needs it to know their moves
+        if !is_reinforced && board.reinforced.contains(&(pos.x, pos.y)) {
+            commands.entity(entity).insert(ReinforcedTrap);
+        }
+        if !is_trap && trapped {
+            commands.entity(entity).insert((TrapTile, TrapPlaceAnim::new()));
+            trap_events.write(TrapPlaced { x: pos.x, y: pos.y });
+        } else if is_trap && !trapped {
+            // a decaying trap ran out on the host
+            commands.entity(entity).remove::<TrapTile>();
+        }
+    }
+
+    mover.mirror(&board.rustaceans, &board.moved, &board.variants);
+    match board.outcome {
+        None => {}
+        Some(NetOutcome::Trapped) => mover.win(),
+        Some(NetOutcome::Escaped) => mover.lose(),
+    }
+}
+
+pub fn apply_host_turn(
+    snapshot: Option<Res<HostSnapshot>>,
+    shown: Res<ShownBoardId>,
+    turn_state: Res<State<TurnState>>,
+    mut next_turnstate: ResMut<NextState<TurnState>>,
+) {
+    let Some(snapshot) = snapshot else {
+        return;
+    };
+    let board = &snapshot.0;
+    if shown.0 != Some(board.board_id) || board.outcome.is_some() {
+        return;
+    }
+
+    let turn = match board.turn {
+        NetTurn::Trapper => TurnState::PlayerTurn,
+        NetTurn::Rustacean => TurnState::RustaceanTurn,
+    };
+    if *turn_state.get() != turn {
+        next_turnstate.set(turn);
+    }
+}
+
+/// Sends the rustacean moves picked on this machine to the host
+pub fn send_chosen_rustacean_moves(
+    mut move_events: EventReader<RustaceanMoveChosen>,
+    session: Res<NetSession>,
+) {
+    if let Some(RustaceanMoveChosen(rustacean_move)) = move_events.read().last().copied() {
+        session.send(&NetMessage::Move { rustacean_move });
+    }
+}
+
+/// Ends the game when the other player stays away for too long
+pub fn abandon_online_match(
+    mut commands: Commands,
+    session: Res<NetSession>,
+    grid_q: Query<Entity, With<GridSize>>,
+    rustacean_q: Query<Entity, With<RustaceanPos>>,
+    mut level_st: ResMut<NextState<LevelState>>,
+    mut app_st: ResMut<NextState<AppState>>,
+    mut game_over_events: EventWriter<GameOver>,
+) {
+    let gone_for = session.disconnected_for.as_ref().map_or(0.0, |stopwatch| stopwatch.elapsed_secs());
+    if gone_for < RECONNECT_TIMEOUT_SECS {
+        return;
+    }
+
+    debug!("online: {} did not come back, leaving the match", session.address);
+    for entity in grid_q.iter().chain(rustacean_q.iter()) {
+        commands.entity(entity).despawn();
+    }
+    // the match ends without a winner, the run is over all the same
+    game_over_events.write(GameOver { won: false });
+    level_st.set(LevelState::OutOfLevel);
+    app_st.set(AppState::MainMenu);
+}
+
+pub fn end_net_session(
+    mut commands: Commands,
+    mut board_id: ResMut<HostBoardId>,
+    mut shown: ResMut<ShownBoardId>,
+) {
+    commands.remove_resource::<NetSession>();
+    commands.remove_resource::<HostSnapshot>();
+    board_id.0 = 0;
+    shown.0 = None;
+}
+
+/// Span of the HUD showing this player's role, whose move it is and the connection
+#[derive(Component)]
+pub struct OnlineText;
+
+pub fn spawn_online_text(
+    world: &mut World,
+) {
+    spawn_hud_text(world, Node {
+        position_type: PositionType::Absolute,
+        top: Val::Px(0.0),
+        right: Val::Px(20.0),
+        ..default()
+    }, OnlineText);
+}
+
+pub fn online_text_update(
+    session: Res<NetSession>,
+    level_state: Res<State<LevelState>>,
+    turn_state: Res<State<TurnState>>,
+    localization: Res<Localization>,
+    mut query: Query<&mut TextSpan, With<OnlineText>>,
+) {
+    let role = if session.is_client() { "online.role_rustacean" } else { "online.role_trapper" };
+    let mut lines = vec![localization.text(role)];
+
+    if !session.connected {
+        let gone_for = session.disconnected_for.as_ref().map_or(0.0, |stopwatch| stopwatch.elapsed_secs());
+        let secs_left = (RECONNECT_TIMEOUT_SECS - gone_for).max(0.0).ceil();
+        lines.push(localization.format("online.reconnecting", &[("secs", &format!("{secs_left:.0}"))]));
+    } else if *level_state == LevelState::InLevel {
+        let my_turn = match turn_state.get() {
+            TurnState::PlayerTurn => !session.is_client(),
+            TurnState::RustaceanTurn => session.is_client(),
+        };
+        lines.push(localization.text(if my_turn { "online.your_move" } else { "online.their_move" }));
+    }
+
+    let text = lines.join("\n");
+    for mut span in &mut query {
+        if text != span.0 {
+            **span = text.clone()
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::io::Write;
+
+use crate::{game::{pathfinding::RustaceanMove, rustaceans::{RustaceanVariant, WinCondition}, tiles::SpecialTile}, utils::hexgrid_utils::GridSize};
+
+/// Bumped whenever a message changes shape, both sides have to agree on it
+pub const PROTOCOL_VERSION: u32 = 4;
+/// Where the lobby hosts and joins unless another address is typed in
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:7878";
+
+/// Whose move the host is waiting for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum NetTurn {
+    Trapper,
+    Rustacean,
+}
+
+/// How a board ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum NetOutcome {
+    Trapped,
+    /// The positions of escaped rustaceans are the exits or out of bounds tiles they left through
+    Escaped,
+}
+
+/// Everything the client needs to show the host's board, sent whenever any of it changes
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct BoardSnapshot {
+    /// Counts up with every board the host sets up, a new id means a new level
+    pub board_id: u32,
+    pub level: u32,
+    pub grid_size: GridSize,
+    /// Tile of every rustacean in turn order, an exit or out of bounds once it escaped
+    pub rustaceans: Vec<(i32, i32)>,
+    /// Turn order places of the rustaceans that already moved this turn
+    pub moved: Vec<usize>,
+    /// Ability and cooldown of every rustacean in turn order, empty for plain ones
+    pub variants: Vec<Option<RustaceanVariant>>,
+    pub win_condition: WinCondition,
+    /// Sorted so two snapshots of the same board compare equal
+    pub traps: Vec<(i32, i32)>,
+    /// The traps out of `traps` jumpers cant get over, sorted the same way
+    pub reinforced: Vec<(i32, i32)>,
+    /// Sorted by position like the traps
+    pub special_tiles: Vec<SpecialTile>,
+    pub turn: NetTurn,
+    pub outcome: Option<NetOutcome>,
+}
+
+/// One line of json on the wire
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum NetMessage {
+    /// First message of the client on every connection, reconnects included
+    Hello { version: u32 },
+    /// The host accepted the client
+    Welcome,
+    Snapshot { board: BoardSnapshot },
+    /// The client's pick for the rustacean, only carried out once the host checked it
+    Move { rustacean_move: RustaceanMove },
+    /// The host refused a connection or a move
+    Rejected { reason: String },
+}
+
+pub fn write_message(mut stream: impl Write, message: &NetMessage) -> std::io::Result<()> {
+    let mut line = serde_json::to_string(message)
+        .expect("Error: Implementation of Serialize must not decide to fail");
+    line.push('\n');
+    stream.write_all(line.as_bytes())
+}
@@ -0,0 +1,320 @@
+use std::{io::{BufRead, BufReader, ErrorKind}, net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs}, sync::{atomic::{AtomicBool, Ordering}, mpsc::{channel, Receiver, Sender}, Arc, Mutex}, thread, time::Duration};
+
+use bevy::{ecs::{event::{Event, EventWriter}, resource::Resource, system::{Res, ResMut}}, log::{debug, warn}, time::{Stopwatch, Time}};
+use crate::network::protocol::{write_message, NetMessage, PROTOCOL_VERSION};
+
+/// Seconds between two attempts of the client to reach the host
+const RETRY_SECS: f32 = 1.0;
+/// How often the host checks for a new connection and for being shut down
+const ACCEPT_POLL_MILLIS: u64 = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetRole {
+    /// Owns the board and places the traps
+    Host,
+    /// Moves the rustacean, every move is checked by the host
+    Client,
+}
+
+/// What the connection threads report back to the game
+enum NetEvent {
+    Connected,
+    Message(NetMessage),
+    Disconnected,
+    Failed(String),
+}
+
+/// A game message from the other side, session messages are handled by the session itself
+#[derive(Event, Clone, Debug)]
+pub struct NetMessageReceived(pub NetMessage);
+
+/// The connection to the other player, alive from the lobby until the game ends
+#[derive(Resource)]
+pub struct NetSession {
+    pub role: NetRole,
+    pub address: String,
+    /// The other side said hello and is taking part in the match
+    pub connected: bool,
+    /// Running while the other side is gone, empty before it first showed up
+    pub disconnected_for: Option<Stopwatch>,
+    /// Set when the host has to send the whole board again, after a reconnect or a refused move
+    pub resync: bool,
+    /// Last reason the connection failed or was refused
+    pub last_error: Option<String>,
+    /// The host turned this client away in the handshake, it does not try again
+    pub refused: bool,
+    events: Mutex<Receiver<NetEvent>>,
+    stream: Arc<Mutex<Option<TcpStream>>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl NetSession {
+    /// Listens on `address` and lets one client at a time join
+    pub fn host(address: &str) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+
+        let (session, sender) = NetSession::new(NetRole::Host, address);
+        let (stream, stop) = (session.stream.clone(), session.stop.clone());
+        thread::spawn(move || accept_loop(listener, sender, stream, stop));
+
+        debug!("network: hosting on {address}");
+        Ok(session)
+    }
+
+    /// Keeps trying to reach the host at `address` until the session is dropped or the host refuses it
+    pub fn join(address: &str) -> Self {
+        let (session, sender) = NetSession::new(NetRole::Client, address);
+        let (stream, stop) = (session.stream.clone(), session.stop.clone());
+        let target = address.to_string();
+        thread::spawn(move || connect_loop(target, sender, stream, stop));
+
+        debug!("network: joining {address}");
+        session
+    }
+
+    /// The session and the sender half of its event channel, which goes to the connection threads
+    fn new(role: NetRole, address: &str) -> (Self, Sender<NetEvent>) {
+        let (sender, receiver) = channel();
+        let session = NetSession {
+            role,
+            address: address.to_string(),
+            connected: false,
+            disconnected_for: None,
+            resync: false,
+            last_error: None,
+            refused: false,
+            events: Mutex::new(receiver),
+            stream: Arc::new(Mutex::new(None)),
+            stop: Arc::new(AtomicBool::new(false)),
+        };
+        (session, sender)
+    }
+
+    pub fn is_client(&self) -> bool {
+        self.role == NetRole::Client
+    }
+
+    /// Sends `message` if there is a connection, a failed write drops the connection
+    pub fn send(&self, message: &NetMessage) {
+        let mut stream = self.stream.lock().expect("Error: connection lock must not be poisoned");
+        let Some(connection) = stream.as_ref() else {
+            return;
+        };
+        if let Err(err) = write_message(connection, message) {
+            warn!("network: could not send to {}: {err}", self.address);
+            let _ = connection.shutdown(Shutdown::Both);
+            *stream = None;
+        }
+    }
+
+    /// Closes the current connection, the client will try to connect again
+    pub fn kick(&self) {
+        let stream = self.stream.lock().expect("Error: connection lock must not be poisoned");
+        if let Some(connection) = stream.as_ref() {
+            let _ = connection.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+impl Drop for NetSession {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // unblocks the reading thread
+        self.kick();
+    }
+}
+
+fn accept_loop(listener: TcpListener, sender: Sender<NetEvent>, stream: Arc<Mutex<Option<TcpStream>>>, stop: Arc<AtomicBool>) {
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((connection, peer)) => {
+                let _ = connection.set_nonblocking(false);
+                let _ = connection.set_nodelay(true);
+
+                let mut current = stream.lock().expect("Error: connection lock must not be poisoned");
+                if current.is_some() {
+                    debug!("network: turning away {peer}, a player is already connected");
+                    let _ = write_message(&connection, &NetMessage::Rejected { reason: String::from("a match is already running") });
+                    continue;
+                }
+
+                let Ok(reader) = connection.try_clone() else {
+                    continue;
+                };
+                debug!("network: {peer} connected");
+                *current = Some(connection);
+                drop(current);
+
+                if sender.send(NetEvent::Connected).is_err() {
+                    return;
+                }
+                let (sender, stream) = (sender.clone(), stream.clone());
+                thread::spawn(move || read_loop(reader, sender, stream));
+            }
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(ACCEPT_POLL_MILLIS));
+            }
+            Err(err) => {
+                if sender.send(NetEvent::Failed(err.to_string())).is_err() {
+                    return;
+                }
+                thread::sleep(Duration::from_millis(ACCEPT_POLL_MILLIS));
+            }
+        }
+    }
+}
+
+fn connect_loop(address: String, sender: Sender<NetEvent>, stream: Arc<Mutex<Option<TcpStream>>>, stop: Arc<AtomicBool>) {
+    while !stop.load(Ordering::Relaxed) {
+        match connect(&address) {
+            Ok(connection) => {
+                let _ = connection.set_nodelay(true);
+                match connection.try_clone() {
+                    Ok(reader) => {
+                        let mut current = stream.lock().expect("Error: connection lock must not be poisoned");
+                        // the session may have been dropped while connecting
+                        if stop.load(Ordering::Relaxed) {
+                            let _ = connection.shutdown(Shutdown::Both);
+                            return;
+                        }
+                        *current = Some(connection);
+                        drop(current);
+
+                        if sender.send(NetEvent::Connected).is_err() {
+                            return;
+                        }
+                        // blocks until the connection is gone
+                        read_loop(reader, sender.clone(), stream.clone());
+                    }
+                    Err(err) => {
+                        let _ = sender.send(NetEvent::Failed(err.to_string()));
+                    }
+                }
+            }
+            Err(err) => {
+                if sender.send(NetEvent::Failed(err)).is_err() {
+                    return;
+                }
+            }
+        }
+
+        thread::sleep(Duration::from_secs_f32(RETRY_SECS));
+    }
+}
+
+fn connect(address: &str) -> Result<TcpStream, String> {
+    let target = address.to_socket_addrs()
+        .map_err(|err| err.to_string())?
+        .next()
+        .ok_or_else(|| format!("{address} does not resolve to an address"))?;
+    TcpStream::connect_timeout(&target, Duration::from_secs(2)).map_err(|err| err.to_string())
+}
+
+/// Forwards every message on `connection` until it closes
+fn read_loop(connection: TcpStream, sender: Sender<NetEvent>, stream: Arc<Mutex<Option<TcpStream>>>) {
+    for line in BufReader::new(connection).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        match serde_json::from_str(&line) {
+            Ok(message) => {
+                if sender.send(NetEvent::Message(message)).is_err() {
+                    return;
+                }
+            }
+            // a bad line is dropped, the next snapshot puts things right again
+            Err(err) => warn!("network: ignoring malformed message: {err}"),
+        }
+    }
+
+    *stream.lock().expect("Error: connection lock must not be poisoned") = None;
+    let _ = sender.send(NetEvent::Disconnected);
+}
+
+/// Run condition that is true while this machine plays the client of an online game
+pub fn is_net_client(
+    session: Option<Res<NetSession>>,
+) -> bool {
+    session.is_some_and(|session| session.is_client())
+}
+
+/// Run condition that is true while this machine hosts an online game
+pub fn is_net_host(
+    session: Option<Res<NetSession>>,
+) -> bool {
+    session.is_some_and(|session| !session.is_client())
+}
+
+/// Handles the handshake and connection changes, and passes game messages on as events
+pub fn poll_net_session(
+    mut session: ResMut<NetSession>,
+    mut message_events: EventWriter<NetMessageReceived>,
+    time: Res<Time>,
+) {
+    if let Some(stopwatch) = session.disconnected_for.as_mut() {
+        stopwatch.tick(time.delta());
+    }
+
+    let events: Vec<NetEvent> = session.events.lock()
+        .expect("Error: event lock must not be poisoned")
+        .try_iter()
+        .collect();
+
+    for event in events {
+        match event {
+            NetEvent::Connected => {
+                debug!("network: connection to {} is up", session.address);
+                if session.is_client() {
+                    session.send(&NetMessage::Hello { version: PROTOCOL_VERSION });
+                }
+            }
+            NetEvent::Message(NetMessage::Hello { version }) if !session.is_client() => {
+                if version != PROTOCOL_VERSION {
+                    let reason = format!("the host speaks protocol {PROTOCOL_VERSION}, the client {version}");
+                    warn!("network: refusing client, {reason}");
+                    session.send(&NetMessage::Rejected { reason });
+                    session.kick();
+                    continue;
+                }
+                session.send(&NetMessage::Welcome);
+                session.connected = true;
+                session.disconnected_for = None;
+                session.resync = true;
+            }
+            NetEvent::Message(NetMessage::Welcome) if session.is_client() => {
+                session.connected = true;
+                session.disconnected_for = None;
+                session.last_error = None;
+            }
+            NetEvent::Message(NetMessage::Rejected { reason }) => {
+                warn!("network: rejected by {}: {reason}", session.address);
+                session.last_error = Some(reason);
+                // a refused move is answered with a fresh snapshot, a refused client would be refused again
+                if !session.connected {
+                    session.refused = true;
+                    session.stop.store(true, Ordering::Relaxed);
+                    session.kick();
+                }
+            }
+            NetEvent::Message(message @ (NetMessage::Snapshot { .. } | NetMessage::Move { .. })) => {
+                if session.connected {
+                    message_events.write(NetMessageReceived(message));
+                }
+            }
+            NetEvent::Message(message) => {
+                warn!("network: ignoring unexpected {:?}", message);
+            }
+            NetEvent::Disconnected => {
+                debug!("network: lost the connection to {}", session.address);
+                if session.connected {
+                    session.disconnected_for = Some(Stopwatch::new());
+                }
+                session.connected = false;
+            }
+            NetEvent::Failed(err) => {
+                session.last_error = Some(err);
+            }
+        }
+    }
+}
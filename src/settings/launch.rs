@@ -0,0 +1,241 @@
+use std::{path::{Path, PathBuf}, str::FromStr, sync::OnceLock};
+
+use bevy::{ecs::resource::Resource, log::Level};
+use crate::{game::{difficulty::DifficultyKind, game_mode::GameMode, replay::Replay, rustaceans::WinCondition}, utils::hexgrid_utils::GridSize};
+
+/// Folder holding settings, stats and scores unless `--data-dir` points somewhere else
+const DEFAULT_DATA_DIR: &str = "./configs";
+
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+pub const USAGE: &str = "\
+Usage: trap-the-tiger-rs [OPTIONS]
+
+Options:
+  --seed <N>             seed every random pick, the same seed lays out the same boards
+  --level <N>            level a new game starts on
+  --grid <COLSxROWS>     board size of every generated level, e.g. 9x14
+  --difficulty <NAME>    easy, normal, hard or custom, instead of the one picked in the menu
+  --ai <NAME>            rustacean strategy on every level, the difficulty's AI tier by default
+  --fullscreen           start in borderless fullscreen
+  --windowed             start in a window, the default
+  --resolution <WxH>     size of the window, 960x540 by default
+  --data-dir <PATH>      folder for settings, stats, scores and replays, ./configs by default
+  --replay <FILE>        play back a recorded game, starts it right away
+  --log-level <LEVEL>    error, warn, info, debug or trace
+  --play                 skip the main menu and start a game right away
+  -h, --help             print this help";
+
+/// How the window opens
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LaunchWindowMode {
+    #[default]
+    Windowed,
+    /// The same borderless fullscreen F11 switches to
+    Fullscreen,
+}
+
+/// Options the game was started with, they hold for every game of the session.
+///
+/// A replay fills them in from the recorded game, including the mode, rustaceans and
+/// win condition that have no option of their own.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct LaunchOptions {
+    pub seed: Option<u64>,
+    pub start_level: Option<u32>,
+    pub grid_size: Option<GridSize>,
+    pub difficulty: Option<DifficultyKind>,
+    pub game_mode: Option<GameMode>,
+    pub rustaceans: Option<u32>,
+    pub win_condition: Option<WinCondition>,
+    /// Strategy every rustacean follows instead of its AI tier
+    pub rustacean_ai: Option<String>,
+    pub window_mode: LaunchWindowMode,
+    pub resolution: Option<(f32, f32)>,
+    pub data_dir: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub log_level: Option<Level>,
+    /// Start a game without going through the main menu
+    pub skip_menu: bool,
+}
+
+impl LaunchOptions {
+    /// Reads the options from the command line arguments, without the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = LaunchOptions::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+            match arg.as_str() {
+                "--seed" => options.seed = Some(parse_number(&arg, &value()?)?),
+                "--level" => options.start_level = Some(parse_level(&arg, &value()?)?),
+                "--grid" => options.grid_size = Some(parse_grid(&arg, &value()?)?),
+                "--difficulty" => options.difficulty = Some(parse_difficulty(&value()?)?),
+                // checked against the strategy registry when the game starts
+                "--ai" => options.rustacean_ai = Some(value()?.to_lowercase()),
+                "--fullscreen" => options.window_mode = LaunchWindowMode::Fullscreen,
+                "--windowed" => options.window_mode = LaunchWindowMode::Windowed,
+                "--resolution" => {
+                    let (width, height) = parse_pair::<f32>(&arg, &value()?)?;
+                    if width < 1.0 || height < 1.0 {
+                        return Err(String::from("--resolution must be at least 1x1"));
+                    }
+                    options.resolution = Some((width, height));
+                }
+                "--data-dir" => options.data_dir = Some(PathBuf::from(value()?)),
+                "--replay" => options.replay = Some(PathBuf::from(value()?)),
+                "--log-level" => {
+                    let level = value()?;
+                    options.log_level = Some(Level::from_str(&level).map_err(|_| format!("unknown log level {level}"))?);
+                }
+                "--play" => options.skip_menu = true,
+                _ => return Err(format!("unknown option {arg}")),
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// Sets up the game the replay was recorded in
+    pub fn follow_replay(&mut self, replay: &Replay) {
+        self.seed = Some(replay.seed);
+        self.start_level = Some(replay.start_level);
+        self.grid_size = replay.grid_size.map(|(cols, rows)| GridSize::new(cols, rows));
+        self.difficulty = Some(replay.difficulty);
+        self.game_mode = Some(replay.game_mode);
+        self.rustaceans = Some(replay.rustaceans);
+        self.win_condition = Some(replay.win_condition);
+        self.rustacean_ai = replay.rustacean_ai.clone();
+        self.skip_menu = true;
+    }
+}
+
+pub fn parse_number<T: FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("{arg} expects a number, got {value}"))
+}
+
+/// Two numbers written as `AxB`
+pub fn parse_pair<T: FromStr>(arg: &str, value: &str) -> Result<(T, T), String> {
+    let (first, second) = value.split_once(['x', 'X'])
+        .ok_or_else(|| format!("{arg} expects two numbers like 9x14, got {value}"))?;
+    Ok((parse_number(arg, first)?, parse_number(arg, second)?))
+}
+
+/// A level number, they start at 1
+pub fn parse_level(arg: &str, value: &str) -> Result<u32, String> {
+    match parse_number(arg, value)? {
+        0 => Err(format!("{arg} starts at 1")),
+        level => Ok(level),
+    }
+}
+
+/// A board size written as `COLSxROWS`
+pub fn parse_grid(arg: &str, value: &str) -> Result<GridSize, String> {
+    let (cols, rows) = parse_pair(arg, value)?;
+    // anything smaller than 3x3 has no tile that isnt on the border
+    if cols < 3 || rows < 3 {
+        return Err(format!("{arg} must be at least 3x3"));
+    }
+    Ok(GridSize::new(cols, rows))
+}
+
+/// A difficulty by the name the menu shows, ignoring case
+pub fn parse_difficulty(name: &str) -> Result<DifficultyKind, String> {
+    DifficultyKind::ALL.into_iter()
+        .find(|kind| kind.key().strip_prefix("difficulty.") == Some(name.to_lowercase().as_str()))
+        .ok_or_else(|| format!("unknown difficulty {name}"))
+}
+
+/// Points every config and save file at `dir`, has to happen before any of them is read
+pub fn set_data_dir(dir: PathBuf) {
+    if DATA_DIR.set(dir).is_err() {
+        panic!("Error: the data directory must only be set once, before it is used");
+    }
+}
+
+/// Folder the configs and saves live in
+pub fn data_dir() -> &'static Path {
+    DATA_DIR.get_or_init(|| PathBuf::from(DEFAULT_DATA_DIR))
+}
+
+/// Keeps what tests write out of ./configs, every test that reads or writes data files calls it first
+#[cfg(test)]
+pub(crate) fn use_test_data_dir() {
+    DATA_DIR.get_or_init(|| std::env::temp_dir().join(format!("trap-the-tiger-tests-{}", std::process::id())));
+}
+
+/// Path of a file in the data directory
+pub fn data_path(file_name: &str) -> PathBuf {
+    data_dir().join(file_name)
+}
+
+/// Creates the data directory if it isnt there yet
+pub fn ensure_data_dir() {
+    if !data_dir().exists() {
+        std::fs::create_dir_all(data_dir())
+            .expect("Error: User should have permission to the directory location");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<LaunchOptions, String> {
+        LaunchOptions::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn reads_every_option() {
+        let options = parse(&[
+            "--seed", "42", "--level", "3", "--grid", "9x14", "--difficulty", "Hard", "--ai", "Strategist", "--fullscreen",
+            "--resolution", "1280x720", "--data-dir", "saves", "--replay", "run.json", "--log-level", "debug", "--play",
+        ]).unwrap();
+
+        assert_eq!(options.seed, Some(42));
+        assert_eq!(options.start_level, Some(3));
+        assert_eq!(options.grid_size, Some(GridSize::new(9, 14)));
+        assert_eq!(options.difficulty, Some(DifficultyKind::Hard));
+        assert_eq!(options.rustacean_ai.as_deref(), Some("strategist"));
+        assert_eq!(options.window_mode, LaunchWindowMode::Fullscreen);
+        assert_eq!(options.resolution, Some((1280.0, 720.0)));
+        assert_eq!(options.data_dir, Some(PathBuf::from("saves")));
+        assert_eq!(options.replay, Some(PathBuf::from("run.json")));
+        assert_eq!(options.log_level, Some(Level::DEBUG));
+        assert!(options.skip_menu);
+    }
+
+    #[test]
+    fn refuses_bad_values() {
+        for args in [
+            &["--seed", "abc"][..],
+            &["--resolution", "0x540"],
+            &["--log-level", "loud"],
+            &["--speed"],
+        ] {
+            assert!(parse(args).is_err(), "{args:?} should be refused");
+        }
+    }
+
+    #[test]
+    fn refuses_a_missing_value() {
+        assert_eq!(parse(&["--play", "--seed"]).unwrap_err(), "--seed needs a value");
+    }
+
+    #[test]
+    fn parses_the_shared_values() {
+        assert_eq!(parse_number::<u64>("--seed", "42"), Ok(42));
+        assert_eq!(parse_level("--level", "3"), Ok(3));
+        assert_eq!(parse_grid("--grid", "9X14"), Ok(GridSize::new(9, 14)));
+        assert_eq!(parse_difficulty("Easy"), Ok(DifficultyKind::Easy));
+
+        assert!(parse_number::<u64>("--seed", "abc").is_err());
+        assert!(parse_level("--level", "0").is_err());
+        assert!(parse_level("--level", "-2").is_err());
+        assert!(parse_grid("--grid", "9").is_err());
+        assert!(parse_grid("--grid", "2x9").is_err());
+        assert!(parse_difficulty("impossible").is_err());
+        assert_eq!(parse_grid("--grid", "0x0").unwrap_err(), "--grid must be at least 3x3");
+    }
+}
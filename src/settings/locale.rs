@@ -0,0 +1,172 @@
+use std::{collections::HashMap, path::PathBuf};
+use bevy::{asset::{AssetServer, Handle}, ecs::resource::Resource, log::warn, text::{Font, TextFont}, utils::default};
+
+/// English is compiled in, so missing files or keys never leave the ui blank
+const FALLBACK_LOCALE: &str = include_str!("../../assets/locales/en.json");
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Language {
+    #[default]
+    English,
+    Spanish,
+    German,
+}
+
+impl Language {
+    pub const ALL: [Language; 3] = [
+        Language::English,
+        Language::Spanish,
+        Language::German,
+    ];
+
+    /// File name of the language file in ./assets/locales
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Spanish => "es",
+            Language::German => "de",
+        }
+    }
+
+    /// The language after this one, wrapping around, used by the settings button
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|language| *language == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// CLDR plural category of `n` for the languages we ship
+    fn plural_category(self, n: u64) -> &'static str {
+        match self {
+            Language::English | Language::Spanish | Language::German => {
+                if n == 1 { "one" } else { "other" }
+            }
+        }
+    }
+
+    fn thousands_separator(self) -> char {
+        match self {
+            Language::English => ',',
+            Language::Spanish | Language::German => '.',
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum LocaleEntry {
+    Text(String),
+    Plural {
+        one: Option<String>,
+        other: String,
+    },
+}
+
+#[derive(serde::Deserialize)]
+struct LocaleFile {
+    /// Path inside ./assets of a font covering the language's script
+    font: Option<String>,
+    strings: HashMap<String, LocaleEntry>,
+}
+
+/// Translated ui strings for the selected language
+#[derive(Resource)]
+pub struct Localization {
+    pub language: Language,
+    pub font: Handle<Font>,
+    strings: HashMap<String, LocaleEntry>,
+    fallback: HashMap<String, LocaleEntry>,
+}
+
+impl Localization {
+    pub fn load(language: Language, asset_server: &AssetServer) -> Self {
+        let fallback: LocaleFile = serde_json::from_str(FALLBACK_LOCALE)
+            .expect("Error: the built in english locale must be valid");
+
+        let path = PathBuf::from(format!("./assets/locales/{}.json", language.code()));
+        let file = std::fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|json| serde_json::from_str::<LocaleFile>(&json).map_err(|err| err.to_string()));
+
+        let file = match file {
+            Ok(file) => file,
+            Err(err) => {
+                warn!("Could not load {}, falling back to english: {err}", path.display());
+                LocaleFile { font: None, strings: HashMap::new() }
+            }
+        };
+
+        // the default font only covers latin script, languages written in another one bring their own
+        let font = match file.font {
+            Some(font_path) if PathBuf::from("./assets").join(&font_path).exists() => asset_server.load(font_path),
+            Some(font_path) => {
+                warn!("Font assets/{font_path} for {:?} is missing, some characters may not render", language);
+                Handle::default()
+            }
+            None => Handle::default(),
+        };
+
+        Localization {
+            language,
+            font,
+            strings: file.strings,
+            fallback: fallback.strings,
+        }
+    }
+
+    fn entry(&self, key: &str) -> Option<&LocaleEntry> {
+        self.strings.get(key).or_else(|| self.fallback.get(key))
+    }
+
+    /// The translation of `key`, or the key itself if no language defines it
+    pub fn text(&self, key: &str) -> String {
+        match self.entry(key) {
+            Some(LocaleEntry::Text(text)) => text.clone(),
+            Some(LocaleEntry::Plural { other, .. }) => other.clone(),
+            None => {
+                warn!("Missing translation for {key}");
+                key.to_string()
+            }
+        }
+    }
+
+    /// The translation of `key` with every `{name}` replaced by its value
+    pub fn format(&self, key: &str, args: &[(&str, &str)]) -> String {
+        let mut text = self.text(key);
+        for (name, value) in args {
+            text = text.replace(&format!("{{{name}}}"), value);
+        }
+        text
+    }
+
+    /// The plural form of `key` matching `n`, with `{n}` replaced by the formatted number
+    pub fn plural(&self, key: &str, n: u64) -> String {
+        let text = match self.entry(key) {
+            Some(LocaleEntry::Plural { one: Some(one), .. }) if self.language.plural_category(n) == "one" => one.clone(),
+            Some(_) => self.text(key),
+            None => key.to_string(),
+        };
+        text.replace("{n}", &self.number(n))
+    }
+
+    /// `n` with the language's thousands separator
+    pub fn number(&self, n: u64) -> String {
+        let digits = n.to_string();
+        let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                formatted.push(self.language.thousands_separator());
+            }
+            formatted.push(digit);
+        }
+        formatted
+    }
+
+    /// Text font using the language's font at the given size
+    pub fn text_font(&self, font_size: f32) -> TextFont {
+        TextFont {
+            font: self.font.clone(),
+            font_size,
+            ..default()
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub mod settings;
+pub mod theme;
+pub mod locale;
+pub mod launch;
\ No newline at end of file
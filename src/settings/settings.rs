@@ -0,0 +1,181 @@
+use anyhow::Context;
+use bevy::{app::{Plugin, PreStartup, Update}, asset::AssetServer, ecs::{resource::Resource, schedule::{common_conditions::resource_changed, IntoScheduleConfigs}, system::{Commands, Res, ResMut}}, log::warn, render::camera::ClearColor};
+use crate::{game::{difficulty::DifficultyKind, game_mode::GameMode, rustaceans::{WinCondition, MAX_RUSTACEANS}}, network::protocol::DEFAULT_ADDRESS, settings::{launch::{data_path, ensure_data_dir, LaunchOptions}, locale::{Language, Localization}, theme::{Theme, ThemeKind}}};
+
+pub struct SettingsPlugin;
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut bevy::app::App) {
+
+        // main puts in the parsed command line, a plain run starts without any options
+        app.init_resource::<LaunchOptions>();
+
+        // settings have to exist before any menu or board reads the theme
+        app.add_systems(PreStartup, setup_settings);
+
+        app.add_systems(
+            Update,
+            (apply_theme_setting, apply_language_setting, save_settings)
+                .run_if(resource_changed::<Settings>)
+        );
+    }
+}
+
+/// User preferences, persisted to settings.json in the data directory
+#[derive(Resource, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub theme: ThemeKind,
+    pub language: Language,
+    pub difficulty: DifficultyKind,
+    pub game_mode: GameMode,
+    /// Rustaceans on every generated board, from 1 to 3
+    pub rustaceans: u32,
+    pub win_condition: WinCondition,
+    /// Volumes run from 0.0 to 1.0, music and effects are scaled by the master volume
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub effects_volume: f32,
+    pub muted: bool,
+    /// Address the multiplayer lobby hosts on or joins
+    pub net_address: String,
+    /// Name the last high score was saved under
+    pub player_name: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            theme: ThemeKind::default(),
+            language: Language::default(),
+            difficulty: DifficultyKind::default(),
+            game_mode: GameMode::default(),
+            rustaceans: 1,
+            win_condition: WinCondition::default(),
+            master_volume: 0.8,
+            music_volume: 0.5,
+            effects_volume: 0.8,
+            muted: false,
+            net_address: String::from(DEFAULT_ADDRESS),
+            player_name: String::from("Player"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VolumeChannel {
+    Master,
+    Music,
+    Effects,
+}
+
+impl VolumeChannel {
+    /// Localization key of the channel's settings label
+    pub fn key(self) -> &'static str {
+        match self {
+            VolumeChannel::Master => "settings.volume.master",
+            VolumeChannel::Music => "settings.volume.music",
+            VolumeChannel::Effects => "settings.volume.effects",
+        }
+    }
+}
+
+impl Settings {
+    /// The rustacean count after the current one, wrapping back to a single rustacean
+    pub fn next_rustaceans(&self) -> u32 {
+        self.rustaceans % MAX_RUSTACEANS + 1
+    }
+
+    pub fn volume(&self, channel: VolumeChannel) -> f32 {
+        match channel {
+            VolumeChannel::Master => self.master_volume,
+            VolumeChannel::Music => self.music_volume,
+            VolumeChannel::Effects => self.effects_volume,
+        }
+    }
+
+    /// Moves a volume by `delta`, snapped to 10% steps and kept within 0 to 100%
+    pub fn adjust_volume(&mut self, channel: VolumeChannel, delta: f32) {
+        let volume = match channel {
+            VolumeChannel::Master => &mut self.master_volume,
+            VolumeChannel::Music => &mut self.music_volume,
+            VolumeChannel::Effects => &mut self.effects_volume,
+        };
+        *volume = ((*volume + delta) * 10.0).round().clamp(0.0, 10.0) / 10.0;
+    }
+
+    /// Final linear volume of a channel after the master volume and mute are applied
+    pub fn effective_volume(&self, channel: VolumeChannel) -> f32 {
+        if self.muted {
+            return 0.0;
+        }
+        match channel {
+            VolumeChannel::Master => self.master_volume,
+            channel => self.master_volume * self.volume(channel),
+        }
+    }
+}
+
+fn setup_settings(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    let settings = load_settings();
+
+    let theme = Theme::from_kind(settings.theme);
+    commands.insert_resource(ClearColor(theme.background));
+    commands.insert_resource(theme);
+    commands.insert_resource(Localization::load(settings.language, &asset_server));
+    commands.insert_resource(settings);
+}
+
+/// Reads settings.json, the defaults when there is none yet
+pub fn load_settings() -> Settings {
+    let settings_path = data_path("settings.json");
+
+    if settings_path.exists() {
+        let json = std::fs::read_to_string(&settings_path)
+            .expect("Error: Path must exist\nContents of the file must be valid utf8");
+
+        // a broken settings file shouldnt keep the game from starting
+        serde_json::from_str(&json).unwrap_or_else(|err| {
+            warn!("Could not parse {}, using default settings: {err}", settings_path.display());
+            Settings::default()
+        })
+    } else {
+        Settings::default()
+    }
+}
+
+fn apply_theme_setting(
+    settings: Res<Settings>,
+    mut theme: ResMut<Theme>,
+    mut clear_color: ResMut<ClearColor>,
+) {
+    if theme.kind != settings.theme {
+        *theme = Theme::from_kind(settings.theme);
+        clear_color.0 = theme.background;
+    }
+}
+
+fn apply_language_setting(
+    settings: Res<Settings>,
+    mut localization: ResMut<Localization>,
+    asset_server: Res<AssetServer>,
+) {
+    if localization.language != settings.language {
+        *localization = Localization::load(settings.language, &asset_server);
+    }
+}
+
+pub fn save_settings(
+    settings: Res<Settings>
+) {
+    ensure_data_dir();
+
+    let json = serde_json::to_string_pretty(settings.into_inner())
+        .expect("Error: Implementation of Serialize must not decide to fail\nT should contain a map with string keys");
+
+    std::fs::write(data_path("settings.json"), json)
+        .context("Writing settings to json file")
+        .expect("Error: The data directory must exist");
+}
@@ -0,0 +1,235 @@
+use bevy::{color::{palettes::css::{DARK_GRAY, LIGHT_GREEN, LIGHT_SKY_BLUE, ORANGE, RED}, Color}, ecs::resource::Resource};
+use crate::game::tiles::TileKind;
+
+/// Built-in palettes selectable from the settings menu
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ThemeKind {
+    #[default]
+    Light,
+    Dark,
+    HighContrast,
+    /// Red-green safe palette for deuteranopia (green-weak vision)
+    Deuteranopia,
+    /// Red-green safe palette for protanopia (red-weak vision)
+    Protanopia,
+}
+
+impl ThemeKind {
+    pub const ALL: [ThemeKind; 5] = [
+        ThemeKind::Light,
+        ThemeKind::Dark,
+        ThemeKind::HighContrast,
+        ThemeKind::Deuteranopia,
+        ThemeKind::Protanopia,
+    ];
+
+    /// Localization key of the theme's display name
+    pub fn key(self) -> &'static str {
+        match self {
+            ThemeKind::Light => "theme.light",
+            ThemeKind::Dark => "theme.dark",
+            ThemeKind::HighContrast => "theme.high_contrast",
+            ThemeKind::Deuteranopia => "theme.deuteranopia",
+            ThemeKind::Protanopia => "theme.protanopia",
+        }
+    }
+
+    /// The theme after this one, wrapping around, used by the settings button
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|kind| *kind == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// Every color the game draws with; tiles, HUD and menus all read from this resource
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct Theme {
+    pub kind: ThemeKind,
+    /// Window clear color behind the board
+    pub background: Color,
+    pub tile: Color,
+    pub trap: Color,
+    /// Traps that vanish after a few turns
+    pub decaying_trap: Color,
+    /// Traps rustacean abilities cant get past
+    pub reinforced_trap: Color,
+    pub rock: Color,
+    pub mud: Color,
+    pub teleporter: Color,
+    pub one_way: Color,
+    pub exit: Color,
+    pub tile_hover: Color,
+    /// Tiles a human controlled rustacean may move to
+    pub legal_move: Color,
+    /// Tiles on a rustacean's way out while it is revealed
+    pub revealed_path: Color,
+    /// Flash color of a tile that cant take a trap
+    pub invalid_tap: Color,
+    /// Board color after trapping the rustacean
+    pub win: Color,
+    /// Board color after the rustacean escapes
+    pub lose: Color,
+    pub hud_text: Color,
+    pub menu_background: Color,
+    pub menu_text: Color,
+    pub button_normal: Color,
+    pub button_hovered: Color,
+    pub button_pressed: Color,
+    pub button_hovered_pressed: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::from_kind(ThemeKind::default())
+    }
+}
+
+impl Theme {
+    pub fn from_kind(kind: ThemeKind) -> Self {
+        match kind {
+            ThemeKind::Light => Theme {
+                kind,
+                background: Color::srgb(0.80, 0.84, 0.88),
+                tile: Color::WHITE,
+                trap: Color::Srgba(DARK_GRAY),
+                decaying_trap: Color::srgb(0.55, 0.55, 0.6),
+                reinforced_trap: Color::srgb(0.1, 0.1, 0.14),
+                rock: Color::srgb(0.45, 0.4, 0.35),
+                mud: Color::srgb(0.6, 0.45, 0.3),
+                teleporter: Color::srgb(0.7, 0.55, 0.95),
+                one_way: Color::srgb(0.85, 0.88, 0.95),
+                exit: Color::srgb(1.0, 0.85, 0.4),
+                tile_hover: Color::Srgba(LIGHT_SKY_BLUE),
+                legal_move: Color::srgb(0.75, 0.95, 0.6),
+                revealed_path: Color::srgb(0.6, 0.85, 1.0),
+                invalid_tap: Color::Srgba(RED),
+                win: Color::Srgba(LIGHT_GREEN),
+                lose: Color::Srgba(RED),
+                hud_text: Color::srgb(0.1, 0.1, 0.1),
+                menu_background: Color::Srgba(ORANGE),
+                menu_text: Color::srgb(0.9, 0.9, 0.9),
+                button_normal: Color::srgb(0.15, 0.15, 0.15),
+                button_hovered: Color::srgb(0.25, 0.25, 0.25),
+                button_pressed: Color::srgb(0.35, 0.75, 0.35),
+                button_hovered_pressed: Color::srgb(0.25, 0.65, 0.25),
+            },
+            ThemeKind::Dark => Theme {
+                kind,
+                background: Color::srgb(0.06, 0.06, 0.08),
+                tile: Color::srgb(0.55, 0.58, 0.64),
+                trap: Color::srgb(0.16, 0.16, 0.2),
+                decaying_trap: Color::srgb(0.3, 0.3, 0.36),
+                reinforced_trap: Color::srgb(0.05, 0.05, 0.08),
+                rock: Color::srgb(0.3, 0.27, 0.24),
+                mud: Color::srgb(0.42, 0.33, 0.24),
+                teleporter: Color::srgb(0.5, 0.4, 0.7),
+                one_way: Color::srgb(0.62, 0.66, 0.74),
+                exit: Color::srgb(0.75, 0.62, 0.3),
+                tile_hover: Color::srgb(0.45, 0.62, 0.85),
+                legal_move: Color::srgb(0.35, 0.6, 0.4),
+                revealed_path: Color::srgb(0.2, 0.45, 0.6),
+                invalid_tap: Color::srgb(0.85, 0.25, 0.25),
+                win: Color::srgb(0.3, 0.7, 0.4),
+                lose: Color::srgb(0.75, 0.2, 0.2),
+                hud_text: Color::srgb(0.9, 0.9, 0.9),
+                menu_background: Color::srgb(0.14, 0.15, 0.2),
+                menu_text: Color::srgb(0.9, 0.9, 0.9),
+                button_normal: Color::srgb(0.22, 0.24, 0.3),
+                button_hovered: Color::srgb(0.32, 0.35, 0.44),
+                button_pressed: Color::srgb(0.3, 0.55, 0.75),
+                button_hovered_pressed: Color::srgb(0.25, 0.45, 0.65),
+            },
+            ThemeKind::HighContrast => Theme {
+                kind,
+                background: Color::BLACK,
+                tile: Color::WHITE,
+                trap: Color::srgb(0.0, 0.0, 0.75),
+                decaying_trap: Color::srgb(0.4, 0.4, 1.0),
+                reinforced_trap: Color::srgb(0.0, 0.0, 0.35),
+                rock: Color::srgb(0.5, 0.5, 0.5),
+                mud: Color::srgb(0.6, 0.3, 0.0),
+                teleporter: Color::srgb(0.6, 0.0, 1.0),
+                one_way: Color::srgb(0.7, 1.0, 1.0),
+                exit: Color::srgb(1.0, 0.5, 0.0),
+                tile_hover: Color::srgb(1.0, 1.0, 0.0),
+                legal_move: Color::srgb(0.0, 1.0, 0.0),
+                revealed_path: Color::srgb(0.0, 1.0, 1.0),
+                invalid_tap: Color::srgb(1.0, 0.0, 0.0),
+                win: Color::srgb(0.0, 1.0, 1.0),
+                lose: Color::srgb(1.0, 0.0, 1.0),
+                hud_text: Color::WHITE,
+                menu_background: Color::BLACK,
+                menu_text: Color::WHITE,
+                button_normal: Color::srgb(0.0, 0.0, 0.75),
+                button_hovered: Color::srgb(0.0, 0.0, 1.0),
+                button_pressed: Color::srgb(0.8, 0.8, 0.0),
+                button_hovered_pressed: Color::srgb(1.0, 1.0, 0.0),
+            },
+            // Okabe-Ito colors: win and lose differ in hue along the blue-yellow axis and in lightness
+            ThemeKind::Deuteranopia => Theme {
+                kind,
+                background: Color::srgb(0.80, 0.84, 0.88),
+                tile: Color::WHITE,
+                trap: Color::Srgba(DARK_GRAY),
+                decaying_trap: Color::srgb(0.5, 0.6, 0.7),
+                reinforced_trap: Color::srgb(0.0, 0.2, 0.4),
+                rock: Color::srgb(0.2, 0.2, 0.2),
+                mud: Color::srgb(0.6, 0.45, 0.3),
+                teleporter: Color::srgb(0.8, 0.475, 0.655),
+                one_way: Color::srgb(0.85, 0.9, 0.95),
+                exit: Color::srgb(0.0, 0.62, 0.451),
+                tile_hover: Color::srgb(0.337, 0.706, 0.914),
+                legal_move: Color::srgb(0.941, 0.894, 0.259),
+                revealed_path: Color::srgb(0.337, 0.706, 0.914),
+                invalid_tap: Color::srgb(0.835, 0.369, 0.0),
+                win: Color::srgb(0.0, 0.447, 0.698),
+                lose: Color::srgb(0.902, 0.624, 0.0),
+                hud_text: Color::srgb(0.1, 0.1, 0.1),
+                menu_background: Color::srgb(0.0, 0.447, 0.698),
+                menu_text: Color::srgb(0.95, 0.95, 0.95),
+                button_normal: Color::srgb(0.15, 0.15, 0.15),
+                button_hovered: Color::srgb(0.25, 0.25, 0.25),
+                button_pressed: Color::srgb(0.902, 0.624, 0.0),
+                button_hovered_pressed: Color::srgb(0.8, 0.55, 0.0),
+            },
+            ThemeKind::Protanopia => Theme {
+                kind,
+                background: Color::srgb(0.80, 0.84, 0.88),
+                tile: Color::WHITE,
+                trap: Color::Srgba(DARK_GRAY),
+                decaying_trap: Color::srgb(0.5, 0.6, 0.7),
+                reinforced_trap: Color::srgb(0.0, 0.2, 0.4),
+                rock: Color::srgb(0.2, 0.2, 0.2),
+                mud: Color::srgb(0.6, 0.45, 0.3),
+                teleporter: Color::srgb(0.0, 0.447, 0.698),
+                one_way: Color::srgb(0.85, 0.9, 0.95),
+                exit: Color::srgb(0.0, 0.62, 0.451),
+                tile_hover: Color::srgb(0.8, 0.8, 0.8),
+                legal_move: Color::srgb(0.8, 0.475, 0.655),
+                revealed_path: Color::srgb(0.337, 0.706, 0.914),
+                invalid_tap: Color::srgb(0.941, 0.894, 0.259),
+                win: Color::srgb(0.337, 0.706, 0.914),
+                lose: Color::srgb(0.941, 0.894, 0.259),
+                hud_text: Color::srgb(0.1, 0.1, 0.1),
+                menu_background: Color::srgb(0.0, 0.447, 0.698),
+                menu_text: Color::srgb(0.95, 0.95, 0.95),
+                button_normal: Color::srgb(0.15, 0.15, 0.15),
+                button_hovered: Color::srgb(0.25, 0.25, 0.25),
+                button_pressed: Color::srgb(0.337, 0.706, 0.914),
+                button_hovered_pressed: Color::srgb(0.25, 0.6, 0.8),
+            },
+        }
+    }
+
+    /// Color of an open tile of the given kind
+    pub fn tile_color(&self, kind: Option<&TileKind>) -> Color {
+        match kind {
+            None => self.tile,
+            Some(TileKind::Rock) => self.rock,
+            Some(TileKind::Mud) => self.mud,
+            Some(TileKind::Teleporter { .. }) => self.teleporter,
+            Some(TileKind::OneWay { .. }) => self.one_way,
+            Some(TileKind::Exit) => self.exit,
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use bevy::{app::{Plugin, Startup, Update}, asset::{Assets, Handle}, audio::{AddAudioSource, AudioPlayer, AudioSink, AudioSinkPlayback, PlaybackSettings, Volume}, ecs::{component::Component, event::EventReader, query::With, resource::Resource, schedule::{common_conditions::{not, resource_changed}, IntoScheduleConfigs}, system::{Commands, Query, Res, ResMut}}, input::{keyboard::KeyCode, ButtonInput}};
+use crate::{game::{events::{LevelStarted, RustaceanEscaped, RustaceanMoved, RustaceanTrapped, TrapPlaced}, score::high_score_name_open}, menu::menu::MenuButtonPressed, settings::settings::{Settings, VolumeChannel}, sound::synth::{Chiptune, Note, Waveform}};
+
+// Note frequencies used by the effects and the music loop
+const C4: f32 = 261.63;
+const E4: f32 = 329.63;
+const G4: f32 = 392.00;
+const A4: f32 = 440.00;
+const C5: f32 = 523.25;
+const D5: f32 = 587.33;
+const E5: f32 = 659.25;
+const G5: f32 = 783.99;
+const C6: f32 = 1046.50;
+
+/// Plays sound effects in response to game events and loops the background music.
+///
+/// If there is no audio device, bevy only logs a warning and never creates the sinks,
+/// so every system here simply finds nothing to do and the game runs silently.
+pub struct SoundPlugin;
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut bevy::app::App) {
+
+        app.add_audio_source::<Chiptune>();
+
+        app.add_systems(Startup, (setup_sound_library, start_background_music).chain());
+
+        app.add_systems(
+            Update,
+            (
+                // an m in a high score name shouldnt mute the game
+                toggle_mute.run_if(not(high_score_name_open)),
+                update_music_volume.run_if(resource_changed::<Settings>),
+                (
+                    play_trap_placed,
+                    play_rustacean_hop,
+                    play_rustacean_escaped,
+                    play_rustacean_trapped,
+                    play_level_up,
+                    play_menu_click,
+                ),
+            ).chain()
+        );
+    }
+}
+
+#[derive(Component)]
+pub struct BackgroundMusic;
+
+/// Handles to every generated sound
+#[derive(Resource)]
+pub struct SoundLibrary {
+    pub trap_placed: Handle<Chiptune>,
+    pub rustacean_hop: Handle<Chiptune>,
+    pub rustacean_escaped: Handle<Chiptune>,
+    pub rustacean_trapped: Handle<Chiptune>,
+    pub level_up: Handle<Chiptune>,
+    pub menu_click: Handle<Chiptune>,
+    pub music: Handle<Chiptune>,
+}
+
+fn setup_sound_library(
+    mut commands: Commands,
+    mut chiptunes: ResMut<Assets<Chiptune>>,
+) {
+    let mut add = |waveform: Waveform, notes: &[Note]| chiptunes.add(Chiptune::new(waveform, notes));
+
+    let library = SoundLibrary {
+        trap_placed: add(Waveform::Square, &[Note::new(220.0, 0.05), Note::new(165.0, 0.07)]),
+        rustacean_hop: add(Waveform::Triangle, &[Note::new(520.0, 0.04), Note::new(780.0, 0.05)]),
+        rustacean_escaped: add(Waveform::Square, &[
+            Note::new(E5, 0.1), Note::new(C5, 0.1), Note::new(A4, 0.1), Note::new(E4, 0.25)
+        ]),
+        rustacean_trapped: add(Waveform::Square, &[
+            Note::new(C5, 0.1), Note::new(E5, 0.1), Note::new(G5, 0.1), Note::new(C6, 0.25)
+        ]),
+        level_up: add(Waveform::Triangle, &[
+            Note::new(G4, 0.08), Note::new(C5, 0.08), Note::new(E5, 0.08), Note::new(G5, 0.2)
+        ]),
+        menu_click: add(Waveform::Sine, &[Note::new(880.0, 0.03)]),
+        music: add(Waveform::Triangle, &[
+            Note::new(C4, 0.3), Note::new(E4, 0.3), Note::new(G4, 0.3), Note::new(E4, 0.3),
+            Note::new(A4, 0.3), Note::new(G4, 0.3), Note::new(E4, 0.6),
+            Note::new(D5, 0.3), Note::new(C5, 0.3), Note::new(A4, 0.3), Note::new(G4, 0.3),
+            Note::new(E4, 0.3), Note::new(G4, 0.3), Note::new(C4, 0.6),
+            Note::new(0.0, 0.6),
+        ]),
+    };
+
+    commands.insert_resource(library);
+}
+
+fn start_background_music(
+    mut commands: Commands,
+    library: Res<SoundLibrary>,
+    settings: Res<Settings>,
+) {
+    commands.spawn((
+        AudioPlayer(library.music.clone()),
+        PlaybackSettings::LOOP.with_volume(Volume::Linear(settings.effective_volume(VolumeChannel::Music))),
+        BackgroundMusic,
+    ));
+}
+
+fn update_music_volume(
+    settings: Res<Settings>,
+    mut music_q: Query<&mut AudioSink, With<BackgroundMusic>>,
+) {
+    for mut sink in music_q.iter_mut() {
+        sink.set_volume(Volume::Linear(settings.effective_volume(VolumeChannel::Music)));
+    }
+}
+
+fn toggle_mute(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<Settings>,
+) {
+    if keys.just_pressed(KeyCode::KeyM) {
+        settings.muted = !settings.muted;
+    }
+}
+
+fn play_effect(commands: &mut Commands, sound: &Handle<Chiptune>, settings: &Settings) {
+    let volume = settings.effective_volume(VolumeChannel::Effects);
+    if volume <= 0.0 {
+        return;
+    }
+    commands.spawn((
+        AudioPlayer(sound.clone()),
+        PlaybackSettings::DESPAWN.with_volume(Volume::Linear(volume)),
+    ));
+}
+
+fn play_trap_placed(
+    mut commands: Commands,
+    mut events: EventReader<TrapPlaced>,
+    library: Res<SoundLibrary>,
+    settings: Res<Settings>,
+) {
+    if events.read().count() > 0 {
+        play_effect(&mut commands, &library.trap_placed, &settings);
+    }
+}
+
+fn play_rustacean_hop(
+    mut commands: Commands,
+    mut events: EventReader<RustaceanMoved>,
+    library: Res<SoundLibrary>,
+    settings: Res<Settings>,
+) {
+    if events.read().count() > 0 {
+        play_effect(&mut commands, &library.rustacean_hop, &settings);
+    }
+}
+
+fn play_rustacean_escaped(
+    mut commands: Commands,
+    mut events: EventReader<RustaceanEscaped>,
+    library: Res<SoundLibrary>,
+    settings: Res<Settings>,
+) {
+    if events.read().count() > 0 {
+        play_effect(&mut commands, &library.rustacean_escaped, &settings);
+    }
+}
+
+fn play_rustacean_trapped(
+    mut commands: Commands,
+    mut events: EventReader<RustaceanTrapped>,
+    library: Res<SoundLibrary>,
+    settings: Res<Settings>,
+) {
+    if events.read().count() > 0 {
+        play_effect(&mut commands, &library.rustacean_trapped, &settings);
+    }
+}
+
+fn play_level_up(
+    mut commands: Commands,
+    mut events: EventReader<LevelStarted>,
+    library: Res<SoundLibrary>,
+    settings: Res<Settings>,
+) {
+    // the first level of a game is not a level up
+    if events.read().any(|event| event.level > 1) {
+        play_effect(&mut commands, &library.level_up, &settings);
+    }
+}
+
+fn play_menu_click(
+    mut commands: Commands,
+    mut events: EventReader<MenuButtonPressed>,
+    library: Res<SoundLibrary>,
+    settings: Res<Settings>,
+) {
+    if events.read().count() > 0 {
+        play_effect(&mut commands, &library.menu_click, &settings);
+    }
+}
@@ -0,0 +1,130 @@
+use std::{f32::consts::TAU, sync::Arc, time::Duration};
+use bevy::{asset::Asset, audio::{Decodable, Source}, reflect::TypePath};
+
+const SAMPLE_RATE: u32 = 44_100;
+/// Fade in and out at each note edge so notes dont click
+const EDGE_SECS: f32 = 0.005;
+/// Peak amplitude of the generated wave, leaves headroom for several overlapping sounds
+const AMPLITUDE: f32 = 0.3;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Waveform {
+    Sine,
+    Square,
+    Triangle,
+}
+
+impl Waveform {
+    /// Sample of the wave at `phase`, which runs from 0 to 1 over one period
+    fn sample(self, phase: f32) -> f32 {
+        match self {
+            Waveform::Sine => (phase * TAU).sin(),
+            Waveform::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+            Waveform::Triangle => 4.0 * (phase - (phase + 0.5).floor()).abs() - 1.0,
+        }
+    }
+}
+
+/// A single tone, a frequency of 0 is a rest
+#[derive(Clone, Copy, Debug)]
+pub struct Note {
+    pub freq: f32,
+    pub secs: f32,
+}
+
+impl Note {
+    pub const fn new(freq: f32, secs: f32) -> Self {
+        Note { freq, secs }
+    }
+}
+
+/// Procedurally generated sound made of a sequence of notes,
+/// which means the game ships its sounds without any audio files
+#[derive(Asset, TypePath, Clone)]
+pub struct Chiptune {
+    pub notes: Arc<[Note]>,
+    pub waveform: Waveform,
+}
+
+impl Chiptune {
+    pub fn new(waveform: Waveform, notes: &[Note]) -> Self {
+        Chiptune {
+            notes: notes.into(),
+            waveform,
+        }
+    }
+}
+
+pub struct ChiptuneDecoder {
+    notes: Arc<[Note]>,
+    waveform: Waveform,
+    note_index: usize,
+    sample_in_note: u32,
+    phase: f32,
+}
+
+impl Iterator for ChiptuneDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let note = *self.notes.get(self.note_index)?;
+            let note_samples = (note.secs * SAMPLE_RATE as f32) as u32;
+
+            if self.sample_in_note >= note_samples {
+                self.note_index += 1;
+                self.sample_in_note = 0;
+                continue;
+            }
+
+            let elapsed = self.sample_in_note as f32 / SAMPLE_RATE as f32;
+            let remaining = note.secs - elapsed;
+            let envelope = (elapsed / EDGE_SECS).min(remaining / EDGE_SECS).min(1.0);
+
+            self.sample_in_note += 1;
+
+            if note.freq <= 0.0 {
+                return Some(0.0);
+            }
+
+            let sample = self.waveform.sample(self.phase) * AMPLITUDE * envelope;
+            self.phase = (self.phase + note.freq / SAMPLE_RATE as f32).fract();
+
+            return Some(sample);
+        }
+    }
+}
+
+impl Source for ChiptuneDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        let secs: f32 = self.notes.iter().map(|note| note.secs).sum();
+        Some(Duration::from_secs_f32(secs))
+    }
+}
+
+impl Decodable for Chiptune {
+    type DecoderItem = <ChiptuneDecoder as Iterator>::Item;
+    type Decoder = ChiptuneDecoder;
+
+    fn decoder(&self) -> Self::Decoder {
+        ChiptuneDecoder {
+            notes: self.notes.clone(),
+            waveform: self.waveform,
+            note_index: 0,
+            sample_in_note: 0,
+            phase: 0.0,
+        }
+    }
+}
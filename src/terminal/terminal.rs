@@ -0,0 +1,352 @@
+use std::{io::{BufRead, Write}, path::PathBuf};
+
+use crate::{game::{difficulty::{load_difficulty_profiles, DifficultyKind}, endless::generated_level_rules, game::{load_total_game_stats, write_total_game_stats}, headless::HeadlessLevel, rules::{record_game_started, record_reached_level, record_rustaceans, record_stars, record_tap}, strategy::StrategyRegistry, tiles::{HexDirection, TileKind}}, settings::{launch::{parse_difficulty, parse_grid, parse_level, parse_number}, settings::load_settings}, utils::{hexgrid_utils::GridSize, rng_utils::{random_seed, seed_game_rng}}};
+
+pub const TERMINAL_USAGE: &str = "\
+Usage: terminal [OPTIONS]
+
+Plays a classic game in the terminal, with the settings and stats of the windowed game.
+Every tap places a regular trap, the limited trap kinds and the player abilities are only in the windowed game.
+
+Options:
+  --seed <N>             seed every random pick, the same seed lays out the same boards
+  --level <N>            level the game starts on
+  --grid <COLSxROWS>     board size of every level, e.g. 9x14
+  --difficulty <NAME>    easy, normal, hard or custom, instead of the one in the settings
+  --ai <NAME>            rustacean strategy on every level, the difficulty's AI tier by default
+  --data-dir <PATH>      folder for settings and stats, ./configs by default
+  --ascii                draw the board with the characters of the puzzle files only
+  -h, --help             print this help";
+
+const HELP: &str = "\
+Type the column and row of a tile to trap it, like `3 5`.
+Or move the cursor with w a s d, several at once work too, and press Enter on an empty line to trap the tile under it.
+q quits, ? shows this again.";
+
+/// Options the terminal frontend was started with
+#[derive(Clone, Debug, Default)]
+pub struct TerminalOptions {
+    pub seed: Option<u64>,
+    pub start_level: Option<u32>,
+    pub grid_size: Option<GridSize>,
+    pub difficulty: Option<DifficultyKind>,
+    /// Name of the rustacean strategy every level is played against
+    pub rustacean: Option<String>,
+    pub data_dir: Option<PathBuf>,
+    /// Only the characters puzzle files use, for terminals without unicode
+    pub ascii: bool,
+}
+
+impl TerminalOptions {
+    /// Reads the options from the command line arguments, without the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = TerminalOptions::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+            match arg.as_str() {
+                "--seed" => options.seed = Some(parse_number(&arg, &value()?)?),
+                "--level" => options.start_level = Some(parse_level(&arg, &value()?)?),
+                "--grid" => options.grid_size = Some(parse_grid(&arg, &value()?)?),
+                "--difficulty" => options.difficulty = Some(parse_difficulty(&value()?)?),
+                "--ai" => options.rustacean = Some(value()?.to_lowercase()),
+                "--data-dir" => options.data_dir = Some(PathBuf::from(value()?)),
+                "--ascii" => options.ascii = true,
+                _ => return Err(format!("unknown option {arg}")),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// What a line typed in asks for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Command {
+    Tap((i32, i32)),
+    /// Moves the cursor by (columns, rows)
+    Move(i32, i32),
+    TapCursor,
+    Help,
+    Quit,
+}
+
+fn parse_command(line: &str) -> Result<Command, String> {
+    let line = line.trim();
+    match line {
+        "" => return Ok(Command::TapCursor),
+        "q" | "quit" => return Ok(Command::Quit),
+        "?" | "h" | "help" => return Ok(Command::Help),
+        _ => {}
+    }
+
+    let numbers: Vec<&str> = line.split([' ', ',']).filter(|part| !part.is_empty()).collect();
+    if let [x, y] = numbers.as_slice()
+        && let (Ok(x), Ok(y)) = (x.parse(), y.parse())
+    {
+        return Ok(Command::Tap((x, y)));
+    }
+
+    line.chars().try_fold((0, 0), |(x, y), key| match key.to_ascii_lowercase() {
+        'w' => Ok((x, y + 1)),
+        's' => Ok((x, y - 1)),
+        'a' => Ok((x - 1, y)),
+        'd' => Ok((x + 1, y)),
+        _ => Err(format!("unknown command `{line}`, ? shows what can be typed")),
+    }).map(|(x, y)| Command::Move(x, y))
+}
+
+/// The board as text, odd columns half a row lower like on screen, the cursor tile in brackets
+fn render_board(level: &HeadlessLevel, cursor: (i32, i32), ascii: bool) -> String {
+    let grid_size = level.grid_size();
+
+    // both ends of a teleporter pair share a letter
+    let mut teleporters: Vec<(i32, i32)> = (0..grid_size.cols)
+        .flat_map(|x| (0..grid_size.rows).map(move |y| (x, y)))
+        .filter_map(|pos| match level.tiles.kind(pos) {
+            Some(TileKind::Teleporter { pair }) => Some(pos.min(pair)),
+            _ => None,
+        })
+        .collect();
+    teleporters.sort_unstable();
+    teleporters.dedup();
+
+    let symbol = |pos: (i32, i32)| -> char {
+        if level.rustaceans.iter().any(|rustacean| rustacean.pos == pos) {
+            return 'R';
+        }
+        if level.traps.contains(&pos) {
+            return if ascii { '#' } else { '●' };
+        }
+        match level.tiles.kind(pos) {
+            None => if ascii { '.' } else { '·' },
+            Some(TileKind::Rock) => if ascii { 'O' } else { '▲' },
+            Some(TileKind::Mud) => '~',
+            Some(TileKind::Exit) => if ascii { 'E' } else { '◎' },
+            Some(TileKind::OneWay { direction }) if ascii => direction.numpad(),
+            Some(TileKind::OneWay { direction }) => match direction {
+                HexDirection::Up => '↑',
+                HexDirection::UpRight => '↗',
+                HexDirection::DownRight => '↘',
+                HexDirection::Down => '↓',
+                HexDirection::DownLeft => '↙',
+                HexDirection::UpLeft => '↖',
+            },
+            Some(TileKind::Teleporter { pair }) => {
+                let index = teleporters.iter().position(|first| *first == pos.min(pair)).unwrap_or(0);
+                char::from(b'a' + (index % 26) as u8)
+            }
+        }
+    };
+    let cell = |pos: (i32, i32)| match pos == cursor {
+        true => format!("[{}]", symbol(pos)),
+        false => format!(" {} ", symbol(pos)),
+    };
+
+    let header: String = (0..grid_size.cols).map(|x| format!("{x:^3}")).collect();
+    let mut text = format!("   {}\n", header.trim_end());
+
+    // top row first, every row takes two lines since odd columns sit half a tile lower
+    for y in (0..grid_size.rows).rev() {
+        for parity in [0, 1] {
+            let mut line = match parity {
+                0 => format!("{y:>3}"),
+                _ => String::from("   "),
+            };
+            for x in 0..grid_size.cols {
+                match x % 2 == parity {
+                    true => line.push_str(&cell((x, y))),
+                    false => line.push_str("   "),
+                }
+            }
+            text.push_str(line.trim_end());
+            text.push('\n');
+        }
+    }
+
+    text
+}
+
+/// Plays a classic game reading commands from `input`, until a level is lost, `q` is typed or the input ends
+pub fn run_terminal(options: &TerminalOptions, strategies: &StrategyRegistry, mut input: impl BufRead, mut output: impl Write) -> std::io::Result<()> {
+    let forced_rustacean = match &options.rustacean {
+        Some(name) => Some(strategies.rustacean(name).ok_or_else(|| std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("unknown rustacean AI {name}, pick one of {}", strategies.rustacean_names().join(", ")),
+        ))?),
+        None => None,
+    };
+    let settings = load_settings();
+    let profiles = load_difficulty_profiles();
+    let difficulty = options.difficulty.unwrap_or(settings.difficulty);
+    let profile = profiles.get(difficulty);
+
+    let mut stats = load_total_game_stats();
+    record_game_started(&mut stats, difficulty);
+
+    seed_game_rng(options.seed.unwrap_or_else(random_seed));
+    let mut level_number = options.start_level.unwrap_or(1);
+    writeln!(output, "{HELP}\n{}\n", legend(options.ascii))?;
+
+    loop {
+        let rules = generated_level_rules(profile, level_number, options.grid_size, settings.rustaceans, settings.win_condition);
+        let rustacean = forced_rustacean.unwrap_or_else(|| strategies.rustacean_for(rules.rustacean_ai));
+        let mut level = HeadlessLevel::generate(rules);
+        let par = level.par();
+        let grid_size = level.grid_size();
+        let mut cursor = (grid_size.cols / 2, grid_size.rows / 2);
+        let mut message = String::new();
+
+        let won = loop {
+            let free = level.trapped().iter().enumerate().filter(|(index, trapped)| !**trapped && !level.escaped(*index)).count();
+            writeln!(output, "Level {level_number}   taps {} / par {}   rustaceans free {free}", level.taps, par.0)?;
+            write!(output, "{}", render_board(&level, cursor, options.ascii))?;
+            if !message.is_empty() {
+                writeln!(output, "{}", std::mem::take(&mut message))?;
+            }
+            write!(output, "> ")?;
+            output.flush()?;
+
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(output)?;
+                write_total_game_stats(&stats);
+                return Ok(());
+            }
+
+            let tap = match parse_command(&line) {
+                Ok(Command::Tap(pos)) => pos,
+                Ok(Command::TapCursor) => cursor,
+                Ok(Command::Move(x, y)) => {
+                    cursor = ((cursor.0 + x).clamp(0, grid_size.cols - 1), (cursor.1 + y).clamp(0, grid_size.rows - 1));
+                    continue;
+                }
+                Ok(Command::Help) => {
+                    message = String::from(HELP);
+                    continue;
+                }
+                Ok(Command::Quit) => {
+                    write_total_game_stats(&stats);
+                    return Ok(());
+                }
+                Err(err) => {
+                    message = err;
+                    continue;
+                }
+            };
+
+            if !level.tap(tap) {
+                message = format!("Cant place a trap on ({}, {})", tap.0, tap.1);
+                continue;
+            }
+            cursor = tap;
+            record_tap(&mut stats, difficulty);
+
+            let escaped_before = level.escaped_count();
+            level.rustacean_turn(|current, board, jumps| rustacean.choose_move(current.pos, board, jumps));
+            let escaped = (level.escaped_count() - escaped_before) as u64;
+            record_rustaceans(&mut stats, difficulty, 0, escaped);
+
+            if let Some(won) = level.outcome() {
+                break won;
+            }
+        };
+
+        write!(output, "{}", render_board(&level, cursor, options.ascii))?;
+        if !won {
+            writeln!(output, "A rustacean got away after {} taps, the game is over on level {level_number}", level.taps)?;
+            write_total_game_stats(&stats);
+            return Ok(());
+        }
+
+        let stars = par.stars(level.taps);
+        writeln!(output, "Level {level_number} won in {} taps, par {}: {}\n", level.taps, par.0, star_text(stars, options.ascii))?;
+        let trapped = level.trapped().iter().filter(|trapped| **trapped).count() as u64;
+        record_rustaceans(&mut stats, difficulty, trapped, 0);
+        record_stars(&mut stats, difficulty, stars);
+        level_number += 1;
+        record_reached_level(&mut stats, difficulty, level_number);
+        write_total_game_stats(&stats);
+    }
+}
+
+fn legend(ascii: bool) -> &'static str {
+    match ascii {
+        true => "R rustacean  . open  # trap  O rock  ~ mud  E exit  1-9 one way, numpad direction  a-z teleporter pair",
+        false => "R rustacean  · open  ● trap  ▲ rock  ~ mud  ◎ exit  ↑↗↘↓↙↖ one way  a-z teleporter pair",
+    }
+}
+
+fn star_text(stars: u8, ascii: bool) -> String {
+    match ascii {
+        true => format!("{stars} of 3 stars"),
+        false => (0..3).map(|star| if star < stars { '★' } else { '☆' }).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{game::{pathfinding::{BoardView, RustaceanMove}, strategy::RustaceanStrategy}, settings::launch::use_test_data_dir, utils::hexgrid_utils::GridTilePos};
+    use super::*;
+
+    /// Never leaves its tile, so a script knows where to put the traps
+    struct SittingRustacean;
+
+    impl RustaceanStrategy for SittingRustacean {
+        fn name(&self) -> &str {
+            "sitting"
+        }
+
+        fn choose_move(&self, _start: (i32, i32), _board: &BoardView, _jumps: &[(RustaceanMove, u32)]) -> RustaceanMove {
+            RustaceanMove::Trapped
+        }
+    }
+
+    fn play(script: &str) -> String {
+        let mut strategies = StrategyRegistry::default();
+        strategies.register_rustacean(SittingRustacean);
+        let options = TerminalOptions {
+            seed: Some(3),
+            grid_size: Some(GridSize::new(7, 7)),
+            difficulty: Some(DifficultyKind::Easy),
+            rustacean: Some(String::from("sitting")),
+            ascii: true,
+            ..TerminalOptions::default()
+        };
+        let mut output = Vec::new();
+        run_terminal(&options, &strategies, Cursor::new(script), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn a_script_that_traps_the_rustacean_wins_the_level() {
+        use_test_data_dir();
+        let trapped_before = load_total_game_stats().for_difficulty(DifficultyKind::Easy).tigers_trapped;
+
+        // the rustacean starts in the middle of the board, some of its neighbors may already hold a trap or rock
+        let script: String = GridTilePos::new(3, 3).get_neighbor_pos().iter()
+            .map(|(x, y)| format!("{x} {y}\n"))
+            .collect();
+        let output = play(&script);
+
+        assert!(output.contains("Level 1 won in"), "{output}");
+        assert!(output.contains("Level 2   taps 0"), "{output}");
+        let mut stats = load_total_game_stats();
+        let easy = stats.for_difficulty(DifficultyKind::Easy);
+        assert_eq!(easy.tigers_trapped, trapped_before + 1);
+        assert!(easy.record_level >= 2);
+    }
+
+    #[test]
+    fn a_refused_tap_and_a_quit_end_the_game_without_a_result() {
+        use_test_data_dir();
+        let output = play("9 9\nq\nthe game is over before this line\n");
+
+        assert!(output.contains("Cant place a trap on (9, 9)"), "{output}");
+        assert!(!output.contains("won in"), "{output}");
+        assert!(!output.contains("got away"), "{output}");
+    }
+}
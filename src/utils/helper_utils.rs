@@ -1,4 +1,10 @@
-use bevy::{core_pipeline::core_2d::Camera2d, ecs::{ event::EventReader, query::With, system::{Res, ResMut, Single}}, input::{keyboard::KeyCode, mouse::MouseWheel, ButtonInput}, log::error, render::camera::Projection, ui::UiScale, window::{MonitorSelection, Window, WindowMode}};
+use bevy::{core_pipeline::core_2d::Camera2d, ecs::{ event::EventReader, query::{Added, With}, system::{Query, Res, ResMut, Single}}, input::{keyboard::KeyCode, mouse::MouseWheel, ButtonInput}, log::error, render::camera::Projection, ui::UiScale, window::{MonitorSelection, Window, WindowMode, WindowResized}};
+use crate::utils::hexgrid_utils::{GridSize, HextileF2FSize};
+
+/// World height the camera shows at the least, the starting board is sized for it
+const MIN_VISIBLE_HEIGHT: f32 = 1080.0;
+/// Room left around the board for the HUD
+const GRID_FIT_MARGIN: f32 = 1.15;
 
 pub fn scroll_zoom_camera_system(
         mut evr_scroll: EventReader<MouseWheel>,
@@ -59,4 +65,34 @@ pub fn toggle_resolution(
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Zooms the camera out far enough to show the whole board whenever a board is spawned or the window resizes
+pub fn fit_camera_to_grid(
+    mut resize_events: EventReader<WindowResized>,
+    new_grid_q: Query<(), Added<GridSize>>,
+    grid_q: Query<(&GridSize, &HextileF2FSize)>,
+    window: Single<&Window>,
+    mut query_camera: Single<&mut Projection, With<Camera2d>>,
+) {
+    let resized = resize_events.read().count() > 0;
+    if !resized && new_grid_q.is_empty() {
+        return;
+    }
+    let Some((grid_size, f2f_size)) = grid_q.iter().next() else {
+        return;
+    };
+
+    // columns overlap by a quarter tile and odd columns sit half a tile lower
+    let grid_width = (grid_size.cols - 1) as f32 * f2f_size.0 * 0.75 + f2f_size.0;
+    let grid_height = grid_size.rows as f32 * f2f_size.to_height() + f2f_size.to_height() / 2.;
+
+    let scale = (MIN_VISIBLE_HEIGHT / window.height())
+        .max(grid_height * GRID_FIT_MARGIN / window.height())
+        .max(grid_width * GRID_FIT_MARGIN / window.width());
+
+    match query_camera.as_mut() {
+        Projection::Orthographic(ortho) => ortho.scale = scale,
+        _ => error!("Camera Error: Projection is not Orthograpic as should be by Default"),
+    }
+}
@@ -1,8 +1,8 @@
-use bevy::{asset::{AssetServer, Handle}, ecs::{component::Component, hierarchy::ChildOf, system::{Commands, Res}}, image::Image, math::{Vec2, Vec3}, picking::Pickable, render::view::Visibility, log::error, sprite::Sprite, transform::components::Transform};
+use bevy::{asset::{AssetServer, Handle}, ecs::{component::{Component, HookContext}, entity::Entity, hierarchy::ChildOf, system::{Commands, Res}, world::DeferredWorld}, image::Image, math::{Vec2, Vec3}, picking::Pickable, render::view::Visibility, log::error, sprite::Sprite, transform::components::Transform};
 
 // GRIDS
 /// Size of the actual grid in game in columns and rows
-#[derive(Component, Default, Clone, Copy, Debug)]
+#[derive(Component, Default, Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct GridSize{
     pub cols: i32,
     pub rows: i32
@@ -20,11 +20,63 @@ impl GridSize {
     }
 }
 
+/// Tile entities of a grid in [`GridTilePos::to_index`] order, so a tile is found by its coordinates without a search.
+///
+/// The tiles add and remove themselves through the hooks of [`GridTilePos`], so it follows them as they spawn and despawn.
+#[derive(Component, Clone, Debug, Default)]
+pub struct GridTiles {
+    grid_size: GridSize,
+    tiles: Vec<Option<Entity>>,
+}
+
+impl GridTiles {
+    pub fn new(grid_size: GridSize) -> Self {
+        GridTiles { grid_size, tiles: vec![None; grid_size.count()] }
+    }
+
+    fn index(&self, pos: (i32, i32)) -> Option<usize> {
+        let in_bounds = pos.0 >= 0 && pos.0 < self.grid_size.cols && pos.1 >= 0 && pos.1 < self.grid_size.rows;
+        in_bounds.then(|| GridTilePos::new(pos.0, pos.1).to_index(&self.grid_size))
+    }
+
+    /// The tile entity on `pos`, None off the grid
+    pub fn get(&self, pos: (i32, i32)) -> Option<Entity> {
+        self.index(pos).and_then(|index| self.tiles[index])
+    }
+}
+
+/// Puts a tile into the index of the grid it is a child of
+fn index_grid_tile(mut world: DeferredWorld, context: HookContext) {
+    let (Some(child_of), Some(pos)) = (world.get::<ChildOf>(context.entity), world.get::<GridTilePos>(context.entity)) else {
+        return;
+    };
+    let (grid, pos) = (child_of.parent(), (pos.x, pos.y));
+    if let Some(mut grid_tiles) = world.get_mut::<GridTiles>(grid)
+        && let Some(index) = grid_tiles.index(pos)
+    {
+        grid_tiles.tiles[index] = Some(context.entity);
+    }
+}
+
+/// Takes a tile out of the index of its grid again, unless the grid is already gone
+fn unindex_grid_tile(mut world: DeferredWorld, context: HookContext) {
+    let (Some(child_of), Some(pos)) = (world.get::<ChildOf>(context.entity), world.get::<GridTilePos>(context.entity)) else {
+        return;
+    };
+    let (grid, pos) = (child_of.parent(), (pos.x, pos.y));
+    if let Some(mut grid_tiles) = world.get_mut::<GridTiles>(grid)
+        && let Some(index) = grid_tiles.index(pos)
+        && grid_tiles.tiles[index] == Some(context.entity)
+    {
+        grid_tiles.tiles[index] = None;
+    }
+}
+
 #[derive(Component, Copy, Clone)]
 pub struct HextileF2FSize(pub f32);
 
 impl HextileF2FSize {
-    pub fn to_height(&self) -> f32 {
+    pub fn to_height(self) -> f32 {
         self.0 * 0.866
     }
 }
@@ -46,6 +98,7 @@ pub enum HexGridOrientation {
 
 // GRIDTILES
 #[derive(Component, Default, Clone, Copy, Debug)]
+#[component(on_insert = index_grid_tile, on_replace = unindex_grid_tile)]
 pub struct GridTilePos {
     pub x: i32,
     pub y: i32
@@ -57,19 +110,21 @@ impl GridTilePos {
     }
     /// Converts a tile position (2D) into an index in a flattened vector (1D), assuming the
     /// tile position lies in a tilemap of the specified size.
-    pub fn to_index(&self, grid_size: &GridSize) -> usize {
+    pub fn to_index(self, grid_size: &GridSize) -> usize {
         ((self.y * grid_size.cols) + self.x) as usize
     }
 
+    /// The tile position at an index of a flattened vector, the reverse of [`GridTilePos::to_index`]
+    pub fn from_index(index: usize, grid_size: &GridSize) -> Self {
+        let index = index as i32;
+        Self { x: index % grid_size.cols, y: index / grid_size.cols }
+    }
+
     pub fn is_border(&self, grid_size: &GridSize) -> bool {
-        if self.x == 0 
+        self.x == 0 
         || self.y == 0
         || self.x == (grid_size.cols - 1)
-        || self.y == (grid_size.rows - 1) {
-            true
-        } else {
-            false
-        }
+        || self.y == (grid_size.rows - 1)
     }
     
     pub fn get_neighbor_pos(&self) -> [(i32, i32); 6] {
@@ -133,7 +188,7 @@ impl GridTilePos {
                     }
                 );
                 
-                return Vec2::new(x,y);
+                Vec2::new(x,y)
             },
             HexGridOrientation::Horizontal => {
                 error!("Error: not functional yet, fucking wait");
@@ -201,53 +256,69 @@ pub fn get_startup_hexgrid(
         mut commands: Commands,
         asset_server: Res<AssetServer>
     | {
-        let position = Transform::from_translation(position);
+        spawn_hexgrid(&mut commands, &asset_server, position, size, orientation, hextile_f2f_size, default_texture);
+    }
+}
 
-        let grid_entity = commands.spawn((
-            position,
-            Visibility::default(),
-            size,
-            hextile_f2f_size,
-            orientation,
-        ))
-        .id();
+/// Spawns a grid entity with all of its tiles as children and returns the grid entity
+pub fn spawn_hexgrid(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    position: Vec3,
+    size: GridSize,
+    orientation: HexGridOrientation,
+    hextile_f2f_size: HextileF2FSize,
+    default_texture: Option<&str>,
+) -> Entity {
+    let position = Transform::from_translation(position);
 
+    let grid_entity = commands.spawn((
+        position,
+        Visibility::default(),
+        size,
+        GridTiles::new(size),
+        hextile_f2f_size,
+        orientation,
+    ))
+    .id();
 
-        let mut texture = Handle::default();
 
-        if let Some(default_texture) = default_texture {
-            texture = asset_server.load(default_texture);
-        }
+    let mut texture = Handle::default();
 
-        for x in 0..size.cols {
-            for y in 0..size.rows {
-                let tile_pos = GridTilePos::new(x, y);
-                let relative_transform = tile_pos
-                    .coord_to_world(&size, &hextile_f2f_size, &orientation)
-                    .extend(0.);
-
-                let mut tile_entity_commands = commands.spawn( (
-                        tile_pos,
-                        ChildOf(grid_entity),
-                        Transform::from_translation(relative_transform),
-                        Pickable::default()
-                    ));
-
-                if let Some(_) = default_texture {
-                    tile_entity_commands.insert(
-                        Sprite{
-                            custom_size: Some(Vec2::new(
-                                hextile_f2f_size.0,
-                                hextile_f2f_size.to_height()
-                            )),
-                            image: texture.clone(),
-                            ..Default::default()
-                        }
-                    );
-                };
-            }
+    if let Some(default_texture) = default_texture {
+        texture = asset_server.load(default_texture);
+    }
+
+    for x in 0..size.cols {
+        for y in 0..size.rows {
+            let tile_pos = GridTilePos::new(x, y);
+            let relative_transform = tile_pos
+                .coord_to_world(&size, &hextile_f2f_size, &orientation)
+                .extend(0.);
+
+            let mut tile_entity_commands = commands.spawn( (
+                    tile_pos,
+                    ChildOf(grid_entity),
+                    Transform::from_translation(relative_transform),
+                    Pickable::default()
+                ));
+
+            if default_texture.is_some() {
+                tile_entity_commands.insert(
+                    Sprite{
+                        custom_size: Some(Vec2::new(
+                            hextile_f2f_size.0,
+                            hextile_f2f_size.to_height()
+                        )),
+                        image: texture.clone(),
+                        ..Default::default()
+                    }
+                );
+            };
         }
     }
+
+    grid_entity
 }
 
 /// Returns neighboring coordinates of this tile on a horizontal hexgrid
@@ -1,2 +1,3 @@
 pub mod hexgrid_utils;
-pub mod helper_utils;
\ No newline at end of file
+pub mod helper_utils;
+pub mod rng_utils;
\ No newline at end of file
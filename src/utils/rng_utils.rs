@@ -0,0 +1,31 @@
+use std::sync::{LazyLock, Mutex, MutexGuard, PoisonError};
+use rand::{distr::uniform::{SampleRange, SampleUniform}, rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+/// Every random pick of the game goes through this generator, so the same seed lays out the same boards
+static GAME_RNG: LazyLock<Mutex<StdRng>> = LazyLock::new(|| Mutex::new(StdRng::from_os_rng()));
+
+fn game_rng() -> MutexGuard<'static, StdRng> {
+    GAME_RNG.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Restarts the game's random picks from `seed`
+pub fn seed_game_rng(seed: u64) {
+    *game_rng() = StdRng::seed_from_u64(seed);
+}
+
+/// A seed for a game that wasnt given one
+pub fn random_seed() -> u64 {
+    rand::random()
+}
+
+pub fn random_range<T, R>(range: R) -> T
+where
+    T: SampleUniform,
+    R: SampleRange<T>,
+{
+    game_rng().random_range(range)
+}
+
+pub fn shuffle<T>(items: &mut [T]) {
+    items.shuffle(&mut *game_rng());
+}